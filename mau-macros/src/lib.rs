@@ -0,0 +1,6423 @@
+//! # Mau 过程宏
+//! 
+//! `mau` 中各个宏的实现，请通过 `mau` 使用（生成的代码引用 `mau` 中的 `Memo` trait 等类型）。
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    Expr, Token, Type, Ident, Pat, PatIdent, FnArg,
+    parse::Parse, parse::ParseStream,
+};
+use std::collections::HashSet;
+
+// 辅助函数：将下划线命名转为大驼峰命名
+fn to_upper_camel_case(s: &str) -> String {
+    s.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                None => String::new(),
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+            }
+        })
+        .collect()
+}
+
+// ref 模式专用：生成自定义键类型的代码
+// RefKey<T>: 统一处理所有 &T 类型
+//   - addr: 地址，用于快速比较
+//   - len: 长度，区分相同地址不同长度的切片（关键！）
+//   - content: T 的克隆，用于慢速比较（不同地址但内容相同）
+// 为每个函数生成唯一的 RefKey 类型，避免冲突
+fn generate_ref_key_struct(fn_name: &Ident) -> proc_macro2::TokenStream {
+    let ref_key_name = Ident::new(
+        &format!("RefKey{}", to_upper_camel_case(&fn_name.to_string())),
+        fn_name.span()
+    );
+    quote! {
+        #[derive(Clone)]
+        struct #ref_key_name<T> {
+            addr: usize,
+            len: usize,
+            content: T,
+        }
+
+        impl<T: PartialEq> PartialEq for #ref_key_name<T> {
+            fn eq(&self, other: &Self) -> bool {
+                // 先比地址和长度（快速路径）
+                // 对于切片，必须同时比较地址和长度！
+                if self.addr == other.addr && self.len == other.len {
+                    return true;
+                }
+                // 地址或长度不同，比较内容（慢速路径）
+                self.content == other.content
+            }
+        }
+
+        impl<T: Eq> Eq for #ref_key_name<T> {}
+
+        impl<T: std::hash::Hash> std::hash::Hash for #ref_key_name<T> {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                // 只基于内容 hash，内容相同进入同一个 HashMap 桶
+                self.content.hash(state);
+            }
+        }
+    }
+}
+
+// 辅助函数：生成空迭代器时返回边界值的代码
+fn generate_empty_handler(operation: &str) -> proc_macro2::TokenStream {
+    match operation {
+        "min" => quote! {
+            match result {
+                Some(v) => v,
+                None => {
+                    // 只有空迭代器才会执行到这里
+                    // 运行时类型检查，返回对应类型的 MAX 值
+                    use ::std::any::TypeId;
+                    
+                    fn get_max_value<T: 'static + Copy>() -> T {
+                        let tid = TypeId::of::<T>();
+                        
+                        macro_rules! try_return {
+                            ($t:ty) => {
+                                if tid == TypeId::of::<$t>() {
+                                    let val = <$t>::MAX;
+                                    return unsafe { ::std::mem::transmute_copy(&val) };
+                                }
+                            };
+                        }
+                        
+                        try_return!(i8);
+                        try_return!(i16);
+                        try_return!(i32);
+                        try_return!(i64);
+                        try_return!(i128);
+                        try_return!(isize);
+                        try_return!(u8);
+                        try_return!(u16);
+                        try_return!(u32);
+                        try_return!(u64);
+                        try_return!(u128);
+                        try_return!(usize);
+                        try_return!(f32);
+                        try_return!(f64);
+                        
+                        if tid == TypeId::of::<char>() {
+                            let val = '\u{10FFFF}';
+                            return unsafe { ::std::mem::transmute_copy(&val) };
+                        }
+                        
+                        panic!("min! macro: type does not have a MAX value, empty iterator not supported")
+                    }
+                    
+                    get_max_value()
+                }
+            }
+        },
+        "max" => quote! {
+            match result {
+                Some(v) => v,
+                None => {
+                    // 只有空迭代器才会执行到这里
+                    // 运行时类型检查，返回对应类型的 MIN 值
+                    use ::std::any::TypeId;
+                    
+                    fn get_min_value<T: 'static + Copy>() -> T {
+                        let tid = TypeId::of::<T>();
+                        
+                        macro_rules! try_return {
+                            ($t:ty) => {
+                                if tid == TypeId::of::<$t>() {
+                                    let val = <$t>::MIN;
+                                    return unsafe { ::std::mem::transmute_copy(&val) };
+                                }
+                            };
+                        }
+                        
+                        try_return!(i8);
+                        try_return!(i16);
+                        try_return!(i32);
+                        try_return!(i64);
+                        try_return!(i128);
+                        try_return!(isize);
+                        try_return!(u8);
+                        try_return!(u16);
+                        try_return!(u32);
+                        try_return!(u64);
+                        try_return!(u128);
+                        try_return!(usize);
+                        try_return!(f32);
+                        try_return!(f64);
+                        
+                        if tid == TypeId::of::<char>() {
+                            let val = '\0';
+                            return unsafe { ::std::mem::transmute_copy(&val) };
+                        }
+                        
+                        panic!("max! macro: type does not have a MIN value, empty iterator not supported")
+                    }
+                    
+                    get_min_value()
+                }
+            }
+        },
+        "sum" => quote! {
+            match result {
+                Some(v) => v,
+                None => {
+                    // 只有空迭代器才会执行到这里
+                    // 运行时类型检查，返回对应类型的零值
+                    use ::std::any::TypeId;
+                    
+                    fn get_zero_value<T: 'static + Copy>() -> T {
+                        let tid = TypeId::of::<T>();
+                        
+                        // 对于所有数值类型，零值都是 0
+                        macro_rules! try_return_zero {
+                            ($t:ty) => {
+                                if tid == TypeId::of::<$t>() {
+                                    let val: $t = 0 as $t;
+                                    return unsafe { ::std::mem::transmute_copy(&val) };
+                                }
+                            };
+                        }
+                        
+                        try_return_zero!(i8);
+                        try_return_zero!(i16);
+                        try_return_zero!(i32);
+                        try_return_zero!(i64);
+                        try_return_zero!(i128);
+                        try_return_zero!(isize);
+                        try_return_zero!(u8);
+                        try_return_zero!(u16);
+                        try_return_zero!(u32);
+                        try_return_zero!(u64);
+                        try_return_zero!(u128);
+                        try_return_zero!(usize);
+                        try_return_zero!(f32);
+                        try_return_zero!(f64);
+                        
+                        panic!("sum! macro: type does not support zero value, empty iterator not supported")
+                    }
+                    
+                    get_zero_value()
+                }
+            }
+        },
+        _ => quote! {
+            result.expect("Iterator cannot be empty")
+        }
+    }
+}
+
+// 辅助函数：生成 ptr 模式的键（地址 + 长度）
+fn generate_ptr_mode_key(
+    arg: &Ident,
+    ty: &Type,
+    key_types: &mut Vec<proc_macro2::TokenStream>,
+    key_exprs: &mut Vec<proc_macro2::TokenStream>,
+) {
+    if let Type::Reference(ty_ref) = ty {
+        if let Type::Slice(_slice_ty) = &*ty_ref.elem {
+            // 对于切片 &[T]，使用 (地址, 长度) 作为键
+            key_types.push(quote! { (usize, usize) });
+            key_exprs.push(quote! { (#arg.as_ptr() as usize, #arg.len()) });
+        } else if let Type::Array(_array_ty) = &*ty_ref.elem {
+            // 对于数组 &[T; N]，使用 (地址, 长度) 作为键
+            key_types.push(quote! { (usize, usize) });
+            key_exprs.push(quote! { (#arg.as_ptr() as usize, #arg.len()) });
+        } else {
+            // 对于其他引用类型（&f64, &String 等），使用 (地址, 长度=1)
+            key_types.push(quote! { (usize, usize) });
+            key_exprs.push(quote! { (#arg as *const _ as usize, 1) });
+        }
+    } else {
+        // 对于非引用类型，直接使用
+        key_types.push(quote! { #ty });
+        key_exprs.push(quote! { #arg });
+    }
+}
+
+// 辅助函数：生成 ref 模式的键（使用 RefKey 包装类型）
+// RefKey<T> 实现了自定义的 Eq 和 Hash：
+//   - Hash: 只基于内容（内容相同进入同一个 HashMap 桶）
+//   - Eq: 先比地址（快），地址相同直接返回 true；地址不同再比内容（慢）
+// 
+// 统一处理逻辑：
+//   - 对于 &[T] 或 &[T; N]：使用 RefKey<Vec<T>>（因为 [T] 不是 Sized）
+//   - 对于 &T（其他）：使用 RefKey<T>
+//   - 对于 T（非引用）：直接使用 T
+fn generate_normal_mode_key(
+    fn_name: &Ident,
+    arg: &Ident,
+    ty: &Type,
+    key_types: &mut Vec<proc_macro2::TokenStream>,
+    key_exprs: &mut Vec<proc_macro2::TokenStream>,
+) {
+    if let Type::Reference(ty_ref) = ty {
+        let ref_key_name = Ident::new(
+            &format!("RefKey{}", to_upper_camel_case(&fn_name.to_string())),
+            fn_name.span()
+        );
+        let inner_ty = &ty_ref.elem;
+        
+        // 检查内部类型并生成对应的 RefKey
+        match &**inner_ty {
+            Type::Slice(slice_ty) => {
+                // 对于 &[T]，检查元素类型
+                let elem_ty = &*slice_ty.elem;  // 解引用 Box
+                
+                // 特殊处理 f64
+            if let Type::Path(type_path) = elem_ty {
+                if let Some(ident) = type_path.path.get_ident() {
+                    if ident == "f64" {
+                            key_types.push(quote! { #ref_key_name<Vec<u64>> });
+                            key_exprs.push(quote! {
+                                #ref_key_name {
+                                    addr: #arg.as_ptr() as usize,
+                                    len: #arg.len(),
+                                    content: #arg.iter().map(|x| x.to_bits()).collect(),
+                                }
+                            });
+                            return;
+                        }
+                    }
+                }
+                
+                // 特殊处理 &[[f64; N]] 类型
+                if let Type::Array(inner_array) = elem_ty {
+                    if let Type::Path(type_path) = &*inner_array.elem {
+                    if let Some(ident) = type_path.path.get_ident() {
+                        if ident == "f64" {
+                                key_types.push(quote! { #ref_key_name<Vec<Vec<u64>>> });
+                                key_exprs.push(quote! {
+                                    #ref_key_name {
+                                        addr: #arg.as_ptr() as usize,
+                                    len: #arg.len(),
+                                        content: #arg.iter().map(|row| row.iter().map(|x| x.to_bits()).collect()).collect(),
+                                    }
+                                });
+                                return;
+                            }
+                        }
+                    }
+                }
+                
+                // 其他类型
+                key_types.push(quote! { #ref_key_name<Vec<#elem_ty>> });
+                key_exprs.push(quote! {
+                    #ref_key_name {
+                        addr: #arg.as_ptr() as usize,
+                                    len: #arg.len(),
+                        content: #arg.to_vec(),
+                    }
+                });
+            }
+            Type::Array(array_ty) => {
+                // 对于 &[T; N]，检查元素类型
+                let elem_ty = &*array_ty.elem;  // 解引用 Box
+                
+                // 特殊处理 f64
+            if let Type::Path(type_path) = elem_ty {
+                if let Some(ident) = type_path.path.get_ident() {
+                    if ident == "f64" {
+                            key_types.push(quote! { #ref_key_name<Vec<u64>> });
+                            key_exprs.push(quote! {
+                                #ref_key_name {
+                                    addr: #arg.as_ptr() as usize,
+                                    len: #arg.len(),
+                                    content: #arg.iter().map(|x| x.to_bits()).collect(),
+                                }
+                            });
+                            return;
+                        }
+                    }
+                }
+                
+                // 特殊处理 &[[f64; M]; N] 类型（嵌套数组）
+                if let Type::Array(inner_array) = elem_ty {
+                    if let Type::Path(type_path) = &*inner_array.elem {
+                if let Some(ident) = type_path.path.get_ident() {
+                    if ident == "f64" {
+                                key_types.push(quote! { #ref_key_name<Vec<Vec<u64>>> });
+                                key_exprs.push(quote! {
+                                    #ref_key_name {
+                                        addr: #arg.as_ptr() as usize,
+                                    len: #arg.len(),
+                                        content: #arg.iter().map(|row| row.iter().map(|x| x.to_bits()).collect()).collect(),
+                                    }
+                                });
+                                return;
+                            }
+                        }
+                    }
+                }
+                
+                // 其他类型
+                key_types.push(quote! { #ref_key_name<Vec<#elem_ty>> });
+                key_exprs.push(quote! {
+                    #ref_key_name {
+                        addr: #arg.as_ptr() as usize,
+                                    len: #arg.len(),
+                        content: #arg.to_vec(),
+                    }
+                });
+            }
+            _ => {
+                // 对于其他类型（如 &i32, &String），使用 RefKey<T>
+                
+                // 特殊处理 f64
+                if let Type::Path(type_path) = &**inner_ty {
+                    if let Some(ident) = type_path.path.get_ident() {
+                        if ident == "f64" {
+                            key_types.push(quote! { #ref_key_name<u64> });
+                            key_exprs.push(quote! {
+                                #ref_key_name {
+                                    addr: #arg as *const _ as usize,
+                        len: 1,
+                                    content: #arg.to_bits(),
+                                }
+                            });
+                            return;
+                        }
+                    }
+                }
+                
+                // 其他类型
+                key_types.push(quote! { #ref_key_name<#inner_ty> });
+                key_exprs.push(quote! {
+                    #ref_key_name {
+                        addr: #arg as *const _ as usize,
+                        len: 1,
+                        content: (*#arg).clone(),
+                    }
+                });
+            }
+        }
+    } else {
+        // 对于非引用类型，直接使用
+        key_types.push(quote! { #ty });
+        key_exprs.push(quote! { #arg });
+    }
+}
+
+// 辅助函数：生成 heavy 模式的键
+fn generate_heavy_mode_key(
+    arg: &Ident,
+    ty: &Type,
+    key_types: &mut Vec<proc_macro2::TokenStream>,
+    key_exprs: &mut Vec<proc_macro2::TokenStream>,
+) {
+    if let Type::Reference(ty_ref) = ty {
+        if let Type::Slice(slice_ty) = &*ty_ref.elem {
+            // 对于 &[T] 类型，完全还原为 [T]
+            let elem_ty = &*slice_ty.elem;
+            if let Type::Path(type_path) = elem_ty {
+                if let Some(ident) = type_path.path.get_ident() {
+                    if ident == "f64" {
+                        // 对于 &[f64] 类型，使用 Vec<u64> 作为键
+                        key_types.push(quote! { Vec<u64> });
+                        key_exprs.push(quote! { #arg.iter().map(|x| x.to_bits()).collect() });
+                    } else {
+                        // 对于其他切片类型，使用 Vec<T> 作为键
+                        key_types.push(quote! { Vec<#elem_ty> });
+                        key_exprs.push(quote! { #arg.to_vec() });
+                    }
+                } else {
+                    key_types.push(quote! { Vec<#elem_ty> });
+                    key_exprs.push(quote! { #arg.to_vec() });
+                }
+            } else if let Type::Array(array_ty) = elem_ty {
+                // 对于 &[[T; N]] 类型，完全还原为 [[T; N]]
+                let inner_elem_ty = &*array_ty.elem;
+                if let Type::Path(type_path) = inner_elem_ty {
+                    if let Some(ident) = type_path.path.get_ident() {
+                        if ident == "f64" {
+                            // 对于 &[[f64; N]] 类型，使用 Vec<Vec<u64>> 作为键
+                            key_types.push(quote! { Vec<Vec<u64>> });
+                            key_exprs.push(quote! { #arg.iter().map(|row| row.iter().map(|x| x.to_bits()).collect()).collect() });
+                        } else {
+                            // 对于其他嵌套数组类型，使用 Vec<Vec<T>> 作为键
+                            key_types.push(quote! { Vec<Vec<#inner_elem_ty>> });
+                            key_exprs.push(quote! { #arg.iter().map(|row| row.to_vec()).collect() });
+                        }
+                    } else {
+                        key_types.push(quote! { Vec<Vec<#inner_elem_ty>> });
+                        key_exprs.push(quote! { #arg.iter().map(|row| row.to_vec()).collect() });
+                    }
+                } else {
+                    key_types.push(quote! { Vec<Vec<#inner_elem_ty>> });
+                    key_exprs.push(quote! { #arg.iter().map(|row| row.to_vec()).collect() });
+                }
+            } else {
+                key_types.push(quote! { Vec<#elem_ty> });
+                key_exprs.push(quote! { #arg.to_vec() });
+            }
+        } else if let Type::Array(array_ty) = &*ty_ref.elem {
+            // 对于 &[T; N] 类型，完全还原为 [T; N]
+            let elem_ty = &*array_ty.elem;
+            if let Type::Path(type_path) = elem_ty {
+                if let Some(ident) = type_path.path.get_ident() {
+                    if ident == "f64" {
+                        // 对于 &[f64; N] 类型，使用 Vec<u64> 作为键
+                        key_types.push(quote! { Vec<u64> });
+                        key_exprs.push(quote! { #arg.iter().map(|x| x.to_bits()).collect() });
+                    } else {
+                        // 对于其他数组类型，使用 Vec<T> 作为键
+                        key_types.push(quote! { Vec<#elem_ty> });
+                        key_exprs.push(quote! { #arg.to_vec() });
+                    }
+                } else {
+                    key_types.push(quote! { Vec<#elem_ty> });
+                    key_exprs.push(quote! { #arg.to_vec() });
+                }
+            } else {
+                key_types.push(quote! { Vec<#elem_ty> });
+                key_exprs.push(quote! { #arg.to_vec() });
+            }
+        } else {
+            // 对于其他引用类型，完全还原
+            let elem_ty = &ty_ref.elem;
+            if let Type::Path(type_path) = &**elem_ty {
+                if let Some(ident) = type_path.path.get_ident() {
+                    if ident == "f64" {
+                        // 对于 f64 类型，使用 u64 作为键（通过位模式转换）
+                        key_types.push(quote! { u64 });
+                        key_exprs.push(quote! { #arg.to_bits() });
+                    } else {
+                        // 对于其他类型，直接克隆
+                        key_types.push(quote! { #elem_ty });
+                        key_exprs.push(quote! { #arg.clone() });
+                    }
+                } else {
+                    key_types.push(quote! { #elem_ty });
+                    key_exprs.push(quote! { #arg.clone() });
+                }
+            } else {
+                key_types.push(quote! { #elem_ty });
+                key_exprs.push(quote! { #arg.clone() });
+            }
+        }
+    } else {
+        // 对于非引用类型，直接使用
+        key_types.push(quote! { #ty });
+        key_exprs.push(quote! { #arg });
+    }
+}
+
+// 范围宏的解析结构
+struct RangeMacro {
+    closure: Expr,
+    range: Expr,  // 改为Expr，支持任何可迭代的表达式
+}
+
+impl Parse for RangeMacro {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // 解析闭包 |i| d[i]
+        let closure = input.parse::<Expr>()?;
+        
+        // 解析逗号
+        input.parse::<Token![,]>()?;
+        
+        // 直接解析迭代器表达式，不再支持中括号
+        let range = input.parse::<Expr>()?;
+        Ok(RangeMacro { closure, range })
+    }
+}
+
+// 多参数宏的解析结构
+struct MultiArgsMacro {
+    args: Vec<Expr>,
+}
+
+impl Parse for MultiArgsMacro {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Vec::new();
+        
+        // 解析所有参数
+        while !input.is_empty() {
+            let arg = input.parse::<Expr>()?;
+            args.push(arg);
+            
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+            } else {
+                break;
+            }
+        }
+        
+        Ok(MultiArgsMacro { args })
+    }
+}
+
+// 通用宏生成器
+fn generate_macro_from_reduce(
+    input: TokenStream,
+    reduce_operation: &str,
+    reduce_closure: proc_macro2::TokenStream,
+) -> TokenStream {
+    // 检查是否是短路操作
+    let is_short_circuit = reduce_operation == "and" || reduce_operation == "or";
+    
+    // 生成空迭代器处理代码
+    let empty_handler = generate_empty_handler(reduce_operation);
+    
+    // 首先尝试解析为范围语法
+    if let Ok(range_macro) = syn::parse::<RangeMacro>(input.clone()) {
+        // 范围语法：macro!(|i| d[i], [iterator]) 或 macro!(|i| d[i], [start..end])
+        let closure = &range_macro.closure;
+        let range = &range_macro.range;
+        
+        // 检查第一个参数是否真的是闭包，如果不是，则不应该走范围语法路径
+        let is_closure = matches!(closure, syn::Expr::Closure(_));
+        if !is_closure {
+            // 第一个参数不是闭包，尝试多参数语法
+            // 这里不return，让代码继续往下走到多参数解析
+        } else {
+        
+        // 检查range是否是范围表达式（如 0..10 或 0..=10）
+        let is_range_expr = matches!(range, syn::Expr::Range(_));
+        
+        let expanded = if is_range_expr {
+            // 处理范围表达式：0..10 或 0..=10
+            if let syn::Expr::Range(range_expr) = range {
+                let start = range_expr.start.as_ref().map(|s| quote! { #s }).unwrap_or(quote! { 0 });
+                let end = range_expr.end.as_ref().map(|e| quote! { #e });
+                
+                if let Some(end_expr) = end {
+                    // 有结束范围的情况：[start..end] 或 [start..=end]
+                    let range_expr = match range_expr.limits {
+                        syn::RangeLimits::HalfOpen(_) => quote! { #start..#end_expr },
+                        syn::RangeLimits::Closed(_) => quote! { #start..=#end_expr },
+                    };
+                    
+                    if is_short_circuit {
+                        // 短路优化版本
+                        if reduce_operation == "and" {
+                            quote! {{
+                                let mut result = true;
+                                for __mau_idx in #range_expr {
+                                    let current_val = (#closure)(__mau_idx);
+                                    if !current_val {
+                                        result = false;
+                                        break;
+                                    }
+                                }
+                                result
+                            }}
+                        } else { // or
+                            quote! {{
+                                let mut result = false;
+                                for __mau_idx in #range_expr {
+                                    let current_val = (#closure)(__mau_idx);
+                                    if current_val {
+                                        result = true;
+                                        break;
+                                    }
+                                }
+                                result
+                            }}
+                        }
+                    } else {
+                        // 普通归约版本
+                        quote! {{
+                            let mut result = None;
+                            
+                            for __mau_idx in #range_expr {
+                                let current_val = (#closure)(__mau_idx);
+                                result = match result {
+                                    None => Some(current_val),
+                                    Some(acc) => Some((#reduce_closure)(acc, current_val)),
+                                };
+                            }
+                            
+                            #empty_handler
+                        }}
+                    }
+                } else {
+                    // 无结束范围的情况：start..
+                    if is_short_circuit {
+                        // 短路优化版本
+                        if reduce_operation == "and" {
+                            quote! {{
+                                let mut result = true;
+                                let mut __mau_idx = #start;
+                                loop {
+                                    let current_val = (#closure)(__mau_idx);
+                                    if !current_val {
+                                        result = false;
+                                        break;
+                                    }
+                                    __mau_idx += 1;
+                                }
+                                result
+                            }}
+                        } else { // or
+                            quote! {{
+                                let mut result = false;
+                                let mut __mau_idx = #start;
+                                loop {
+                                    let current_val = (#closure)(__mau_idx);
+                                    if current_val {
+                                        result = true;
+                                        break;
+                                    }
+                                    __mau_idx += 1;
+                                }
+                                result
+                            }}
+                        }
+                    } else {
+                        // 普通归约版本
+                        quote! {{
+                            let mut result = None;
+                            let mut __mau_idx = #start;
+                            
+                            loop {
+                                let current_val = (#closure)(__mau_idx);
+                                result = match result {
+                                    None => Some(current_val),
+                                    Some(acc) => Some((#reduce_closure)(acc, current_val)),
+                                };
+                                __mau_idx += 1;
+                            }
+                        }}
+                    }
+                }
+            } else {
+                // 这不应该发生，因为我们已经检查了is_range_expr
+                unreachable!()
+            }
+        } else {
+            // 处理迭代器表达式：任何实现了IntoIterator的类型
+            // 直接解引用，让闭包接收值而不是引用
+            if is_short_circuit {
+                // 短路优化版本
+                if reduce_operation == "and" {
+                    quote! {{
+                        let mut result = true;
+                        for __mau_item in #range {
+                            let current_val = (#closure)(*__mau_item);
+                            if !current_val {
+                                result = false;
+                                break;
+                            }
+                        }
+                        result
+                    }}
+                } else { // or
+                    quote! {{
+                        let mut result = false;
+                        for __mau_item in #range {
+                            let current_val = (#closure)(*__mau_item);
+                            if current_val {
+                                result = true;
+                                break;
+                            }
+                        }
+                        result
+                    }}
+                }
+            } else {
+                // 普通归约版本
+                quote! {{
+                    let mut result = None;
+                    
+                    for __mau_item in #range {
+                        let current_val = (#closure)(*__mau_item);
+                        result = match result {
+                            None => Some(current_val),
+                            Some(acc) => Some((#reduce_closure)(acc, current_val)),
+                        };
+                    }
+                    
+                    #empty_handler
+                }}
+            }
+        };
+        
+        return expanded.into();
+        }  // 结束 is_closure 的 else 分支
+    }
+    
+    // 如果范围语法解析失败或第一个参数不是闭包，尝试解析为多参数语法
+    if let Ok(multi_args) = syn::parse::<MultiArgsMacro>(input.clone()) {
+        if multi_args.args.is_empty() {
+            return syn::Error::new(proc_macro2::Span::call_site(), format!("{}! macro requires at least one argument", reduce_operation)).to_compile_error().into();
+        }
+        
+        if multi_args.args.len() == 1 {
+            // 只有一个参数，检查是否为数组类型
+            let arg = &multi_args.args[0];
+            
+            // 检查参数是否是数组/切片类型
+            let is_array_like = match arg {
+                syn::Expr::Path(_) => true,  // 变量名
+                syn::Expr::Array(_) => true,  // 数组字面量
+                syn::Expr::Call(call) => {
+                    if let syn::Expr::Path(path) = &*call.func {
+                        path.path.is_ident("vec") || path.path.is_ident("Vec")
+                    } else {
+                        false
+                    }
+                }
+                _ => false,
+            };
+            
+            if is_array_like {
+                // 简写语法：macro!(array) -> macro!(|i| array[i], [0..array.len()])
+                // 统一使用range语法处理
+                let closure = quote! { |__mau_idx| #arg[__mau_idx] };
+                let range_expr = quote! { 0..#arg.len() };
+                
+                if is_short_circuit {
+                    // 短路优化版本
+                    if reduce_operation == "and" {
+                        return quote! {{
+                            let mut result = true;
+                            for __mau_idx in #range_expr {
+                                let current_val = (#closure)(__mau_idx);
+                                if !current_val {
+                                    result = false;
+                                    break;
+                                }
+                            }
+                            result
+                        }}.into();
+                    } else { // or
+                        return quote! {{
+                            let mut result = false;
+                            for __mau_idx in #range_expr {
+                                let current_val = (#closure)(__mau_idx);
+                                if current_val {
+                                    result = true;
+                                    break;
+                                }
+                            }
+                            result
+                        }}.into();
+                    }
+                } else {
+                    // 普通归约版本
+                    return quote! {{
+                        let mut result = None;
+                        
+                        for __mau_idx in #range_expr {
+                            let current_val = (#closure)(__mau_idx);
+                            result = match result {
+                                None => Some(current_val),
+                                Some(acc) => Some((#reduce_closure)(acc, current_val)),
+                            };
+                        }
+                        
+                        #empty_handler
+                    }}.into();
+                }
+            } else {
+                // 单个非数组参数，直接返回
+                return quote! { #arg }.into();
+            }
+        } else {
+            // 多个参数：先生成数组，然后使用range语法统一处理
+            let args = &multi_args.args;
+            let array_len = args.len();
+            
+            // 生成数组：let __mau_array = [arg1, arg2, arg3, ...];
+            let array_expr = quote! { [#(#args),*] };
+            let closure = quote! { |__mau_idx| __mau_array[__mau_idx] };
+            let range_expr = quote! { 0..#array_len };
+            
+            if is_short_circuit {
+                // 短路优化版本
+                if reduce_operation == "and" {
+                    return quote! {{
+                        let __mau_array = #array_expr;
+                        let mut result = true;
+                        for __mau_idx in #range_expr {
+                            let current_val = (#closure)(__mau_idx);
+                            if !current_val {
+                                result = false;
+                                break;
+                            }
+                        }
+                        result
+                    }}.into();
+                } else { // or
+                    return quote! {{
+                        let __mau_array = #array_expr;
+                        let mut result = false;
+                        for __mau_idx in #range_expr {
+                            let current_val = (#closure)(__mau_idx);
+                            if current_val {
+                                result = true;
+                                break;
+                            }
+                        }
+                        result
+                    }}.into();
+                }
+            } else {
+                // 普通归约版本
+                return quote! {{
+                    let __mau_array = #array_expr;
+                    let mut result = None;
+                    
+                    for __mau_idx in #range_expr {
+                        let current_val = (#closure)(__mau_idx);
+                        result = match result {
+                            None => Some(current_val),
+                            Some(acc) => Some((#reduce_closure)(acc, current_val)),
+                        };
+                    }
+                    
+                        #empty_handler
+                }}.into();
+            }
+        }
+    }
+    
+    // 如果所有解析都失败，返回错误
+    syn::Error::new(proc_macro2::Span::call_site(), format!("Invalid syntax for {}! macro. Use either {}!(a, b, c), {}!(array), or {}!(|i| expr, [start..end])", reduce_operation, reduce_operation, reduce_operation, reduce_operation)).to_compile_error().into()
+}
+
+/// min! 宏：在指定范围内找到最小值
+/// 
+/// 语法：min!(|i| d[i], [start..end])
+/// 
+/// 示例：
+/// ```rust
+/// use mau::min;
+///
+/// let d = vec![3, 1, 4, 1, 5, 9];
+/// let min_val = min!(|i| d[i], 0..d.len());
+/// assert_eq!(min_val, 1);
+/// ```
+#[proc_macro]
+pub fn min(input: TokenStream) -> TokenStream {
+    generate_macro_from_reduce(input, "min", quote! { |a, b| if a < b { a } else { b } })
+}
+
+/// max! 宏：在指定范围内找到最大值
+///
+/// 语法：max!(|i| d[i], [start..end])
+///
+/// 示例：
+/// ```rust
+/// use mau::max;
+///
+/// let d = vec![3, 1, 4, 1, 5, 9];
+/// let max_val = max!(|i| d[i], 0..d.len());
+/// assert_eq!(max_val, 9);
+/// ```
+#[proc_macro]
+pub fn max(input: TokenStream) -> TokenStream {
+    generate_macro_from_reduce(input, "max", quote! { |a, b| if a > b { a } else { b } })
+}
+
+/// sum! 宏：在指定范围内求和
+///
+/// 语法：sum!(|i| d[i], [start..end])
+///
+/// 示例：
+/// ```rust
+/// use mau::sum;
+///
+/// let d = vec![3, 1, 4, 1, 5, 9];
+/// let sum_val = sum!(|i| d[i], 0..d.len());
+/// assert_eq!(sum_val, 23);
+/// ```
+#[proc_macro]
+pub fn sum(input: TokenStream) -> TokenStream {
+    generate_macro_from_reduce(input, "sum", quote! { |a, b| a + b })
+}
+
+/// and! 宏：在指定范围内进行逻辑与运算
+///
+/// 语法：and!(|i| d[i], [start..end])
+///
+/// 示例：
+/// ```rust
+/// use mau::and;
+///
+/// let bools = vec![true, true, false, true];
+/// let and_val = and!(|i| bools[i], 0..bools.len());
+/// assert_eq!(and_val, false);
+/// ```
+#[proc_macro]
+pub fn and(input: TokenStream) -> TokenStream {
+    generate_macro_from_reduce(input, "and", quote! { |a, b| a && b })
+}
+
+/// or! 宏：在指定范围内进行逻辑或运算
+///
+/// 语法：or!(|i| d[i], [start..end])
+///
+/// 示例：
+/// ```rust
+/// use mau::or;
+///
+/// let bools = vec![true, true, false, true];
+/// let or_val = or!(|i| bools[i], 0..bools.len());
+/// assert_eq!(or_val, true);
+/// ```
+#[proc_macro]
+pub fn or(input: TokenStream) -> TokenStream {
+    generate_macro_from_reduce(input, "or", quote! { |a, b| a || b })
+}
+
+// Reduce宏的解析结构
+struct ReduceMacro {
+    data_closure: Expr,
+    range: Expr,  // 改为Expr，支持任何可迭代的表达式
+    reduce_closure: Expr,
+}
+
+impl Parse for ReduceMacro {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // 解析数据闭包 |i| data[i]
+        let data_closure = input.parse::<Expr>()?;
+        
+        // 解析逗号
+        input.parse::<Token![,]>()?;
+        
+        // 直接解析迭代器表达式，不再支持中括号
+        let range = input.parse::<Expr>()?;
+        
+        // 解析逗号
+        input.parse::<Token![,]>()?;
+        
+        // 解析归约闭包 |a, b| if a > b { a } else { b }
+        let reduce_closure = input.parse::<Expr>()?;
+        
+        Ok(ReduceMacro { 
+            data_closure,
+            range,
+            reduce_closure,
+        })
+    }
+}
+
+// Fold宏的解析结构
+struct FoldMacro {
+    init_value: Expr,
+    data_closure: Expr,
+    range: Expr,
+    fold_closure: Expr,
+}
+
+impl Parse for FoldMacro {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // 解析初始值
+        let init_value = input.parse::<Expr>()?;
+        
+        // 解析逗号
+        input.parse::<Token![,]>()?;
+        
+        // 解析数据闭包 |i| data[i]
+        let data_closure = input.parse::<Expr>()?;
+        
+        // 解析逗号
+        input.parse::<Token![,]>()?;
+        
+        // 解析迭代器表达式
+        let range = input.parse::<Expr>()?;
+        
+        // 解析逗号
+        input.parse::<Token![,]>()?;
+        
+        // 解析折叠闭包 |acc, val| acc + val
+        let fold_closure = input.parse::<Expr>()?;
+        
+        Ok(FoldMacro {
+            init_value,
+            data_closure,
+            range,
+            fold_closure,
+        })
+    }
+}
+
+/// reduce! 宏：在指定范围内进行归约操作
+///
+/// 语法：reduce!(|i| data[i], [start..end], |a, b| if a > b { a } else { b })
+///
+/// # 参数
+/// - `data_closure`: 数据访问闭包，如 `|i| data[i]`
+/// - `range`: 范围表达式，如 `[0..data.len()]` 或 `[2..6]`
+/// - `reduce_closure`: 归约操作闭包，如 `|a, b| if a > b { a } else { b }`
+///
+/// # 示例
+/// ```rust
+/// use mau::reduce;
+///
+/// let data = vec![3, 1, 4, 1, 5, 9, 2, 6];
+/// let max_val = reduce!(|i| data[i], 0..data.len(), |a, b| if a > b { a } else { b });
+/// assert_eq!(max_val, 9);
+///
+/// // 也可以用于求和
+/// let sum_val = reduce!(|i| data[i], 0..data.len(), |a, b| a + b);
+/// assert_eq!(sum_val, 31);
+///
+/// // 部分范围
+/// let partial_max = reduce!(|i| data[i], 2..6, |a, b| if a > b { a } else { b });
+/// assert_eq!(partial_max, 9); // 只检查索引2到5: [4, 1, 5, 9]
+/// ```
+#[proc_macro]
+pub fn reduce(input: TokenStream) -> TokenStream {
+    // 解析reduce宏语法
+    if let Ok(reduce_macro) = syn::parse::<ReduceMacro>(input) {
+        let data_closure = &reduce_macro.data_closure;
+        let range = &reduce_macro.range;
+        let reduce_closure = &reduce_macro.reduce_closure;
+        
+        // 检查range是否是范围表达式（如 0..10 或 0..=10）
+        let is_range_expr = matches!(range, syn::Expr::Range(_));
+
+        let expanded = if is_range_expr {
+            // 处理范围表达式：0..10 或 0..=10
+            if let syn::Expr::Range(range_expr) = range {
+                let start = range_expr.start.as_ref().map(|s| quote! { #s }).unwrap_or(quote! { 0 });
+                let end = range_expr.end.as_ref().map(|e| quote! { #e });
+
+                if let Some(end_expr) = end {
+                    // 有结束范围的情况：[start..end] 或 [start..=end]
+                    let range_expr = match range_expr.limits {
+                        syn::RangeLimits::HalfOpen(_) => quote! { #start..#end_expr },
+                        syn::RangeLimits::Closed(_) => quote! { #start..=#end_expr },
+                    };
+
+                    quote! {{
+                        let mut result = None;
+
+                        for __mau_idx in #range_expr {
+                            let current_val = (#data_closure)(__mau_idx);
+                            result = match result {
+                                None => Some(current_val),
+                                Some(acc) => Some((#reduce_closure)(acc, current_val)),
+                            };
+                        }
+
+                        result.expect("Range cannot be empty")
+                    }}
+                } else {
+                    // 无结束范围的情况：start..
+                    quote! {{
+                        let mut result = None;
+                        let mut __mau_idx = #start;
+
+                        loop {
+                            let current_val = (#data_closure)(__mau_idx);
+                            result = match result {
+                                None => Some(current_val),
+                                Some(acc) => Some((#reduce_closure)(acc, current_val)),
+                            };
+                            __mau_idx += 1;
+                        }
+                    }}
+                }
+            } else {
+                // 这不应该发生，因为我们已经检查了is_range_expr
+                unreachable!()
+            }
+        } else {
+            // 处理迭代器表达式：任何实现了IntoIterator的类型
+            quote! {{
+                let mut result = None;
+
+                for __mau_item in #range {
+                    let current_val = (#data_closure)(__mau_item);
+                    result = match result {
+                        None => Some(current_val),
+                        Some(acc) => Some((#reduce_closure)(acc, current_val)),
+                    };
+                }
+
+                result.expect("Iterator cannot be empty")
+            }}
+        };
+        
+        return expanded.into();
+    }
+    
+    // 如果解析失败，返回错误
+    syn::Error::new(proc_macro2::Span::call_site(), "Invalid syntax for reduce! macro. Use reduce!(|i| data[i], [start..end], |a, b| operation)").to_compile_error().into()
+}
+
+/// fold! 宏：从初始值开始进行折叠操作
+///
+/// 语法：fold!(init_value, |i| data[i], start..end, |acc, val| acc + val)
+///
+/// # 参数
+/// - `init_value`: 初始累加器值
+/// - `data_closure`: 数据访问闭包，如 `|i| data[i]`
+/// - `range`: 范围表达式，如 `0..data.len()` 或 `2..6`
+/// - `fold_closure`: 折叠操作闭包，如 `|acc, val| acc + val`
+///
+/// # 与 reduce! 的区别
+/// - `fold!` 需要提供初始值，可以处理空迭代器（返回初始值）
+/// - `reduce!` 使用第一个元素作为初始值，空迭代器会 panic
+/// - `fold!` 的累加器类型可以与元素类型不同
+///
+/// # 示例
+/// ```rust
+/// use mau::fold;
+///
+/// let data = vec![1, 2, 3, 4, 5];
+/// 
+/// // 求和，初始值为 0
+/// let sum = fold!(0, |i| data[i], 0..data.len(), |acc, val| acc + val);
+/// assert_eq!(sum, 15);
+///
+/// // 求积，初始值为 1
+/// let product = fold!(1, |i| data[i], 0..data.len(), |acc, val| acc * val);
+/// assert_eq!(product, 120);
+///
+/// // 空范围返回初始值
+/// let empty = fold!(100, |i| data[i], 0..0, |acc, val| acc + val);
+/// assert_eq!(empty, 100);
+///
+/// // 构建字符串
+/// let data = vec!["Hello", "World", "Rust"];
+/// let sentence = fold!(String::new(), |i| data[i], 0..data.len(), |mut acc: String, val| {
+///     if !acc.is_empty() { acc.push(' '); }
+///     acc.push_str(val);
+///     acc
+/// });
+/// assert_eq!(sentence, "Hello World Rust");
+/// ```
+#[proc_macro]
+pub fn fold(input: TokenStream) -> TokenStream {
+    // 解析fold宏语法
+    if let Ok(fold_macro) = syn::parse::<FoldMacro>(input) {
+        let init_value = &fold_macro.init_value;
+        let data_closure = &fold_macro.data_closure;
+        let range = &fold_macro.range;
+        let fold_closure = &fold_macro.fold_closure;
+        
+        // 检查range是否是范围表达式（如 0..10 或 0..=10）
+        let is_range_expr = matches!(range, syn::Expr::Range(_));
+
+        let expanded = if is_range_expr {
+            // 处理范围表达式：0..10 或 0..=10
+            if let syn::Expr::Range(range_expr) = range {
+                let start = range_expr.start.as_ref().map(|s| quote! { #s }).unwrap_or(quote! { 0 });
+                let end = range_expr.end.as_ref().map(|e| quote! { #e });
+
+                if let Some(end_expr) = end {
+                    // 有结束范围的情况：[start..end] 或 [start..=end]
+                    let range_expr = match range_expr.limits {
+                        syn::RangeLimits::HalfOpen(_) => quote! { #start..#end_expr },
+                        syn::RangeLimits::Closed(_) => quote! { #start..=#end_expr },
+                    };
+
+                    quote! {{
+                        let mut acc = #init_value;
+
+                        for __mau_idx in #range_expr {
+                            let current_val = (#data_closure)(__mau_idx);
+                            acc = (#fold_closure)(acc, current_val);
+                        }
+
+                        acc
+                    }}
+                } else {
+                    // 无结束范围的情况：start..（无限循环）
+                    quote! {{
+                        let mut acc = #init_value;
+                        let mut __mau_idx = #start;
+
+                        loop {
+                            let current_val = (#data_closure)(__mau_idx);
+                            acc = (#fold_closure)(acc, current_val);
+                            __mau_idx += 1;
+                        }
+                    }}
+                }
+            } else {
+                // 这不应该发生，因为我们已经检查了is_range_expr
+                unreachable!()
+            }
+        } else {
+            // 处理迭代器表达式：任何实现了IntoIterator的类型
+            quote! {{
+                let mut acc = #init_value;
+
+                for __mau_item in #range {
+                    let current_val = (#data_closure)(__mau_item);
+                    acc = (#fold_closure)(acc, current_val);
+                }
+
+                acc
+            }}
+        };
+        
+        return expanded.into();
+    }
+    
+    // 如果解析失败，返回错误
+    syn::Error::new(proc_macro2::Span::call_site(), "Invalid syntax for fold! macro. Use fold!(init_value, |i| data[i], start..end, |acc, val| operation)").to_compile_error().into()
+}
+
+// 从备份文件中提取memo宏的实现
+use syn::{
+    parse_macro_input, ItemFn, ReturnType,
+    punctuated::Punctuated, spanned::Spanned,
+};
+
+// KeyArgs 结构用于解析 memo 宏的属性参数
+struct KeyArgs {
+    args: Punctuated<Ident, syn::Token![,]>,
+    named_args: std::collections::HashMap<String, String>,
+    // 列表参数 name(...)，保存括号内的 token，由各自的解析函数处理
+    list_args: std::collections::HashMap<String, proc_macro2::TokenStream>,
+    // 开关参数（如 share），不参与位置参数的模式解析
+    flags: HashSet<String>,
+}
+
+impl syn::parse::Parse for KeyArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut args = Punctuated::new();
+        let mut named_args = std::collections::HashMap::new();
+        let mut list_args = std::collections::HashMap::new();
+        let mut flags = HashSet::new();
+        
+        while !input.is_empty() {
+            // 尝试解析命名参数 key=value
+            if input.peek(Ident) && input.peek2(syn::Token![=]) {
+                let key: Ident = input.parse()?;
+                input.parse::<syn::Token![=]>()?;
+                
+                // 解析值（可能是关键字 ref，或整数/字符串字面量）
+                let value_str = if key == "on_cycle" || key == "fixpoint" {
+                    // on_cycle / fixpoint 的值是任意表达式（如 -1、(0, 0)、u64::MAX）
+                    let value: Expr = input.parse()?;
+                    quote!(#value).to_string()
+                } else if input.peek(syn::Token![ref]) {
+                    input.parse::<syn::Token![ref]>()?;
+                    "ref".to_string()
+                } else if input.peek(syn::LitInt) {
+                    let value: syn::LitInt = input.parse()?;
+                    value.base10_digits().to_string()
+                } else if input.peek(syn::LitStr) {
+                    let value: syn::LitStr = input.parse()?;
+                    value.value()
+                } else {
+                    // 标识符或路径（如 cache_if = path::to::predicate）
+                    let value: syn::Path = input.parse()?;
+                    quote!(#value).to_string()
+                };
+                
+                named_args.insert(key.to_string(), value_str);
+            } else if input.peek(Ident) && input.peek2(syn::token::Paren) {
+                // 列表参数 name(...)
+                let name: Ident = input.parse()?;
+                let content;
+                syn::parenthesized!(content in input);
+                if !MEMO_LIST_ARGS.contains(&name.to_string().as_str()) {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!("无效的参数名 '{}'. 列表参数只支持 {}", name, MEMO_LIST_ARGS.join("、")),
+                    ));
+                }
+                list_args.insert(name.to_string(), content.parse()?);
+            } else if input.peek(Ident) {
+                // 开关参数，或位置参数（向后兼容）
+                let arg: Ident = input.parse()?;
+                if MEMO_FLAGS.contains(&arg.to_string().as_str()) {
+                    flags.insert(arg.to_string());
+                } else {
+                    args.push(arg);
+                }
+            } else {
+                break;
+            }
+            
+            // 解析逗号
+            if input.peek(syn::Token![,]) {
+                input.parse::<syn::Token![,]>()?;
+            } else {
+                break;
+            }
+        }
+        
+        Ok(KeyArgs { args, named_args, list_args, flags })
+    }
+}
+
+// memo 宏支持的命名参数
+const MEMO_NAMED_ARGS: &[&str] = &[
+    "thread", "key", "lifetime", "capacity", "evict", "ttl_ms", "stats", "receiver", "helpers",
+    "cache_if", "key_with", "out_of_bounds", "hasher", "shards", "persist", "persist_interval_ms",
+    "stack", "stack_mb", "on_cycle", "fixpoint", "fixpoint_limit", "scope",
+];
+
+// memo 宏支持的列表参数 name(...)
+const MEMO_LIST_ARGS: &[&str] = &["symmetric", "sorted", "ignore", "dense", "tabulate", "helpers"];
+
+// memo 宏支持的开关参数
+const MEMO_FLAGS: &[&str] = &["share", "handle", "cycle_check"];
+
+// 解析线程模式、键模式和生命周期的辅助函数
+fn parse_memo_modes(key_args: &KeyArgs) -> (String, String, String) {
+    // 首先检查命名参数
+    if !key_args.named_args.is_empty() {
+        // 验证参数名
+        for k in key_args.named_args.keys() {
+            if !MEMO_NAMED_ARGS.contains(&k.as_str()) {
+                panic!("无效的参数名 '{}'. 只支持 {}", k, MEMO_NAMED_ARGS.join("、"));
+            }
+        }
+        
+        let thread_mode = key_args
+            .named_args
+            .get("thread")
+            .map(|s| {
+                // 验证 thread 模式
+                match s.as_str() {
+                    "single" | "multi" => s.clone(),
+                    _ => panic!("无效的 thread 模式 '{}'. 只支持 'single' 或 'multi'", s),
+                }
+            })
+            .unwrap_or_else(|| "single".to_string());
+        
+        let key_mode = key_args
+            .named_args
+            .get("key")
+            .map(|s| {
+                // 验证 key 模式
+                match s.as_str() {
+                    "ptr" | "ref" | "val" => s.clone(),
+                    _ => panic!("无效的 key 模式 '{}'. 只支持 'ptr', 'ref' 或 'val'", s),
+                }
+            })
+            .unwrap_or_else(|| "ptr".to_string());
+        
+        let lifetime_mode = key_args
+            .named_args
+            .get("lifetime")
+            .map(|s| {
+                // 验证 lifetime 模式
+                match s.as_str() {
+                    "program" | "problem" => s.clone(),
+                    _ => panic!("无效的 lifetime 模式 '{}'. 只支持 'program' 或 'problem'", s),
+                }
+            })
+            .unwrap_or_else(|| "problem".to_string());
+        
+        return (thread_mode, key_mode, lifetime_mode);
+    }
+    
+    // 向后兼容：位置参数（映射旧名称到新名称）
+    if key_args.args.is_empty() {
+        return ("single".to_string(), "ptr".to_string(), "problem".to_string());
+    }
+    
+    // 检查第一个参数
+        if let Some(first_arg) = key_args.args.first() {
+            let arg_str = first_arg.to_string();
+        
+        // 映射旧的线程模式名称
+        let thread_mode = match arg_str.as_str() {
+            "local" => "single".to_string(),  // local -> single
+            "single" => {
+                // 旧的 single -> 现在不存在，报错或使用默认
+                eprintln!("警告：旧的 'single' 模式已被移除，请使用 'single'(原local) 或 'multi'");
+                "single".to_string()
+            }
+            "multi" => "multi".to_string(),
+            // 检查是否是键模式
+            "light" => {
+                // 这是键模式，使用默认线程和生命周期
+                return ("single".to_string(), "ptr".to_string(), "problem".to_string());
+            }
+            "normal" => {
+                return ("single".to_string(), "ref".to_string(), "problem".to_string());
+            }
+            "heavy" => {
+                return ("single".to_string(), "val".to_string(), "problem".to_string());
+            }
+            "ptr" | "ref" | "val" => {
+                // 新的键模式名称
+                let key_mode = match arg_str.as_str() {
+                    "ptr" => "ptr".to_string(),
+                    "ref" => "ref".to_string(),
+                    "val" => "val".to_string(),
+                    _ => "ptr".to_string(),
+                };
+                return ("single".to_string(), key_mode, "problem".to_string());
+            }
+            _ => "single".to_string(),
+        };
+        
+        // 检查第二个参数
+        let key_mode = if key_args.args.len() > 1 {
+                    let second_arg = &key_args.args[1];
+                    let second_str = second_arg.to_string();
+            match second_str.as_str() {
+                // 旧名称映射
+                "light" => "ptr".to_string(),
+                "normal" => "ref".to_string(),
+                "heavy" => "val".to_string(),
+                // 新名称
+                "ptr" | "ref" | "val" => second_str,
+                _ => "ptr".to_string(),
+                    }
+                } else {
+            "ptr".to_string()
+        };
+        
+        (thread_mode, key_mode, "problem".to_string())
+            } else {
+        ("single".to_string(), "ptr".to_string(), "problem".to_string())
+    }
+}
+
+// 解析接收者的键模式：receiver=ptr|ref|val|ignore
+// 默认与 key 模式相同；返回 None 表示接收者不参与键（或不是方法）
+fn parse_receiver_mode(key_args: &KeyArgs, key_mode: &str, has_receiver: bool) -> Option<String> {
+    let mode = key_args.named_args.get("receiver");
+    if !has_receiver {
+        if mode.is_some() {
+            panic!("receiver 只能用于带 self 参数的方法");
+        }
+        return None;
+    }
+    match mode.map(|s| s.as_str()) {
+        None => Some(key_mode.to_string()),
+        Some("ignore") => None,
+        Some(s @ ("ptr" | "ref" | "val")) => Some(s.to_string()),
+        Some(s) => panic!("无效的 receiver 模式 '{}'. 只支持 'ptr'、'ref'、'val' 或 'ignore'", s),
+    }
+}
+
+// 解析是否生成 _start / _clear / _stats 辅助函数：helpers=none 表示不生成
+// trait impl 中的方法只能使用 helpers=none（trait impl 不允许额外的关联函数）
+// 可以改名或不生成的辅助函数（按后缀）
+const MEMO_HELPERS: &[&str] = &[
+    "start", "clear", "stats", "with", "snapshot", "export_json", "export_csv", "print_grid",
+    "cached", "insert", "invalidate", "retain",
+];
+
+// 辅助函数的名字：默认为 {fn}_{后缀}
+// helpers(start = run, clear = reset) 改名，helpers(clear = none) 不生成，helpers=none 全部不生成
+struct Helpers {
+    enabled: bool,
+    renamed: std::collections::HashMap<String, Option<Ident>>,
+}
+
+impl Helpers {
+    fn name(&self, fn_name: &Ident, suffix: &str) -> Option<Ident> {
+        if !self.enabled {
+            return None;
+        }
+        match self.renamed.get(suffix) {
+            Some(name) => name.clone(),
+            None => Some(Ident::new(&format!("{}_{}", fn_name, suffix), fn_name.span())),
+        }
+    }
+}
+
+fn parse_memo_helpers(key_args: &KeyArgs) -> Helpers {
+    use syn::parse::Parser;
+
+    let enabled = match key_args.named_args.get("helpers").map(|s| s.as_str()) {
+        None => true,
+        Some("none") => false,
+        Some(s) => panic!("无效的 helpers 选项 '{}'. 只支持 'none'", s),
+    };
+    let mut renamed = std::collections::HashMap::new();
+    if let Some(tokens) = key_args.list_args.get("helpers") {
+        if !enabled {
+            panic!("helpers=none 不能与 helpers(...) 同时使用");
+        }
+        let parser = Punctuated::<syn::MetaNameValue, Token![,]>::parse_terminated;
+        let items = parser.parse2(tokens.clone()).unwrap_or_else(|error| panic!("helpers(...) 解析失败：{}", error));
+        for item in items {
+            let helper = item.path.get_ident().map(|ident| ident.to_string()).unwrap_or_default();
+            if !MEMO_HELPERS.contains(&helper.as_str()) {
+                panic!("helpers: 无效的辅助函数 '{}'. 只支持 {}", quote!(#item.path), MEMO_HELPERS.join("、"));
+            }
+            let name = match &item.value {
+                Expr::Path(path) if path.path.is_ident("none") => None,
+                Expr::Path(path) if path.path.get_ident().is_some() => path.path.get_ident().cloned(),
+                value => panic!("helpers: {} 的值必须是函数名或 none，而不是 '{}'", helper, quote!(#value)),
+            };
+            if renamed.insert(helper.clone(), name).is_some() {
+                panic!("helpers: {} 重复指定", helper);
+            }
+        }
+    }
+    Helpers { enabled, renamed }
+}
+
+// 解析缓存的作用域：scope=global（默认）缓存在静态变量中；
+// scope=instance 缓存在调用者持有的 {Fn}Cache 中，通过 {fn}_in(&mut cache, ..) 调用
+fn parse_scope(key_args: &KeyArgs) -> bool {
+    match key_args.named_args.get("scope").map(|s| s.as_str()) {
+        None | Some("global") => false,
+        Some("instance") => true,
+        Some(s) => panic!("无效的 scope 选项 '{}'. 只支持 'global' 或 'instance'", s),
+    }
+}
+
+// 把参数中的 impl Trait 改写为具名泛型参数 __MauImpl{i}
+// 这样它们可以参与缓存的 TypeId，内层函数也可以用 turbofish 调用
+fn desugar_impl_trait_args(sig: &mut syn::Signature) {
+    use syn::visit_mut::{self, VisitMut};
+
+    struct ImplTraitRewriter {
+        params: Vec<syn::TypeParam>,
+    }
+
+    impl VisitMut for ImplTraitRewriter {
+        fn visit_type_mut(&mut self, ty: &mut Type) {
+            if let Type::ImplTrait(impl_trait) = ty {
+                // 先改写约束中嵌套的 impl Trait（如 impl Iterator<Item = impl Hash>）
+                visit_mut::visit_type_impl_trait_mut(self, impl_trait);
+                let ident = Ident::new(&format!("__MauImpl{}", self.params.len()), impl_trait.span());
+                let bounds = &impl_trait.bounds;
+                self.params.push(syn::parse_quote! { #ident: #bounds });
+                *ty = syn::parse_quote! { #ident };
+            } else {
+                visit_mut::visit_type_mut(self, ty);
+            }
+        }
+    }
+
+    let mut rewriter = ImplTraitRewriter { params: Vec::new() };
+    for arg in sig.inputs.iter_mut() {
+        if let FnArg::Typed(pat_type) = arg {
+            rewriter.visit_type_mut(&mut pat_type.ty);
+        }
+    }
+    for param in rewriter.params {
+        sig.generics.params.push(syn::GenericParam::Type(param));
+    }
+}
+
+// 缓存结构体本身的配置（与线程模式、键模式无关）
+struct CacheOptions {
+    // 容量上限和淘汰策略，None 表示不限容量；分片时是每个分片的容量
+    capacity: Option<(usize, String)>,
+    // 属性中声明的容量上限（分片前）
+    declared_capacity: Option<usize>,
+    // 条目过期时间（毫秒），None 表示永不过期
+    ttl_ms: Option<u64>,
+    // 有界整数参数的稠密表，None 表示使用哈希表
+    dense: Option<DenseTable>,
+    // 哈希表使用的哈希函数：std | fx | ahash_like | identity
+    hasher: String,
+    // multi 模式的分片数，None 表示单个 Mutex
+    shards: Option<usize>,
+}
+
+// dense(n: 0..=1000, k: 0..=50)：每个参数的取值范围 [start, end)
+// out_of_bounds = hash | panic：超出范围的参数退回哈希表（默认）或 panic
+// tabulate(n: 0..=1000, k: 0..=50, order = ascending) 同样使用稠密表，并且自底向上填满整张表
+struct DenseTable {
+    dims: Vec<(Ident, Expr, proc_macro2::TokenStream)>,
+    panic_out_of_bounds: bool,
+    tabulate: Option<Tabulate>,
+}
+
+// 自底向上填表：按声明的顺序嵌套循环（第一个参数在最外层），每一维按 order 的方向遍历
+struct Tabulate {
+    dims: Vec<(Ident, Expr, proc_macro2::TokenStream)>,
+    descending: bool,
+}
+
+enum TabulateItem {
+    Dim(Box<DenseDim>),
+    Order(Ident),
+}
+
+impl Parse for TabulateItem {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Ident) && input.peek2(Token![=]) {
+            let name: Ident = input.parse()?;
+            if name != "order" {
+                return Err(syn::Error::new(name.span(), "tabulate 只支持 order = ascending | descending"));
+            }
+            input.parse::<Token![=]>()?;
+            return Ok(TabulateItem::Order(input.parse()?));
+        }
+        Ok(TabulateItem::Dim(Box::new(input.parse()?)))
+    }
+}
+
+struct DenseDim {
+    name: Ident,
+    start: Expr,
+    end: proc_macro2::TokenStream,
+}
+
+impl Parse for DenseDim {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let range: Expr = input.parse()?;
+        let Expr::Range(syn::ExprRange { start: Some(start), limits, end: Some(end), .. }) = range else {
+            return Err(syn::Error::new(range.span(), "取值范围必须写成 start..end 或 start..=end"));
+        };
+        let end = match limits {
+            syn::RangeLimits::HalfOpen(_) => quote! { (#end) },
+            syn::RangeLimits::Closed(_) => quote! { (#end) + 1 },
+        };
+        Ok(DenseDim { name, start: *start, end })
+    }
+}
+
+fn parse_dense_table(key_args: &KeyArgs) -> Option<DenseTable> {
+    use syn::parse::Parser;
+
+    let panic_out_of_bounds = match key_args.named_args.get("out_of_bounds").map(|s| s.as_str()) {
+        None | Some("hash") => false,
+        Some("panic") => true,
+        Some(s) => panic!("无效的 out_of_bounds 选项 '{}'. 只支持 'hash' 或 'panic'", s),
+    };
+    if let Some(tokens) = key_args.list_args.get("tabulate") {
+        if key_args.list_args.contains_key("dense") {
+            panic!("tabulate(...) 已经使用稠密表，不需要再指定 dense(...)");
+        }
+        let items = Punctuated::<TabulateItem, Token![,]>::parse_terminated
+            .parse2(tokens.clone())
+            .unwrap_or_else(|err| panic!("无效的 tabulate(...): {}", err));
+        let mut dims = Vec::new();
+        let mut descending = false;
+        for item in items {
+            match item {
+                TabulateItem::Dim(dim) => dims.push((dim.name, dim.start, dim.end)),
+                TabulateItem::Order(order) => {
+                    descending = match order.to_string().as_str() {
+                        "ascending" => false,
+                        "descending" => true,
+                        s => panic!("无效的 tabulate order '{}'. 只支持 'ascending' 或 'descending'", s),
+                    }
+                }
+            }
+        }
+        if dims.is_empty() {
+            panic!("tabulate(...) 至少需要一个参数");
+        }
+        return Some(DenseTable {
+            dims: dims.clone(),
+            panic_out_of_bounds,
+            tabulate: Some(Tabulate { dims, descending }),
+        });
+    }
+    let Some(tokens) = key_args.list_args.get("dense") else {
+        if key_args.named_args.contains_key("out_of_bounds") {
+            panic!("out_of_bounds 需要同时指定 dense(...) 或 tabulate(...)");
+        }
+        return None;
+    };
+    let dims = Punctuated::<DenseDim, Token![,]>::parse_terminated
+        .parse2(tokens.clone())
+        .unwrap_or_else(|err| panic!("无效的 dense(...): {}", err));
+    if dims.is_empty() {
+        panic!("dense(...) 至少需要一个参数");
+    }
+    Some(DenseTable {
+        dims: dims.into_iter().map(|dim| (dim.name, dim.start, dim.end)).collect(),
+        panic_out_of_bounds,
+        tabulate: None,
+    })
+}
+
+// 解析正整数参数
+fn parse_positive_arg(key_args: &KeyArgs, name: &str) -> Option<u64> {
+    key_args.named_args.get(name).map(|s| {
+        let value: u64 = s
+            .parse()
+            .unwrap_or_else(|_| panic!("无效的 {} '{}'. 必须是正整数", name, s));
+        if value == 0 {
+            panic!("{} 必须大于 0", name);
+        }
+        value
+    })
+}
+
+// 解析缓存结构体配置：
+//   capacity=N, evict=lru|lfu|fifo  容量上限与淘汰策略
+//   ttl_ms=N                        条目过期时间
+//   dense(..), out_of_bounds=..     有界整数参数的稠密表
+//   hasher=std|fx|ahash_like|identity  哈希函数
+//   shards=N                        multi 模式按键的哈希分成 N 个 RwLock 分片
+fn parse_cache_options(key_args: &KeyArgs) -> CacheOptions {
+    let evict = key_args.named_args.get("evict").map(|s| match s.as_str() {
+        "lru" | "lfu" | "fifo" => s.clone(),
+        _ => panic!("无效的 evict 策略 '{}'. 只支持 'lru'、'lfu' 或 'fifo'", s),
+    });
+
+    let capacity = match parse_positive_arg(key_args, "capacity") {
+        Some(capacity) => Some((capacity as usize, evict.unwrap_or_else(|| "lru".to_string()))),
+        None => {
+            if evict.is_some() {
+                panic!("evict 需要同时指定 capacity");
+            }
+            None
+        }
+    };
+
+    let ttl_ms = parse_positive_arg(key_args, "ttl_ms");
+    let dense = parse_dense_table(key_args);
+    if dense.is_some() && (capacity.is_some() || ttl_ms.is_some()) {
+        panic!("dense(...) 不能与 capacity / ttl_ms 同时使用");
+    }
+
+    let hasher = match key_args.named_args.get("hasher").map(|s| s.as_str()) {
+        None => "std".to_string(),
+        Some(s @ ("std" | "fx" | "ahash_like" | "identity")) => s.to_string(),
+        Some(s) => panic!("无效的 hasher '{}'. 只支持 'std'、'fx'、'ahash_like' 或 'identity'", s),
+    };
+
+    let shards = parse_positive_arg(key_args, "shards").map(|shards| shards as usize);
+    if shards.is_some() {
+        if key_args.named_args.get("thread").map(|s| s.as_str()) != Some("multi") {
+            panic!("shards 只能用于 thread=multi");
+        }
+        if dense.is_some() {
+            panic!("shards 不能与 dense(...) 同时使用");
+        }
+    }
+    // 分片后容量上限平均分给每个分片
+    let declared_capacity = capacity.as_ref().map(|(capacity, _)| *capacity);
+    let capacity = match (capacity, shards) {
+        (Some((capacity, evict)), Some(shards)) => Some((capacity.div_ceil(shards), evict)),
+        (capacity, _) => capacity,
+    };
+
+    CacheOptions { capacity, declared_capacity, ttl_ms, dense, hasher, shards }
+}
+
+// 结果写入缓存的条件：cache_if = ok | some | path::to::predicate
+enum CacheIf {
+    Ok,
+    Some,
+    Predicate(syn::Path),
+}
+
+fn parse_cache_if(key_args: &KeyArgs) -> Option<CacheIf> {
+    let value = key_args.named_args.get("cache_if")?;
+    Some(match value.as_str() {
+        "ok" => CacheIf::Ok,
+        "some" => CacheIf::Some,
+        _ => match syn::parse_str::<syn::Path>(value) {
+            Ok(path) => CacheIf::Predicate(path),
+            Err(_) => panic!("无效的 cache_if 选项 '{}'. 只支持 'ok'、'some' 或谓词函数路径", value),
+        },
+    })
+}
+
+// share：缓存中保存 Rc<T>（single）或 Arc<T>（multi），命中时只增加引用计数，返回类型随之改为共享指针
+// 返回共享指针的路径
+fn parse_share(key_args: &KeyArgs, thread_mode: &str) -> Option<proc_macro2::TokenStream> {
+    if !key_args.flags.contains("share") {
+        return None;
+    }
+    Some(if thread_mode == "multi" {
+        quote! { ::std::sync::Arc }
+    } else {
+        quote! { ::std::rc::Rc }
+    })
+}
+
+// 自定义缓存键（替换按参数逐个生成的键）
+//   key_with = path::to::key_fn  键为 key_fn(参数..) 的返回值，参数传递方式与 cache_if 谓词相同
+//   symmetric(a, b, ..)           这些参数可以任意交换：按参数值排序后作为键
+//   sorted(v)                     v 视为多重集合：元素排序后的 Vec 作为键
+//   ignore(graph, scratch)        上下文参数不参与键，原样传给原函数体（允许 &mut）
+struct KeyCanon {
+    key_with: Option<syn::Path>,
+    symmetric: Vec<Ident>,
+    sorted: Vec<Ident>,
+    ignored: Vec<Ident>,
+}
+
+fn parse_key_canon(key_args: &KeyArgs) -> KeyCanon {
+    use syn::parse::Parser;
+
+    let idents = |name: &str| -> Vec<Ident> {
+        match key_args.list_args.get(name) {
+            None => Vec::new(),
+            Some(tokens) => Punctuated::<Ident, syn::Token![,]>::parse_terminated
+                .parse2(tokens.clone())
+                .unwrap_or_else(|_| panic!("{}(...) 只接受参数名列表", name))
+                .into_iter()
+                .collect(),
+        }
+    };
+    let key_with = key_args.named_args.get("key_with").map(|value| {
+        syn::parse_str::<syn::Path>(value)
+            .unwrap_or_else(|_| panic!("无效的 key_with 选项 '{}'. 需要键函数的路径", value))
+    });
+    let symmetric = idents("symmetric");
+    let sorted = idents("sorted");
+    let ignored = idents("ignore");
+
+    if key_args.list_args.contains_key("symmetric") && symmetric.len() < 2 {
+        panic!("symmetric(...) 至少需要两个参数");
+    }
+    if key_with.is_some() && !(symmetric.is_empty() && sorted.is_empty()) {
+        panic!("key_with 不能与 symmetric / sorted 同时使用");
+    }
+    if let Some(name) = ignored.iter().find(|name| symmetric.contains(name) || sorted.contains(name)) {
+        panic!("参数 {} 被 ignore 忽略，不能再用于 symmetric / sorted", name);
+    }
+    KeyCanon { key_with, symmetric, sorted, ignored }
+}
+
+// sorted(v) 的元素类型：Vec<T>、[T; N]、&[T]、&Vec<T>、&[T; N]
+fn sorted_element_type(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Reference(ty_ref) => sorted_element_type(&ty_ref.elem),
+        Type::Slice(slice) => Some(&slice.elem),
+        Type::Array(array) => Some(&array.elem),
+        Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            match &segment.arguments {
+                syn::PathArguments::AngleBracketed(args) if segment.ident == "Vec" && args.args.len() == 1 => {
+                    match args.args.first()? {
+                        syn::GenericArgument::Type(elem) => Some(elem),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// 根据 cache_if 给写入缓存的语句加上条件
+// 谓词依次接收每个参数（非引用参数传引用，引用参数原样传入）和结果的引用
+fn guard_cache_store(
+    cache_if: Option<&CacheIf>,
+    predicate_args: &[proc_macro2::TokenStream],
+    result: &Ident,
+    store: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let condition = match cache_if {
+        None => return store,
+        Some(CacheIf::Ok) => quote! { #result.is_ok() },
+        Some(CacheIf::Some) => quote! { #result.is_some() },
+        Some(CacheIf::Predicate(path)) => quote! { #path(#(#predicate_args,)* &#result) },
+    };
+    quote! {
+        if #condition {
+            #store
+        }
+    }
+}
+
+// 生成缓存哈希表使用的哈希函数，返回 (定义, BuildHasher 类型)
+// std 使用标准库的 SipHash（RandomState，抗 HashDoS）；其余为每个函数专用的无依赖哈希 MemoHasher{Fn}，
+// 固定种子、不抗 HashDoS，适合整数、元组等小键的热点递归：
+//   - fx:         rustc 使用的 FxHash，每个字 rotate + xor + 乘法
+//   - ahash_like: 每个字做一次 128 位折叠乘法，finish 时再混合一次，高位分布更好
+//   - identity:   单个整数键直接作为哈希值，多个部分简单混合（只建议用于整数键）
+fn generate_hasher(fn_name: &Ident, hasher: &str) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    if hasher == "std" {
+        return (quote! {}, quote! { ::std::collections::hash_map::RandomState });
+    }
+    let hasher_struct = Ident::new(
+        &format!("MemoHasher{}", to_upper_camel_case(&fn_name.to_string())),
+        fn_name.span()
+    );
+    let folded_multiply = if hasher == "ahash_like" {
+        quote! {
+            #[inline]
+            fn folded_multiply(a: u64, b: u64) -> u64 {
+                let full = (a as u128).wrapping_mul(b as u128);
+                (full as u64) ^ ((full >> 64) as u64)
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let (mix, finish) = match hasher {
+        "fx" => (
+            quote! { self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(0x517c_c1b7_2722_0a95); },
+            quote! { self.hash },
+        ),
+        "ahash_like" => (
+            quote! { self.hash = Self::folded_multiply(self.hash ^ word, 0x5851_f42d_4c95_7f2d); },
+            quote! { Self::folded_multiply(self.hash, 0x2d35_8dcc_aa6c_78a5).rotate_left((self.hash & 63) as u32) },
+        ),
+        _ => (
+            quote! { self.hash = self.hash.wrapping_mul(0x0100_0000_01b3) ^ word; },
+            quote! { self.hash },
+        ),
+    };
+    let items = quote! {
+        #[derive(Default)]
+        struct #hasher_struct {
+            hash: u64,
+        }
+
+        impl #hasher_struct {
+            #folded_multiply
+
+            #[inline]
+            fn add_word(&mut self, word: u64) {
+                #mix
+            }
+        }
+
+        impl ::std::hash::Hasher for #hasher_struct {
+            #[inline]
+            fn write(&mut self, bytes: &[u8]) {
+                let mut chunks = bytes.chunks_exact(8);
+                for chunk in &mut chunks {
+                    let mut word = [0u8; 8];
+                    word.copy_from_slice(chunk);
+                    self.add_word(u64::from_le_bytes(word));
+                }
+                let rest = chunks.remainder();
+                if !rest.is_empty() {
+                    let mut word = [0u8; 8];
+                    word[..rest.len()].copy_from_slice(rest);
+                    self.add_word(u64::from_le_bytes(word));
+                }
+            }
+
+            #[inline]
+            fn write_u8(&mut self, i: u8) { self.add_word(i as u64); }
+            #[inline]
+            fn write_u16(&mut self, i: u16) { self.add_word(i as u64); }
+            #[inline]
+            fn write_u32(&mut self, i: u32) { self.add_word(i as u64); }
+            #[inline]
+            fn write_u64(&mut self, i: u64) { self.add_word(i); }
+            #[inline]
+            fn write_usize(&mut self, i: usize) { self.add_word(i as u64); }
+
+            #[inline]
+            fn finish(&self) -> u64 {
+                #finish
+            }
+        }
+    };
+    (items, quote! { ::std::hash::BuildHasherDefault<#hasher_struct> })
+}
+
+// 生成每个函数专用的缓存结构体 MemoCache<K, V>
+// 不限容量时只是 HashMap 的薄包装；
+// 限容量时额外维护一个按 rank 排序的 BTreeMap 作为淘汰索引：
+//   - rank = (访问次数, 序号)，pop_first 即为淘汰对象
+//   - fifo: 序号为插入顺序，命中不更新
+//   - lru:  命中时序号更新为最新
+//   - lfu:  命中时访问次数 +1，次数相同按插入顺序淘汰
+// 设置 ttl_ms 时每个条目记录插入时间，过期条目在查询时移除并视为未命中；
+// 插入时距离上次清扫超过 ttl_ms 则移除全部过期条目，len 只统计未过期的条目
+fn generate_cache_struct(fn_name: &Ident, options: &CacheOptions) -> proc_macro2::TokenStream {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let cache_struct = Ident::new(&format!("MemoCache{}", camel), fn_name.span());
+    let entry_struct = Ident::new(&format!("MemoEntry{}", camel), fn_name.span());
+    let stats_struct = Ident::new(&format!("{}Stats", camel), fn_name.span());
+
+    let bounded = options.capacity.is_some();
+    let (hasher_items, build_hasher) = generate_hasher(fn_name, &options.hasher);
+
+    // 条目字段
+    let rank_field = if bounded { quote! { rank: (u64, u64), } } else { quote! {} };
+    let inserted_field = if options.ttl_ms.is_some() {
+        quote! { inserted: ::std::time::Instant, }
+    } else {
+        quote! {}
+    };
+
+    // 缓存字段
+    let (order_field, order_init) = if bounded {
+        (
+            quote! {
+                order: ::std::collections::BTreeMap<(u64, u64), K>,
+                tick: u64,
+            },
+            quote! {
+                order: ::std::collections::BTreeMap::new(),
+                tick: 0,
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    // 过期检查：过期条目直接移除
+    let expire_check = match options.ttl_ms {
+        Some(ttl_ms) => quote! {
+            let expired = match self.map.get(key) {
+                Some(entry) => entry.inserted.elapsed() >= ::std::time::Duration::from_millis(#ttl_ms),
+                None => return None,
+            };
+            if expired {
+                self.remove(key);
+                return None;
+            }
+        },
+        None => quote! {},
+    };
+
+    // 过期条目的清扫：每个 ttl_ms 周期最多遍历一次
+    let (purge_field, purge_init, purge_fn, purge_call) = match options.ttl_ms {
+        Some(ttl_ms) => {
+            let drop_order = if bounded { quote! { order.remove(&entry.rank); } } else { quote! {} };
+            let order_binding = if bounded { quote! { let order = &mut self.order; } } else { quote! {} };
+            (
+                quote! { last_purge: ::std::time::Instant, },
+                quote! { last_purge: ::std::time::Instant::now(), },
+                quote! {
+                    fn purge_expired(&mut self) {
+                        let ttl = ::std::time::Duration::from_millis(#ttl_ms);
+                        if self.last_purge.elapsed() < ttl {
+                            return;
+                        }
+                        self.last_purge = ::std::time::Instant::now();
+                        #order_binding
+                        self.map.retain(|_, entry| entry.inserted.elapsed() < ttl || {
+                            #drop_order
+                            false
+                        });
+                    }
+                },
+                quote! { self.purge_expired(); },
+            )
+        }
+        None => (quote! {}, quote! {}, quote! {}, quote! {}),
+    };
+
+    // 命中时如何更新 rank（fifo 不更新）
+    let get_body = match &options.capacity {
+        Some((_, evict)) if evict != "fifo" => {
+            let new_rank = if evict == "lfu" {
+                quote! { (entry.rank.0 + 1, entry.rank.1) }
+            } else {
+                quote! {{ self.tick += 1; (0, self.tick) }}
+            };
+            quote! {
+                let entry = self.map.get_mut(key)?;
+                let rank = #new_rank;
+                if let Some(k) = self.order.remove(&entry.rank) {
+                    self.order.insert(rank, k);
+                }
+                entry.rank = rank;
+                Some(entry.value.clone())
+            }
+        }
+        _ => quote! {
+            self.map.get(key).map(|entry| entry.value.clone())
+        },
+    };
+
+    // 插入新条目：限容量时先淘汰
+    let now = if options.ttl_ms.is_some() {
+        quote! { inserted: ::std::time::Instant::now(), }
+    } else {
+        quote! {}
+    };
+    let refresh = if options.ttl_ms.is_some() {
+        quote! { entry.inserted = ::std::time::Instant::now(); }
+    } else {
+        quote! {}
+    };
+    let insert_body = match &options.capacity {
+        Some((capacity, _)) => quote! {
+            // 已存在（多线程下可能并发计算同一个键）：只替换值
+            if let Some(entry) = self.map.get_mut(&key) {
+                entry.value = value;
+                #refresh
+                return;
+            }
+            if self.map.len() >= #capacity {
+                if let Some((_, victim)) = self.order.pop_first() {
+                    self.map.remove(&victim);
+                }
+            }
+            self.tick += 1;
+            let rank = (0, self.tick);
+            self.order.insert(rank, key.clone());
+            self.map.insert(key, #entry_struct { value, rank, #now });
+        },
+        None => quote! {
+            self.map.insert(key, #entry_struct { value, #now });
+        },
+    };
+
+    let remove_body = if bounded {
+        quote! {
+            if let Some(entry) = self.map.remove(key) {
+                self.order.remove(&entry.rank);
+            }
+        }
+    } else {
+        quote! {
+            self.map.remove(key);
+        }
+    };
+
+    let retain_body = if bounded {
+        quote! {
+            self.map.retain(|key, entry| keep(key, &entry.value) || {
+                self.order.remove(&entry.rank);
+                false
+            });
+        }
+    } else {
+        quote! {
+            self.map.retain(|key, entry| keep(key, &entry.value));
+        }
+    };
+
+    let clear_order = if bounded { quote! { self.order.clear(); } } else { quote! {} };
+
+    // 快照和条目数中不包含已过期的条目
+    let live_filter = match options.ttl_ms {
+        Some(ttl_ms) => quote! {
+            .filter(|(_, entry)| entry.inserted.elapsed() < ::std::time::Duration::from_millis(#ttl_ms))
+        },
+        None => quote! {},
+    };
+    let live_len = if options.ttl_ms.is_some() {
+        quote! { self.map.iter()#live_filter.count() }
+    } else {
+        quote! { self.map.len() }
+    };
+
+    // 分片时，命中不需要修改条目的缓存在读锁下查询
+    let peek_fn = if options.shards.is_some() && !bounded && options.ttl_ms.is_none() {
+        quote! {
+            fn peek(&self, key: &K) -> Option<V> {
+                self.map.get(key).map(|entry| entry.value.clone())
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #hasher_items
+
+        struct #entry_struct<V> {
+            value: V,
+            #rank_field
+            #inserted_field
+        }
+
+        struct #cache_struct<K, V> {
+            map: ::std::collections::HashMap<K, #entry_struct<V>, #build_hasher>,
+            #order_field
+            #purge_field
+            stats: #stats_struct,
+        }
+
+        impl<K: ::std::hash::Hash + Eq + Clone, V: Clone> #cache_struct<K, V> {
+            fn new() -> Self {
+                Self {
+                    map: ::std::collections::HashMap::default(),
+                    #order_init
+                    #purge_init
+                    stats: #stats_struct::default(),
+                }
+            }
+
+            fn get(&mut self, key: &K) -> Option<V> {
+                let result = self.lookup(key);
+                if result.is_some() {
+                    self.stats.hits += 1;
+                } else {
+                    self.stats.misses += 1;
+                }
+                result
+            }
+
+            fn lookup(&mut self, key: &K) -> Option<V> {
+                #expire_check
+                #get_body
+            }
+
+            fn insert(&mut self, key: K, value: V) {
+                self.store(key, value);
+                self.stats.inserts += 1;
+                self.stats.peak_len = self.stats.peak_len.max(self.map.len());
+            }
+
+            fn store(&mut self, key: K, value: V) {
+                #purge_call
+                #insert_body
+            }
+
+            #purge_fn
+
+            fn remove(&mut self, key: &K) {
+                #remove_body
+            }
+
+            fn retain(&mut self, mut keep: impl FnMut(&K, &V) -> bool) {
+                #retain_body
+            }
+
+            #peek_fn
+
+            // on_start：由 _start 清除（计入 clears），否则是手动清除（计入 manual_clears）
+            fn clear(&mut self, on_start: bool) {
+                self.map.clear();
+                #clear_order
+                if on_start {
+                    self.stats.clears += 1;
+                } else {
+                    self.stats.manual_clears += 1;
+                }
+            }
+
+            fn stats(&self) -> #stats_struct {
+                #stats_struct { len: #live_len, ..self.stats }
+            }
+
+            fn entries(&self) -> ::std::vec::Vec<(K, V)> {
+                self.map
+                    .iter()
+                    #live_filter
+                    .map(|(key, entry)| (key.clone(), entry.value.clone()))
+                    .collect()
+            }
+        }
+    }
+}
+
+// dense(...) 时的缓存结构体：接口与 MemoCache<K, V> 相同（new / get / insert / remove / retain / clear / stats）
+// 键在声明范围内时直接按行优先下标访问预分配的 Vec<Option<V>>，不做哈希；
+// 超出范围时退回哈希表，或在 out_of_bounds=panic 时 panic
+// 下标由 MemoDenseIndex{Fn} trait 为具体的键类型计算，new / clear / stats 不依赖键类型，
+// 方法的类型擦除缓存可以在只知道 K: Hash + Eq + Clone 时创建和清除缓存
+// dims 已按参数（即键中各部分）的顺序排列
+fn generate_dense_cache_struct(
+    fn_name: &Ident,
+    dense: &DenseTable,
+    hasher: &str,
+    key_type: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let cache_struct = Ident::new(&format!("MemoCache{}", camel), fn_name.span());
+    let stats_struct = Ident::new(&format!("{}Stats", camel), fn_name.span());
+    let index_trait = Ident::new(&format!("MemoDenseIndex{}", camel), fn_name.span());
+    let label = fn_name.to_string();
+
+    let parts: Vec<_> = (0..dense.dims.len())
+        .map(|i| Ident::new(&format!("__mau_dense_{}", i), proc_macro2::Span::call_site()))
+        .collect();
+    let destructure = if parts.len() == 1 {
+        quote! { let #(#parts)* = ::std::clone::Clone::clone(self); }
+    } else {
+        quote! { let (#(#parts),*) = ::std::clone::Clone::clone(self); }
+    };
+    let extents: Vec<_> = dense
+        .dims
+        .iter()
+        .map(|(_, start, end)| quote! { (#end) as i128 - (#start) as i128 })
+        .collect();
+    let out_of_bounds = |name: &Ident, part: &Ident, start: &Expr, end: &proc_macro2::TokenStream| {
+        if dense.panic_out_of_bounds {
+            let name = name.to_string();
+            quote! {
+                panic!(
+                    "{}: 参数 {} = {} 超出 dense 声明的范围 {}..{}",
+                    #label, #name, #part, (#start) as i128, (#end) as i128
+                )
+            }
+        } else {
+            quote! { return None }
+        }
+    };
+    let offsets = dense.dims.iter().zip(&parts).zip(&extents).map(|(((name, start, end), part), extent)| {
+        let out_of_bounds = out_of_bounds(name, part, start, end);
+        quote! {
+            let extent = #extent;
+            let offset = #part as i128 - (#start) as i128;
+            if offset < 0 || offset >= extent {
+                #out_of_bounds;
+            }
+            let index = index * extent as usize + offset as usize;
+        }
+    });
+
+    // 由下标还原键（快照使用）：第 k 维的偏移为 index / (之后各维长度之积) % 第 k 维长度
+    let restore = dense.dims.iter().zip(&parts).enumerate().map(|(k, ((_, start, _), part))| {
+        let extent = &extents[k];
+        let inner = &extents[k + 1..];
+        quote! {
+            let #part = ((#start) as i128
+                + (index / (1usize #(* (#inner) as usize)*) % (#extent) as usize) as i128) as _;
+        }
+    });
+    let restored = if parts.len() == 1 { quote! { #(#parts)* } } else { quote! { (#(#parts),*) } };
+
+    // 超出范围时的哈希表
+    let (hasher_items, build_hasher) = generate_hasher(fn_name, hasher);
+    let (fallback_field, fallback_init, fallback_get, fallback_insert, fallback_clear, fallback_len) =
+        if dense.panic_out_of_bounds {
+            (quote! {}, quote! {}, quote! { None }, quote! {}, quote! {}, quote! { 0 })
+        } else {
+            (
+                quote! { fallback: ::std::collections::HashMap<K, V, #build_hasher>, },
+                quote! { fallback: ::std::collections::HashMap::default(), },
+                quote! { self.fallback.get(key).cloned() },
+                quote! { self.fallback.insert(key, value); },
+                quote! { self.fallback.clear(); },
+                quote! { self.fallback.len() },
+            )
+        };
+    let (fallback_entries, fallback_remove, fallback_retain) = if dense.panic_out_of_bounds {
+        (quote! {}, quote! {}, quote! {})
+    } else {
+        (
+            quote! {
+                entries.extend(self.fallback.iter().map(|(key, value)| (key.clone(), value.clone())));
+            },
+            quote! { self.fallback.remove(key); },
+            quote! { self.fallback.retain(|key, value| keep(key, value)); },
+        )
+    };
+
+    let hasher_items = if dense.panic_out_of_bounds { quote! {} } else { hasher_items };
+
+    quote! {
+        #hasher_items
+
+        trait #index_trait {
+            fn dense_index(&self) -> Option<usize>;
+            fn from_dense_index(index: usize) -> Self;
+        }
+
+        impl #index_trait for #key_type {
+            fn dense_index(&self) -> Option<usize> {
+                #destructure
+                let index = 0usize;
+                #(#offsets)*
+                Some(index)
+            }
+
+            fn from_dense_index(index: usize) -> Self {
+                #(#restore)*
+                #restored
+            }
+        }
+
+        struct #cache_struct<K, V> {
+            table: ::std::vec::Vec<Option<V>>,
+            filled: usize,
+            #fallback_field
+            stats: #stats_struct,
+            _key: ::std::marker::PhantomData<K>,
+        }
+
+        impl<K, V> #cache_struct<K, V> {
+            fn new() -> Self {
+                let size = [#(#extents),*].iter().map(|&extent| extent.max(0) as usize).product();
+                Self {
+                    table: ::std::iter::repeat_with(|| None).take(size).collect(),
+                    filled: 0,
+                    #fallback_init
+                    stats: #stats_struct::default(),
+                    _key: ::std::marker::PhantomData,
+                }
+            }
+
+            fn len(&self) -> usize {
+                self.filled + #fallback_len
+            }
+
+            fn clear(&mut self, on_start: bool) {
+                if self.filled > 0 {
+                    self.table.iter_mut().for_each(|slot| *slot = None);
+                    self.filled = 0;
+                }
+                #fallback_clear
+                if on_start {
+                    self.stats.clears += 1;
+                } else {
+                    self.stats.manual_clears += 1;
+                }
+            }
+
+            fn stats(&self) -> #stats_struct {
+                #stats_struct { len: self.len(), ..self.stats }
+            }
+        }
+
+        impl<K: ::std::hash::Hash + Eq + Clone + #index_trait, V: Clone> #cache_struct<K, V> {
+            fn get(&mut self, key: &K) -> Option<V> {
+                let result = self.lookup(key);
+                if result.is_some() {
+                    self.stats.hits += 1;
+                } else {
+                    self.stats.misses += 1;
+                }
+                result
+            }
+
+            fn lookup(&mut self, key: &K) -> Option<V> {
+                match key.dense_index() {
+                    Some(index) => self.table[index].clone(),
+                    None => #fallback_get,
+                }
+            }
+
+            fn entries(&self) -> ::std::vec::Vec<(K, V)> {
+                let mut entries: ::std::vec::Vec<(K, V)> = self
+                    .table
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, slot)| slot.as_ref().map(|value| (K::from_dense_index(index), value.clone())))
+                    .collect();
+                #fallback_entries
+                entries
+            }
+
+            fn insert(&mut self, key: K, value: V) {
+                match key.dense_index() {
+                    Some(index) => {
+                        if self.table[index].replace(value).is_none() {
+                            self.filled += 1;
+                        }
+                    }
+                    None => { #fallback_insert }
+                }
+                self.stats.inserts += 1;
+                self.stats.peak_len = self.stats.peak_len.max(self.len());
+            }
+
+            fn remove(&mut self, key: &K) {
+                match key.dense_index() {
+                    Some(index) => {
+                        if self.table[index].take().is_some() {
+                            self.filled -= 1;
+                        }
+                    }
+                    None => { #fallback_remove }
+                }
+            }
+
+            fn retain(&mut self, mut keep: impl FnMut(&K, &V) -> bool) {
+                for (index, slot) in self.table.iter_mut().enumerate() {
+                    if slot.as_ref().is_some_and(|value| !keep(&K::from_dense_index(index), value)) {
+                        *slot = None;
+                        self.filled -= 1;
+                    }
+                }
+                #fallback_retain
+            }
+        }
+    }
+}
+
+// 生成 multi 模式的分片缓存 MemoShards{Fn}<K, V>：N 个 RwLock<MemoCache<K, V>>，按键的哈希选择分片
+// 接口与 Mutex<MemoCache> 的用法对应（get / insert / remove / retain / clear / stats），但只需要 &self：
+//   - 不限容量且没有 ttl 时，命中不修改条目，查询只取读锁，多个线程可以同时读同一个分片
+//   - 限容量（命中要更新淘汰顺序）或有 ttl（过期条目要移除）时查询取写锁，仍然只锁一个分片
+//   - hits / misses 是每个分片自己的原子计数，读锁下也能更新；分片按缓存行对齐，避免伪共享
+//   - _stats 汇总所有分片（clears 取最大值）
+// 分片下标取哈希乘以黄金比例常数后的中间位，避免与分片内 HashMap 使用的低位、高 7 位相关
+fn generate_sharded_cache_struct(fn_name: &Ident, options: &CacheOptions) -> proc_macro2::TokenStream {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let cache_struct = Ident::new(&format!("MemoCache{}", camel), fn_name.span());
+    let shard_struct = Ident::new(&format!("MemoShard{}", camel), fn_name.span());
+    let shards_struct = Ident::new(&format!("MemoShards{}", camel), fn_name.span());
+    let stats_struct = Ident::new(&format!("{}Stats", camel), fn_name.span());
+    let shards = options.shards.unwrap_or(1);
+    let (_, build_hasher) = generate_hasher(fn_name, &options.hasher);
+
+    let lookup = if options.capacity.is_none() && options.ttl_ms.is_none() {
+        quote! { shard.cache.read().unwrap().peek(key) }
+    } else {
+        quote! { shard.cache.write().unwrap().lookup(key) }
+    };
+
+    quote! {
+        #[repr(align(128))]
+        struct #shard_struct<K, V> {
+            cache: ::std::sync::RwLock<#cache_struct<K, V>>,
+            hits: ::std::sync::atomic::AtomicU64,
+            misses: ::std::sync::atomic::AtomicU64,
+        }
+
+        struct #shards_struct<K, V> {
+            shards: ::std::boxed::Box<[#shard_struct<K, V>]>,
+            hash_builder: #build_hasher,
+        }
+
+        impl<K: ::std::hash::Hash + Eq + Clone, V: Clone> #shards_struct<K, V> {
+            fn new() -> Self {
+                Self {
+                    shards: (0..#shards)
+                        .map(|_| #shard_struct {
+                            cache: ::std::sync::RwLock::new(#cache_struct::new()),
+                            hits: ::std::sync::atomic::AtomicU64::new(0),
+                            misses: ::std::sync::atomic::AtomicU64::new(0),
+                        })
+                        .collect(),
+                    hash_builder: ::std::default::Default::default(),
+                }
+            }
+
+            fn shard(&self, key: &K) -> &#shard_struct<K, V> {
+                use ::std::hash::BuildHasher;
+                let hash = self.hash_builder.hash_one(key).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                &self.shards[(hash >> 32) as usize % #shards]
+            }
+
+            fn get(&self, key: &K) -> Option<V> {
+                let shard = self.shard(key);
+                let result = #lookup;
+                let counter = if result.is_some() { &shard.hits } else { &shard.misses };
+                counter.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);
+                result
+            }
+
+            fn lookup(&self, key: &K) -> Option<V> {
+                let shard = self.shard(key);
+                #lookup
+            }
+
+            fn insert(&self, key: K, value: V) {
+                self.shard(&key).cache.write().unwrap().insert(key, value);
+            }
+
+            fn remove(&self, key: &K) {
+                self.shard(key).cache.write().unwrap().remove(key);
+            }
+
+            fn retain(&self, mut keep: impl FnMut(&K, &V) -> bool) {
+                for shard in self.shards.iter() {
+                    shard.cache.write().unwrap().retain(&mut keep);
+                }
+            }
+
+            fn clear(&self, on_start: bool) {
+                for shard in self.shards.iter() {
+                    shard.cache.write().unwrap().clear(on_start);
+                }
+            }
+
+            fn entries(&self) -> ::std::vec::Vec<(K, V)> {
+                self.shards.iter().flat_map(|shard| shard.cache.read().unwrap().entries()).collect()
+            }
+
+            fn stats(&self) -> #stats_struct {
+                let mut stats = #stats_struct::default();
+                for shard in self.shards.iter() {
+                    let cache = shard.cache.read().unwrap().stats();
+                    stats.hits += shard.hits.load(::std::sync::atomic::Ordering::Relaxed);
+                    stats.misses += shard.misses.load(::std::sync::atomic::Ordering::Relaxed);
+                    stats.inserts += cache.inserts;
+                    stats.len += cache.len;
+                    stats.peak_len += cache.peak_len;
+                    stats.clears = stats.clears.max(cache.clears);
+                    stats.manual_clears = stats.manual_clears.max(cache.manual_clears);
+                }
+                stats
+            }
+        }
+    }
+}
+
+// 生成统计信息结构体 {Fn}Stats，由 {fn}_stats() 返回
+fn generate_stats_struct(fn_name: &Ident, fn_vis: &syn::Visibility) -> proc_macro2::TokenStream {
+    let stats_struct = Ident::new(
+        &format!("{}Stats", to_upper_camel_case(&fn_name.to_string())),
+        fn_name.span()
+    );
+    let doc = format!("`{}` 的缓存统计信息", fn_name);
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        #fn_vis struct #stats_struct {
+            /// 缓存命中次数
+            pub hits: u64,
+            /// 缓存未命中次数
+            pub misses: u64,
+            /// 写入缓存的次数
+            pub inserts: u64,
+            /// 当前条目数
+            pub len: usize,
+            /// 历史最大条目数
+            pub peak_len: usize,
+            /// `_start` 返回前自动清除缓存的次数
+            pub clears: u64,
+            /// 手动清除缓存的次数（`_clear` 等）
+            pub manual_clears: u64,
+        }
+
+        impl #stats_struct {
+            /// 命中率，没有任何查询时返回 0.0
+            pub fn hit_rate(&self) -> f64 {
+                let total = self.hits + self.misses;
+                if total == 0 { 0.0 } else { self.hits as f64 / total as f64 }
+            }
+        }
+    }
+}
+
+// 生成程序退出时打印统计摘要的代码（stats=print）
+// 通过 C 运行时的 atexit 注册回调，第一次调用函数时注册一次。
+// single 模式下缓存是线程局部的：线程退出时把本线程的统计合并到全局汇总，
+// 退出回调再读取当前线程（若线程局部变量仍可访问）的统计，二者不会重复计算。
+// stats_expr 是读取统计的表达式，其中 cache 为缓存静态变量中保存的值
+fn generate_stats_report(
+    fn_name: &Ident,
+    cache_name: &Ident,
+    thread_mode: &str,
+    sharded: bool,
+    stats_expr: &proc_macro2::TokenStream,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let stats_struct = Ident::new(&format!("{}Stats", camel), fn_name.span());
+    let cache_struct = Ident::new(&format!("MemoCache{}", camel), fn_name.span());
+    let report_name = Ident::new(&format!("__mau_report_{}", fn_name), fn_name.span());
+    let total_name = Ident::new(&format!("{}_STATS_TOTAL", fn_name.to_string().to_uppercase()), fn_name.span());
+    let label = fn_name.to_string();
+
+    let (collect, merge_on_drop) = if sharded {
+        (quote! { let stats = #stats_expr; }, quote! {})
+    } else if thread_mode == "multi" {
+        (
+            quote! {
+                let stats = #cache_name.lock().map(|cache| #stats_expr).unwrap_or_default();
+            },
+            quote! {},
+        )
+    } else {
+        (
+            quote! {
+                let mut stats = #total_name.lock().map(|total| *total).unwrap_or_default();
+                if let Ok(local) = #cache_name.try_with(|cache| { let cache = cache.borrow(); #stats_expr }) {
+                    stats.hits += local.hits;
+                    stats.misses += local.misses;
+                    stats.inserts += local.inserts;
+                    stats.len += local.len;
+                    stats.peak_len = stats.peak_len.max(local.peak_len);
+                    stats.clears += local.clears;
+                    stats.manual_clears += local.manual_clears;
+                }
+            },
+            quote! {
+                static #total_name: ::std::sync::Mutex<#stats_struct> = ::std::sync::Mutex::new(#stats_struct {
+                    hits: 0, misses: 0, inserts: 0, len: 0, peak_len: 0, clears: 0, manual_clears: 0,
+                });
+
+                impl<K, V> Drop for #cache_struct<K, V> {
+                    fn drop(&mut self) {
+                        if let Ok(mut total) = #total_name.lock() {
+                            total.hits += self.stats.hits;
+                            total.misses += self.stats.misses;
+                            total.inserts += self.stats.inserts;
+                            total.peak_len = total.peak_len.max(self.stats.peak_len);
+                            total.clears += self.stats.clears;
+                            total.manual_clears += self.stats.manual_clears;
+                        }
+                    }
+                }
+            },
+        )
+    };
+
+    let items = quote! {
+        #merge_on_drop
+
+        extern "C" fn #report_name() {
+            use ::std::io::Write;
+            #collect
+            let _ = writeln!(
+                ::std::io::stderr(),
+                "[mau] {}: hits={} misses={} hit_rate={:.1}% inserts={} len={} peak_len={} clears={} manual_clears={}",
+                #label, stats.hits, stats.misses, stats.hit_rate() * 100.0,
+                stats.inserts, stats.len, stats.peak_len, stats.clears, stats.manual_clears,
+            );
+        }
+    };
+
+    let register = quote! {
+        static __MAU_REPORT_REGISTERED: ::std::sync::Once = ::std::sync::Once::new();
+        __MAU_REPORT_REGISTERED.call_once(|| {
+            unsafe extern "C" {
+                fn atexit(callback: extern "C" fn()) -> ::std::os::raw::c_int;
+            }
+            unsafe { atexit(#report_name); }
+        });
+    };
+
+    (items, register)
+}
+
+// persist="path"：缓存保存在文件中，跨进程复用
+//   persist_interval_ms=N  除了 _clear 和程序退出时，距离上次写回超过 N 毫秒的插入也会写回文件
+struct Persist {
+    path: String,
+    interval_ms: Option<u64>,
+}
+
+fn parse_persist(key_args: &KeyArgs) -> Option<Persist> {
+    let interval_ms = parse_positive_arg(key_args, "persist_interval_ms");
+    let Some(path) = key_args.named_args.get("persist") else {
+        if interval_ms.is_some() {
+            panic!("persist_interval_ms 需要同时设置 persist");
+        }
+        return None;
+    };
+    if path.is_empty() {
+        panic!("persist 的文件路径不能为空");
+    }
+    Some(Persist { path: path.clone(), interval_ms })
+}
+
+// stack=grow：_start 在一个大栈的新线程中执行，深层递归不会溢出调用者的栈
+//   stack_mb=N  新线程的栈大小（MiB），默认 1024；只保留地址空间，实际用到多少才占用多少内存
+// 返回栈大小（字节）
+fn parse_stack(key_args: &KeyArgs) -> Option<usize> {
+    let stack_mb = parse_positive_arg(key_args, "stack_mb");
+    match key_args.named_args.get("stack").map(|s| s.as_str()) {
+        None => {
+            if stack_mb.is_some() {
+                panic!("stack_mb 需要同时设置 stack=grow");
+            }
+            None
+        }
+        Some("grow") => Some(stack_mb.unwrap_or(1024) as usize * 1024 * 1024),
+        Some(s) => panic!("无效的 stack 选项 '{}'. 只支持 'grow'", s),
+    }
+}
+
+// 把 _start 的函数体放到大栈的作用域线程中执行：参数可以借用调用者的数据，
+// 结果原样返回，panic 通过 resume_unwind 以原来的 payload 传回调用者。
+// single 模式下函数体中的清除缓存也在新线程中执行，清除的正是计算用到的线程局部缓存
+fn grow_stack(fn_name: &Ident, stack_bytes: usize, body: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let label = fn_name.to_string();
+    quote! {
+        ::std::thread::scope(|__mau_scope| {
+            let __mau_worker = ::std::thread::Builder::new()
+                .name(::std::string::String::from(#label))
+                .stack_size(#stack_bytes)
+                .spawn_scoped(__mau_scope, move || { #body })
+                .expect("memo: 无法创建 stack=grow 的计算线程");
+            match __mau_worker.join() {
+                Ok(result) => result,
+                Err(payload) => ::std::panic::resume_unwind(payload),
+            }
+        })
+    }
+}
+
+// on_cycle：同一个键在自己的计算过程中被再次调用（循环调用）时的处理方式
+//   未设置          不检测（每次未命中都要查找计算中的键表并克隆键，且要求键类型为 'static）
+//   cycle_check     debug 构建中检测并 panic，release 构建不检测（没有额外开销）
+//   on_cycle=panic  任何构建中都检测并 panic
+//   on_cycle=表达式 任何构建中都检测，返回该值作为这次调用的结果（不写入缓存）
+enum OnCycle {
+    Off,
+    Debug,
+    Panic,
+    Value(Expr),
+}
+
+fn parse_on_cycle(key_args: &KeyArgs) -> OnCycle {
+    match key_args.named_args.get("on_cycle").map(|s| s.as_str()) {
+        None if key_args.flags.contains("cycle_check") => OnCycle::Debug,
+        None => OnCycle::Off,
+        Some("panic") => OnCycle::Panic,
+        Some(value) => OnCycle::Value(
+            syn::parse_str(value).unwrap_or_else(|_| panic!("无效的 on_cycle 值 '{}'", value)),
+        ),
+    }
+}
+
+// 生成循环调用检测所需的定义（计算中的键表、进入/退出、键的显示）
+// 每个线程记录正在计算的键：stack 保存调用路径，index 记录每个键在路径中的位置。
+// 与 single-flight 的登记表一样按 TypeId 区分 (键类型, 类型参数)，泛型函数和 key_with 也适用。
+// 离开计算（包括 panic 展开）时由 MemoCycleGuard{Fn} 出栈。
+fn generate_cycle_items(fn_name: &Ident, on_cycle: &OnCycle) -> proc_macro2::TokenStream {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let computing_struct = Ident::new(&format!("MemoComputing{}", camel), fn_name.span());
+    let guard_struct = Ident::new(&format!("MemoCycleGuard{}", camel), fn_name.span());
+    let table_name = Ident::new(&format!("{}_COMPUTING", fn_name.to_string().to_uppercase()), fn_name.span());
+    let computing_name = Ident::new(&format!("__mau_computing_{}", fn_name), fn_name.span());
+    let enter_name = Ident::new(&format!("__mau_enter_{}", fn_name), fn_name.span());
+    let cfg = match on_cycle {
+        OnCycle::Debug => quote! { #[cfg(debug_assertions)] },
+        _ => quote! {},
+    };
+    let tables_ty = quote! {
+        ::std::collections::HashMap<::std::any::TypeId, ::std::boxed::Box<dyn ::std::any::Any>>
+    };
+
+    quote! {
+        #cfg
+        struct #computing_struct<K> {
+            stack: ::std::vec::Vec<K>,
+            index: ::std::collections::HashMap<K, usize>,
+        }
+
+        #cfg
+        ::std::thread_local! {
+            static #table_name: ::std::cell::RefCell<#tables_ty> =
+                ::std::cell::RefCell::new(::std::collections::HashMap::new());
+        }
+
+        #cfg
+        fn #computing_name<K: ::std::hash::Hash + Eq + 'static, P: 'static>(
+            tables: &mut #tables_ty,
+        ) -> &mut #computing_struct<K> {
+            tables
+                .entry(::std::any::TypeId::of::<(K, P)>())
+                .or_insert_with(|| ::std::boxed::Box::new(#computing_struct::<K> {
+                    stack: ::std::vec::Vec::new(),
+                    index: ::std::collections::HashMap::new(),
+                }))
+                .downcast_mut()
+                .expect("memo: computing table type mismatch")
+        }
+
+        #cfg
+        struct #guard_struct<K: ::std::hash::Hash + Eq + 'static, P: 'static>(::std::marker::PhantomData<fn() -> (K, P)>);
+
+        #cfg
+        impl<K: ::std::hash::Hash + Eq + 'static, P: 'static> Drop for #guard_struct<K, P> {
+            fn drop(&mut self) {
+                let _ = #table_name.try_with(|tables| {
+                    let mut tables = tables.borrow_mut();
+                    let computing = #computing_name::<K, P>(&mut tables);
+                    if let Some(key) = computing.stack.pop() {
+                        computing.index.remove(&key);
+                    }
+                });
+            }
+        }
+
+        // 开始计算 key；key 已经在计算中时返回从它开始的调用路径
+        #cfg
+        fn #enter_name<K: ::std::hash::Hash + Eq + Clone + 'static, P: 'static>(
+            key: &K,
+            describe: fn(&K) -> ::std::string::String,
+        ) -> ::std::result::Result<#guard_struct<K, P>, ::std::string::String> {
+            #table_name.with(|tables| {
+                let mut tables = tables.borrow_mut();
+                let computing = #computing_name::<K, P>(&mut tables);
+                if let Some(&start) = computing.index.get(key) {
+                    let path: ::std::vec::Vec<_> = computing.stack[start..]
+                        .iter()
+                        .chain(::std::iter::once(key))
+                        .map(describe)
+                        .collect();
+                    return Err(path.join(" → "));
+                }
+                computing.index.insert(key.clone(), computing.stack.len());
+                computing.stack.push(key.clone());
+                Ok(#guard_struct(::std::marker::PhantomData))
+            })
+        }
+    }
+}
+
+// 生成在消息中显示键的定义：MemoKey{Fn}(&key) 通过自动引用选择 trait 实现，
+// 键实现了 Debug 时显示键的值，否则显示 ".."（多个部分的键显示 "(..)"）
+fn generate_key_describe_items(
+    fn_name: &Ident,
+    key_is_tuple: bool,
+    cfg: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let key_struct = Ident::new(&format!("MemoKey{}", camel), fn_name.span());
+    let debug_trait = Ident::new(&format!("MemoKeyDebug{}", camel), fn_name.span());
+    let opaque_trait = Ident::new(&format!("MemoKeyOpaque{}", camel), fn_name.span());
+    let opaque = if key_is_tuple { "(..)" } else { ".." };
+
+    quote! {
+        #cfg
+        struct #key_struct<'a, K>(&'a K);
+
+        #cfg
+        trait #debug_trait {
+            fn describe(&self) -> ::std::string::String;
+        }
+
+        #cfg
+        impl<K: ::std::fmt::Debug> #debug_trait for #key_struct<'_, K> {
+            fn describe(&self) -> ::std::string::String {
+                format!("{:?}", self.0)
+            }
+        }
+
+        #cfg
+        trait #opaque_trait {
+            fn describe(&self) -> ::std::string::String;
+        }
+
+        #cfg
+        impl<K> #opaque_trait for &#key_struct<'_, K> {
+            fn describe(&self) -> ::std::string::String {
+                ::std::string::String::from(#opaque)
+            }
+        }
+    }
+}
+
+// 按函数调用的形式显示键（key 为 &K 表达式），用于循环路径、fixpoint 和 tabulate 的错误消息
+fn describe_key(fn_name: &Ident, key_is_tuple: bool, key: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let key_struct = Ident::new(
+        &format!("MemoKey{}", to_upper_camel_case(&fn_name.to_string())),
+        fn_name.span()
+    );
+    let label = fn_name.to_string();
+    let format = if key_is_tuple { format!("{}{{}}", label) } else { format!("{}({{}})", label) };
+    quote! { format!(#format, (&#key_struct(#key)).describe()) }
+}
+
+// 显示键的闭包（fn(&K) -> String）
+fn key_describer(fn_name: &Ident, key_is_tuple: bool) -> proc_macro2::TokenStream {
+    let describe = describe_key(fn_name, key_is_tuple, quote! { key });
+    quote! { |key| #describe }
+}
+
+// 缓存未命中、开始计算之前的循环检测语句：守卫一直保留到作用域结束（写入缓存之后）
+// sentinel 为 on_cycle=表达式 时返回的值（已按 share 包装）
+fn generate_cycle_check(
+    fn_name: &Ident,
+    on_cycle: &OnCycle,
+    key_is_tuple: bool,
+    key: &Ident,
+    type_params: &[Ident],
+    sentinel: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let enter_name = Ident::new(&format!("__mau_enter_{}", fn_name), fn_name.span());
+    let label = fn_name.to_string();
+    let describe = key_describer(fn_name, key_is_tuple);
+    let (cfg, on_detected) = match on_cycle {
+        OnCycle::Debug => (
+            quote! { #[cfg(debug_assertions)] },
+            quote! { panic!("{}: 检测到循环调用 {}", #label, path) },
+        ),
+        OnCycle::Panic => (quote! {}, quote! { panic!("{}: 检测到循环调用 {}", #label, path) }),
+        OnCycle::Value(_) => (quote! {}, quote! {{ let _ = path; return #sentinel; }}),
+        OnCycle::Off => return quote! {},
+    };
+    quote! {
+        #cfg
+        let __mau_cycle_guard = match #enter_name::<_, (#(#type_params,)*)>(&#key, #describe) {
+            Ok(guard) => guard,
+            Err(path) => #on_detected,
+        };
+    }
+}
+
+// fixpoint=底值：循环的递归定义（图上的可达性、带环的最短路、博弈值等）按最小不动点求值
+//   fixpoint_limit=N  每个循环入口最多重新计算 N 轮（默认 1000），仍未收敛时 panic
+struct Fixpoint {
+    bottom: Expr,
+    limit: u64,
+}
+
+fn parse_fixpoint(key_args: &KeyArgs) -> Option<Fixpoint> {
+    let limit = parse_positive_arg(key_args, "fixpoint_limit");
+    let Some(bottom) = key_args.named_args.get("fixpoint") else {
+        if limit.is_some() {
+            panic!("fixpoint_limit 需要同时设置 fixpoint");
+        }
+        return None;
+    };
+    let bottom = syn::parse_str(bottom).unwrap_or_else(|_| panic!("无效的 fixpoint 底值 '{}'", bottom));
+    Some(Fixpoint { bottom, limit: limit.unwrap_or(1000) })
+}
+
+// 生成 fixpoint 求值所需的定义。每个线程一张 MemoFixpoint{Fn}<K, V>（按 TypeId 区分）：
+//   stack / index  正在计算的键及其深度；每帧的 low 是计算中遇到的最浅的正在计算的键的深度
+//   approx         被再次进入过的键的当前近似值（从底值开始）
+//   tentative      依赖了正在计算的键的结果（暂定值，附带 low），不写入缓存
+// 再次进入正在计算的键时返回近似值；一帧结束时若 low 小于自己的深度，结果为暂定值，
+// 否则它是循环的入口：本轮有近似值改变就清除暂定值重新计算，没有改变则已收敛，
+// 本轮的暂定值和入口的结果都是最终结果，一起写入缓存
+fn generate_fixpoint_items(fn_name: &Ident) -> proc_macro2::TokenStream {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let frame_struct = Ident::new(&format!("MemoFixpointFrame{}", camel), fn_name.span());
+    let fixpoint_struct = Ident::new(&format!("MemoFixpoint{}", camel), fn_name.span());
+    let step_enum = Ident::new(&format!("MemoFixpointStep{}", camel), fn_name.span());
+    let guard_struct = Ident::new(&format!("MemoFixpointGuard{}", camel), fn_name.span());
+    let table_name = Ident::new(&format!("{}_FIXPOINT", fn_name.to_string().to_uppercase()), fn_name.span());
+    let open_name = Ident::new(&format!("__mau_fixpoint_{}", fn_name), fn_name.span());
+    let enter_name = Ident::new(&format!("__mau_fixpoint_enter_{}", fn_name), fn_name.span());
+    let finish_name = Ident::new(&format!("__mau_fixpoint_finish_{}", fn_name), fn_name.span());
+    let tables_ty = quote! {
+        ::std::collections::HashMap<::std::any::TypeId, ::std::boxed::Box<dyn ::std::any::Any>>
+    };
+    let bounds = quote! {
+        K: ::std::hash::Hash + Eq + Clone + 'static, V: Clone + PartialEq + 'static, P: 'static
+    };
+
+    quote! {
+        struct #frame_struct<K> {
+            key: K,
+            low: usize,
+            changed: bool,
+            rounds: u64,
+        }
+
+        struct #fixpoint_struct<K, V> {
+            stack: ::std::vec::Vec<#frame_struct<K>>,
+            index: ::std::collections::HashMap<K, usize>,
+            approx: ::std::collections::HashMap<K, V>,
+            tentative: ::std::collections::HashMap<K, (V, usize)>,
+        }
+
+        enum #step_enum<K, V> {
+            // 结果依赖正在计算的键，只返回不缓存
+            Tentative,
+            // 循环入口：近似值有改变，重新计算
+            Again,
+            // 收敛：写入缓存的暂定结果（入口自己的结果由调用方写入）
+            Done(::std::vec::Vec<(K, V)>),
+            // 超过迭代上限：循环入口
+            Diverged(::std::string::String),
+        }
+
+        ::std::thread_local! {
+            static #table_name: ::std::cell::RefCell<#tables_ty> =
+                ::std::cell::RefCell::new(::std::collections::HashMap::new());
+        }
+
+        fn #open_name<#bounds>(tables: &mut #tables_ty) -> &mut #fixpoint_struct<K, V> {
+            tables
+                .entry(::std::any::TypeId::of::<(K, V, P)>())
+                .or_insert_with(|| ::std::boxed::Box::new(#fixpoint_struct::<K, V> {
+                    stack: ::std::vec::Vec::new(),
+                    index: ::std::collections::HashMap::new(),
+                    approx: ::std::collections::HashMap::new(),
+                    tentative: ::std::collections::HashMap::new(),
+                }))
+                .downcast_mut()
+                .expect("memo: fixpoint table type mismatch")
+        }
+
+        // panic 展开时移除这一帧及其上的所有状态
+        struct #guard_struct<#bounds> {
+            depth: usize,
+            _types: ::std::marker::PhantomData<fn() -> (K, V, P)>,
+        }
+
+        impl<#bounds> Drop for #guard_struct<K, V, P> {
+            fn drop(&mut self) {
+                if !::std::thread::panicking() {
+                    return;
+                }
+                let depth = self.depth;
+                let _ = #table_name.try_with(|tables| {
+                    let mut tables = tables.borrow_mut();
+                    let fixpoint = #open_name::<K, V, P>(&mut tables);
+                    while fixpoint.stack.len() > depth {
+                        if let Some(frame) = fixpoint.stack.pop() {
+                            fixpoint.index.remove(&frame.key);
+                        }
+                    }
+                    fixpoint.tentative.retain(|_, entry| entry.1 < depth);
+                    if fixpoint.stack.is_empty() {
+                        fixpoint.approx.clear();
+                    }
+                });
+            }
+        }
+
+        // 开始计算 key：正在计算或已有暂定值时返回 Err(当前值)
+        fn #enter_name<#bounds>(key: &K, bottom: fn() -> V) -> ::std::result::Result<#guard_struct<K, V, P>, V> {
+            #table_name.with(|tables| {
+                let mut tables = tables.borrow_mut();
+                let fixpoint = #open_name::<K, V, P>(&mut tables);
+                if let Some(&depth) = fixpoint.index.get(key) {
+                    if let Some(top) = fixpoint.stack.last_mut() {
+                        top.low = top.low.min(depth);
+                    }
+                    return Err(fixpoint.approx.entry(key.clone()).or_insert_with(bottom).clone());
+                }
+                if let Some((value, low)) = fixpoint.tentative.get(key) {
+                    if let Some(top) = fixpoint.stack.last_mut() {
+                        top.low = top.low.min(*low);
+                    }
+                    return Err(value.clone());
+                }
+                let depth = fixpoint.stack.len();
+                fixpoint.index.insert(key.clone(), depth);
+                fixpoint.stack.push(#frame_struct { key: key.clone(), low: depth, changed: false, rounds: 0 });
+                Ok(#guard_struct { depth, _types: ::std::marker::PhantomData })
+            })
+        }
+
+        // key 的一轮计算结束，value 为本轮的结果
+        fn #finish_name<#bounds>(
+            key: &K,
+            value: &V,
+            limit: u64,
+            describe: fn(&K) -> ::std::string::String,
+        ) -> #step_enum<K, V> {
+            #table_name.with(|tables| {
+                let mut tables = tables.borrow_mut();
+                let fixpoint = #open_name::<K, V, P>(&mut tables);
+                let Some(mut frame) = fixpoint.stack.pop() else {
+                    unreachable!("memo: fixpoint stack is empty");
+                };
+                let depth = fixpoint.stack.len();
+                if let Some(previous) = fixpoint.approx.get_mut(key) {
+                    if previous != value {
+                        *previous = value.clone();
+                        frame.changed = true;
+                    }
+                }
+                if frame.low < depth {
+                    fixpoint.index.remove(key);
+                    fixpoint.tentative.insert(key.clone(), (value.clone(), frame.low));
+                    if let Some(parent) = fixpoint.stack.last_mut() {
+                        parent.low = parent.low.min(frame.low);
+                        parent.changed |= frame.changed;
+                    }
+                    return #step_enum::Tentative;
+                }
+                if frame.changed {
+                    frame.rounds += 1;
+                    let diverged = frame.rounds >= limit;
+                    frame.low = depth;
+                    frame.changed = false;
+                    fixpoint.stack.push(frame);
+                    if diverged {
+                        return #step_enum::Diverged(describe(key));
+                    }
+                    fixpoint.tentative.retain(|_, entry| entry.1 < depth);
+                    return #step_enum::Again;
+                }
+                fixpoint.index.remove(key);
+                fixpoint.approx.remove(key);
+                let settled: ::std::vec::Vec<K> = fixpoint
+                    .tentative
+                    .iter()
+                    .filter(|(_, entry)| entry.1 >= depth)
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                let committed = settled
+                    .into_iter()
+                    .filter_map(|key| {
+                        fixpoint.approx.remove(&key);
+                        fixpoint.tentative.remove(&key).map(|(value, _)| (key, value))
+                    })
+                    .collect();
+                #step_enum::Done(committed)
+            })
+        }
+    }
+}
+
+// fixpoint 时未命中之后的计算流程（替代 计算 → 写入缓存）
+struct FixpointFlow<'a> {
+    fn_name: &'a Ident,
+    fixpoint: &'a Fixpoint,
+    key_is_tuple: bool,
+    key: &'a Ident,
+    result: &'a Ident,
+    // (缓存值类型, 类型参数)，用于 turbofish
+    value_type: &'a proc_macro2::TokenStream,
+    type_params: &'a [Ident],
+    // 底值（已按 share 包装）
+    bottom: proc_macro2::TokenStream,
+    compute: proc_macro2::TokenStream,
+    store: proc_macro2::TokenStream,
+    // 写入一个收敛的暂定结果，使用 __mau_key / __mau_value
+    store_settled: proc_macro2::TokenStream,
+}
+
+fn generate_fixpoint(flow: FixpointFlow) -> proc_macro2::TokenStream {
+    let FixpointFlow {
+        fn_name, fixpoint, key_is_tuple, key, result, value_type, type_params, bottom, compute, store,
+        store_settled,
+    } = flow;
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let step_enum = Ident::new(&format!("MemoFixpointStep{}", camel), fn_name.span());
+    let enter_name = Ident::new(&format!("__mau_fixpoint_enter_{}", fn_name), fn_name.span());
+    let finish_name = Ident::new(&format!("__mau_fixpoint_finish_{}", fn_name), fn_name.span());
+    let describe = key_describer(fn_name, key_is_tuple);
+    let limit = fixpoint.limit;
+    let label = fn_name.to_string();
+    let types = quote! { _, #value_type, (#(#type_params,)*) };
+
+    quote! {
+        let __mau_fixpoint_guard = match #enter_name::<#types>(&#key, || #bottom) {
+            Ok(guard) => guard,
+            Err(value) => return value,
+        };
+        loop {
+            let #result = #compute;
+            match #finish_name::<#types>(&#key, &#result, #limit, #describe) {
+                #step_enum::Tentative => return #result,
+                #step_enum::Again => continue,
+                #step_enum::Done(settled) => {
+                    for (__mau_key, __mau_value) in settled {
+                        #store_settled
+                    }
+                    #store
+                    return #result;
+                }
+                #step_enum::Diverged(entry) => {
+                    panic!("{}: fixpoint 在 {} 轮迭代后仍未收敛（循环入口 {}）", #label, #limit, entry)
+                }
+            }
+        }
+    }
+}
+
+// tabulate：正在填表的标记，依赖尚未计算的表项时给出顺序错误而不是递归下去
+fn generate_tabulate_items(fn_name: &Ident) -> proc_macro2::TokenStream {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let guard_struct = Ident::new(&format!("MemoTabulateGuard{}", camel), fn_name.span());
+    let flag_name = Ident::new(&format!("{}_TABULATING", fn_name.to_string().to_uppercase()), fn_name.span());
+
+    quote! {
+        ::std::thread_local! {
+            static #flag_name: ::std::cell::Cell<bool> = const { ::std::cell::Cell::new(false) };
+        }
+
+        // 填表结束（包括 panic 展开）时清除标记
+        struct #guard_struct;
+
+        impl #guard_struct {
+            fn start() -> Self {
+                #flag_name.with(|flag| flag.set(true));
+                #guard_struct
+            }
+
+            fn active() -> bool {
+                #flag_name.with(|flag| flag.get())
+            }
+        }
+
+        impl Drop for #guard_struct {
+            fn drop(&mut self) {
+                let _ = #flag_name.try_with(|flag| flag.set(false));
+            }
+        }
+    }
+}
+
+// tabulate 时未命中之后的填表语句：键在声明的范围内时按顺序计算整张表，再从表中取结果；
+// 超出范围的键照常递归计算（out_of_bounds=hash）
+struct TabulateFill<'a> {
+    fn_name: &'a Ident,
+    tabulate: &'a Tabulate,
+    key_is_tuple: bool,
+    // 与 tabulate.dims 一一对应的参数类型
+    dim_types: Vec<&'a Type>,
+    cached: &'a proc_macro2::TokenStream,
+    key_tuple: &'a proc_macro2::TokenStream,
+    compute: proc_macro2::TokenStream,
+}
+
+fn generate_tabulate_fill(fill: TabulateFill) -> proc_macro2::TokenStream {
+    let TabulateFill { fn_name, tabulate, key_is_tuple, dim_types, cached, key_tuple, compute } = fill;
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let guard_struct = Ident::new(&format!("MemoTabulateGuard{}", camel), fn_name.span());
+    let index_trait = Ident::new(&format!("MemoDenseIndex{}", camel), fn_name.span());
+    let label = fn_name.to_string();
+    let order = if tabulate.descending { "descending" } else { "ascending" };
+    let describe = describe_key(fn_name, key_is_tuple, quote! { &cache_key });
+
+    // 由内向外包上每一维的循环，循环变量与参数同名，键和原函数体的参数都取循环变量
+    let mut body = quote! {
+        let result = #compute;
+        #cached.insert(#key_tuple, result);
+    };
+    for ((name, start, end), ty) in tabulate.dims.iter().zip(&dim_types).rev() {
+        let range = if tabulate.descending {
+            quote! { ((#start) as i128..(#end) as i128).rev() }
+        } else {
+            quote! { (#start) as i128..(#end) as i128 }
+        };
+        body = quote! {
+            for #name in #range {
+                let #name = #name as #ty;
+                #body
+            }
+        };
+    }
+
+    quote! {
+        // 同一作用域中可能有多个 MemoDenseIndex{Fn} 为同一个键类型实现，使用完全限定的调用
+        if #index_trait::dense_index(&cache_key).is_some() {
+            if #guard_struct::active() {
+                panic!(
+                    "{}: tabulate 按 order = {} 填表时依赖了尚未计算的 {}（请检查 order 和各参数的声明顺序）",
+                    #label, #order, #describe
+                );
+            }
+            // 自底向上填满整张表
+            let __mau_tabulate_guard = #guard_struct::start();
+            #body
+            drop(__mau_tabulate_guard);
+            return #cached.lookup(&cache_key).expect("memo: tabulate table is incomplete");
+        }
+    }
+}
+
+// scope=instance：调用者持有的缓存 {Fn}Cache 和入口 {fn}_in(&mut cache, ..)
+struct InstanceScope<'a> {
+    fn_name: &'a Ident,
+    fn_vis: &'a syn::Visibility,
+    cache_name: &'a Ident,
+    // 函数的泛型参数（scope=instance 只允许生命周期参数）和 where 子句，原样用于 {fn}_in
+    generics: &'a syn::Generics,
+    store_ty: &'a proc_macro2::TokenStream,
+    store_init: &'a proc_macro2::TokenStream,
+    stats_struct: &'a Ident,
+    memo_inputs: &'a Punctuated<FnArg, Token![,]>,
+    call_args: &'a [proc_macro2::TokenStream],
+    memo_output: &'a proc_macro2::TokenStream,
+}
+
+// {fn}_in 把缓存移入 thread_local 的栈中，中间层（包括原函数体中的递归调用）使用栈顶的缓存，
+// 返回或 panic 展开时由守卫 MemoScope{Fn} 移回 {Fn}Cache
+fn generate_instance_items(scope: InstanceScope) -> proc_macro2::TokenStream {
+    let InstanceScope {
+        fn_name, fn_vis, cache_name, generics, store_ty, store_init, stats_struct, memo_inputs, call_args, memo_output,
+    } = scope;
+    let where_clause = &generics.where_clause;
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let instance_struct = Ident::new(&format!("{}Cache", camel), fn_name.span());
+    let guard_struct = Ident::new(&format!("MemoScope{}", camel), fn_name.span());
+    let in_name = Ident::new(&format!("{}_in", fn_name), fn_name.span());
+
+    quote! {
+        // 只在 {fn}_in 执行期间为 None（此时调用者无法访问）
+        #fn_vis struct #instance_struct {
+            cache: ::std::option::Option<#store_ty>,
+        }
+
+        impl #instance_struct {
+            #fn_vis fn new() -> Self {
+                #instance_struct { cache: ::std::option::Option::Some(#store_init) }
+            }
+
+            fn inner(&self) -> &#store_ty {
+                self.cache.as_ref().expect("memo: instance cache is in use")
+            }
+
+            fn inner_mut(&mut self) -> &mut #store_ty {
+                self.cache.as_mut().expect("memo: instance cache is in use")
+            }
+
+            #fn_vis fn len(&self) -> usize {
+                self.inner().stats().len
+            }
+
+            #fn_vis fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            #fn_vis fn clear(&mut self) {
+                if let ::std::option::Option::Some(cache) = &mut self.cache {
+                    cache.clear(false);
+                }
+            }
+
+            #fn_vis fn stats(&self) -> #stats_struct {
+                self.inner().stats()
+            }
+        }
+
+        impl ::std::default::Default for #instance_struct {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl ::std::fmt::Debug for #instance_struct {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.debug_struct(stringify!(#instance_struct)).field("len", &self.len()).finish()
+            }
+        }
+
+        struct #guard_struct<'a> {
+            owner: &'a mut #instance_struct,
+        }
+
+        impl<'a> #guard_struct<'a> {
+            fn enter(owner: &'a mut #instance_struct) -> Self {
+                let cache = owner.cache.take().expect("memo: instance cache is in use");
+                #cache_name.with(|stack| stack.borrow_mut().push(cache));
+                #guard_struct { owner }
+            }
+        }
+
+        impl Drop for #guard_struct<'_> {
+            fn drop(&mut self) {
+                if let Ok(cache) = #cache_name.try_with(|stack| stack.borrow_mut().pop()) {
+                    self.owner.cache = cache;
+                }
+            }
+        }
+
+        // 使用调用者的缓存计算；原函数体中的递归调用同样使用这个缓存
+        #fn_vis fn #in_name #generics (__mau_cache: &mut #instance_struct, #memo_inputs) #memo_output #where_clause {
+            let _guard = #guard_struct::enter(__mau_cache);
+            #fn_name(#(#call_args),*)
+        }
+    }
+}
+
+// 快照和导出：{fn}_snapshot() -> Vec<(键, 值)>、{fn}_export_json / {fn}_export_csv，
+// 两个整数参数的函数还有 {fn}_print_grid 打印二维表格
+// scope=instance 时生成为 {Fn}Cache 的方法（snapshot / export_json / export_csv / print_grid）
+// 导出函数要求键和值实现 Debug：约束写成 for<'__mau> T: Debug，只在调用时检查，不使用导出时不需要 Debug
+struct SnapshotItems<'a> {
+    fn_name: &'a Ident,
+    fn_vis: &'a syn::Visibility,
+    key_type: &'a proc_macro2::TokenStream,
+    value_type: &'a proc_macro2::TokenStream,
+    // 键的各部分（类型, 列名）
+    key_parts: Vec<(&'a proc_macro2::TokenStream, String)>,
+    key_is_tuple: bool,
+    // 读取缓存中所有条目的表达式（顺序不定）；方法时为 Some(&self) 之类的接收者
+    source: proc_macro2::TokenStream,
+    receiver: Option<proc_macro2::TokenStream>,
+    // 各函数的名字（按后缀 snapshot / export_json / ..），None 时不生成
+    name: &'a dyn Fn(&str) -> Option<Ident>,
+}
+
+const INTEGER_TYPES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+];
+
+fn generate_snapshot_items(items: SnapshotItems) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let SnapshotItems { fn_name, fn_vis, key_type, value_type, key_parts, key_is_tuple, source, receiver, name } = items;
+    let json_value = Ident::new(&format!("__mau_json_{}", fn_name), fn_name.span());
+    let csv_field = Ident::new(&format!("__mau_csv_{}", fn_name), fn_name.span());
+    let self_param = match &receiver {
+        Some(receiver) => quote! { #receiver, },
+        None => quote! {},
+    };
+
+    // 整数键按键排序，其余键的顺序不定（键不一定实现 Ord）
+    let integer_key = key_parts.iter().all(|(ty, _)| INTEGER_TYPES.contains(&ty.to_string().as_str()));
+    let sort = if integer_key {
+        quote! { entries.sort_by(|a, b| a.0.cmp(&b.0)); }
+    } else {
+        quote! {}
+    };
+    // 导出函数不调用 _snapshot（它可能被改名或不生成）
+    let snapshot = quote! {{
+        #[allow(unused_mut)]
+        let mut entries: ::std::vec::Vec<(#key_type, #value_type)> = #source;
+        #sort
+        entries
+    }};
+
+    let part_types: Vec<_> = key_parts.iter().map(|(ty, _)| *ty).collect();
+    let debug_bounds = quote! {
+        where
+            #(for<'__mau> #part_types: ::std::fmt::Debug,)*
+            for<'__mau> #value_type: ::std::fmt::Debug,
+    };
+    let indices: Vec<_> = (0..key_parts.len()).map(syn::Index::from).collect();
+    let (json_key, csv_key) = if key_is_tuple {
+        (
+            quote! {
+                format!("[{}]", [#(#json_value(format!("{:?}", key.#indices))),*].join(", "))
+            },
+            quote! { [#(#csv_field(format!("{:?}", key.#indices))),*].join(",") },
+        )
+    } else {
+        (quote! { #json_value(format!("{:?}", key)) }, quote! { #csv_field(format!("{:?}", key)) })
+    };
+    let header = key_parts.iter().map(|(_, name)| name.as_str()).chain(["value"]).collect::<Vec<_>>().join(",");
+
+    // 二维表格：两个整数参数，行为第一个参数，列为第二个参数，没有缓存的格子显示 "."
+    let grid_name = name("print_grid").filter(|_| key_is_tuple && key_parts.len() == 2 && integer_key);
+    let grid_fn = if let Some(grid_name) = grid_name {
+        let corner = format!("{}\\{}", key_parts[0].1, key_parts[1].1);
+        quote! {
+            #fn_vis fn #grid_name(#self_param mut out: impl ::std::io::Write) -> ::std::io::Result<()>
+            where
+                for<'__mau> #value_type: ::std::fmt::Debug,
+            {
+                let entries = #snapshot;
+                let mut rows: ::std::vec::Vec<_> = entries.iter().map(|(key, _)| key.0).collect();
+                let mut columns: ::std::vec::Vec<_> = entries.iter().map(|(key, _)| key.1).collect();
+                // 条目已按键排序，行号已经有序
+                rows.dedup();
+                columns.sort();
+                columns.dedup();
+                let cells: ::std::collections::HashMap<_, _> =
+                    entries.iter().map(|(key, value)| (*key, format!("{:?}", value))).collect();
+                let cell = |row, column| cells.get(&(row, column)).map_or(".", |text: &::std::string::String| text.as_str());
+
+                let label_width = rows
+                    .iter()
+                    .map(|row| row.to_string().chars().count())
+                    .chain([#corner.chars().count()])
+                    .max()
+                    .unwrap_or(0);
+                let widths: ::std::vec::Vec<usize> = columns
+                    .iter()
+                    .map(|&column| {
+                        rows.iter()
+                            .map(|&row| cell(row, column).chars().count())
+                            .chain([column.to_string().chars().count()])
+                            .max()
+                            .unwrap_or(0)
+                    })
+                    .collect();
+
+                write!(out, "{:>width$} |", #corner, width = label_width)?;
+                for (column, width) in columns.iter().zip(&widths) {
+                    write!(out, " {:>width$}", column, width = width)?;
+                }
+                writeln!(out)?;
+                let rule: usize = widths.iter().map(|width| width + 1).sum();
+                writeln!(out, "{}+{}", "-".repeat(label_width + 1), "-".repeat(rule))?;
+                for &row in &rows {
+                    write!(out, "{:>width$} |", row, width = label_width)?;
+                    for (&column, width) in columns.iter().zip(&widths) {
+                        write!(out, " {:>width$}", cell(row, column), width = width)?;
+                    }
+                    writeln!(out)?;
+                }
+                Ok(())
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let snapshot_fn = name("snapshot").map(|snapshot_name| quote! {
+        #fn_vis fn #snapshot_name(#self_param) -> ::std::vec::Vec<(#key_type, #value_type)> {
+            #snapshot
+        }
+    });
+
+    // [{"key": .., "value": ..}, ..]：能表示为 JSON 数字或布尔值的 Debug 文本原样输出，其余输出为字符串
+    let json_fn = name("export_json").map(|json_name| quote! {
+        #fn_vis fn #json_name(#self_param mut out: impl ::std::io::Write) -> ::std::io::Result<()>
+        #debug_bounds
+        {
+            let entries = #snapshot;
+            if entries.is_empty() {
+                return writeln!(out, "[]");
+            }
+            writeln!(out, "[")?;
+            for (i, (key, value)) in entries.iter().enumerate() {
+                let separator = if i + 1 < entries.len() { "," } else { "" };
+                writeln!(
+                    out,
+                    "  {{\"key\": {}, \"value\": {}}}{}",
+                    #json_key,
+                    #json_value(format!("{:?}", value)),
+                    separator,
+                )?;
+            }
+            writeln!(out, "]")
+        }
+    });
+
+    // 表头为参数名和 value，每个字段是 Debug 文本
+    let csv_fn = name("export_csv").map(|csv_name| quote! {
+        #fn_vis fn #csv_name(#self_param mut out: impl ::std::io::Write) -> ::std::io::Result<()>
+        #debug_bounds
+        {
+            writeln!(out, #header)?;
+            let entries = #snapshot;
+            for (key, value) in entries.iter() {
+                writeln!(out, "{},{}", #csv_key, #csv_field(format!("{:?}", value)))?;
+            }
+            Ok(())
+        }
+    });
+
+    // JSON / CSV 字段的转义（方法时不能放在 impl 块中，单独返回）
+    let json_helper = json_fn.is_some().then(|| quote! {
+        fn #json_value(text: ::std::string::String) -> ::std::string::String {
+            let number = text.parse::<f64>().is_ok_and(f64::is_finite) && !text.starts_with('+');
+            if number || text == "true" || text == "false" {
+                return text;
+            }
+            let mut escaped = ::std::string::String::from("\"");
+            for c in text.chars() {
+                match c {
+                    '"' => escaped.push_str("\\\""),
+                    '\\' => escaped.push_str("\\\\"),
+                    '\n' => escaped.push_str("\\n"),
+                    c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => escaped.push(c),
+                }
+            }
+            escaped.push('"');
+            escaped
+        }
+    });
+    let csv_helper = csv_fn.is_some().then(|| quote! {
+        fn #csv_field(text: ::std::string::String) -> ::std::string::String {
+            if text.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", text.replace('"', "\"\""))
+            } else {
+                text
+            }
+        }
+    });
+
+    let items = quote! {
+        #snapshot_fn
+        #json_fn
+        #csv_fn
+        #grid_fn
+    };
+    (items, quote! { #json_helper #csv_helper })
+}
+
+// 细粒度的缓存操作：{fn}_cached(参数..) 只查缓存、不计算，{fn}_insert(参数.., 值) 写入一个条目（如预先写入边界条件），
+// {fn}_invalidate(参数..) 删除一个条目，{fn}_retain(|键, 值| ..) 只保留回调返回 true 的条目
+// 键由中间层的 key_tuple 生成，与 key 模式和 ignore / symmetric / sorted / key_with 的处理一致；
+// 被忽略的参数不参与键，不出现在参数列表中
+// scope=instance 时生成为 {Fn}Cache 的方法（cached / insert / invalidate / retain）
+struct EntryItems<'a> {
+    fn_vis: &'a syn::Visibility,
+    generics: &'a syn::Generics,
+    key_inputs: Vec<&'a FnArg>,
+    key_tuple: &'a proc_macro2::TokenStream,
+    key_type: &'a proc_macro2::TokenStream,
+    value_type: &'a proc_macro2::TokenStream,
+    // 对缓存调用一个方法（如 lookup(&cache_key)）的表达式，cache_key 已在作用域中
+    access: &'a dyn Fn(proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+    // 类型擦除的缓存要由键推断出具体的 MemoCache，没有键的 retain 不生成
+    erased: bool,
+    receiver: Option<proc_macro2::TokenStream>,
+    // 各函数的名字（按后缀 cached / insert / ..），None 时不生成
+    name: &'a dyn Fn(&str) -> Option<Ident>,
+}
+
+fn generate_entry_items(items: EntryItems) -> proc_macro2::TokenStream {
+    let EntryItems {
+        fn_vis, generics, key_inputs, key_tuple, key_type, value_type, access, erased, receiver, name,
+    } = items;
+    let self_param = match &receiver {
+        Some(receiver) => quote! { #receiver, },
+        None => quote! {},
+    };
+    let where_clause = &generics.where_clause;
+
+    let cached_fn = name("cached").map(|cached_name| {
+        let lookup = access(quote! { lookup(&cache_key) });
+        quote! {
+            #fn_vis fn #cached_name #generics (#self_param #(#key_inputs),*) -> ::std::option::Option<#value_type> #where_clause {
+                let cache_key = #key_tuple;
+                #lookup
+            }
+        }
+    });
+    let insert_fn = name("insert").map(|insert_name| {
+        let insert = access(quote! { insert(cache_key, __mau_value) });
+        quote! {
+            #fn_vis fn #insert_name #generics (#self_param #(#key_inputs,)* __mau_value: #value_type) #where_clause {
+                let cache_key = #key_tuple;
+                #insert
+            }
+        }
+    });
+    let invalidate_fn = name("invalidate").map(|invalidate_name| {
+        let remove = access(quote! { remove(&cache_key) });
+        quote! {
+            #fn_vis fn #invalidate_name #generics (#self_param #(#key_inputs),*) #where_clause {
+                let cache_key = #key_tuple;
+                #remove
+            }
+        }
+    });
+    let retain_fn = name("retain").filter(|_| !erased).map(|retain_name| {
+        let retain = access(quote! { retain(__mau_keep) });
+        quote! {
+            #fn_vis fn #retain_name(#self_param __mau_keep: impl FnMut(&#key_type, &#value_type) -> bool) {
+                #retain
+            }
+        }
+    });
+
+    quote! {
+        #cached_fn
+        #insert_fn
+        #invalidate_fn
+        #retain_fn
+    }
+}
+
+// 句柄：#[memo(handle)] 生成零大小的 Memo{Fn}，实现 ::mau::Memo（需要以 mau 的名字依赖本库）
+// 句柄的方法直接使用 _clear / _stats / _start 的实现，不依赖这些辅助函数是否生成
+struct MemoHandle<'a> {
+    fn_name: &'a Ident,
+    fn_vis: &'a syn::Visibility,
+    // 只有生命周期参数（类型参数和 const 参数的函数不生成句柄）
+    generics: &'a syn::Generics,
+    arg_types: &'a [Type],
+    call_args: &'a [proc_macro2::TokenStream],
+    output: &'a proc_macro2::TokenStream,
+    clear_impl: &'a proc_macro2::TokenStream,
+    stats_impl: &'a proc_macro2::TokenStream,
+    start_impl: &'a proc_macro2::TokenStream,
+    // ::mau::MemoConfig 的值
+    config: proc_macro2::TokenStream,
+}
+
+fn generate_memo_handle(handle: MemoHandle) -> proc_macro2::TokenStream {
+    use syn::visit_mut::{self, VisitMut};
+
+    // Args<'a>：省略的生命周期、'_ 和函数的生命周期参数都改写为 '__mau
+    struct ArgsLifetime<'a> {
+        params: Vec<&'a syn::Lifetime>,
+    }
+
+    impl VisitMut for ArgsLifetime<'_> {
+        fn visit_type_reference_mut(&mut self, reference: &mut syn::TypeReference) {
+            if reference.lifetime.is_none() {
+                reference.lifetime = Some(syn::parse_quote!('__mau));
+            }
+            visit_mut::visit_type_reference_mut(self, reference);
+        }
+
+        fn visit_lifetime_mut(&mut self, lifetime: &mut syn::Lifetime) {
+            if lifetime.ident == "_" || self.params.contains(&&*lifetime) {
+                *lifetime = syn::parse_quote!('__mau);
+            }
+        }
+    }
+
+    let MemoHandle {
+        fn_name, fn_vis, generics, arg_types, call_args, output, clear_impl, stats_impl, start_impl, config,
+    } = handle;
+    let handle_struct = Ident::new(&format!("Memo{}", to_upper_camel_case(&fn_name.to_string())), fn_name.span());
+    let mut rewriter = ArgsLifetime { params: generics.lifetimes().map(|param| &param.lifetime).collect() };
+    let arg_types: Vec<_> = arg_types
+        .iter()
+        .map(|ty| {
+            let mut ty = ty.clone();
+            rewriter.visit_type_mut(&mut ty);
+            ty
+        })
+        .collect();
+    let doc = format!("`{}` 的记忆化句柄，实现 `mau::Memo`", fn_name);
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+        #fn_vis struct #handle_struct;
+
+        impl ::mau::Memo for #handle_struct {
+            type Args<'__mau> = (#(#arg_types,)*);
+            type Output = #output;
+
+            fn clear(&self) {
+                let __mau_on_start = false;
+                #clear_impl
+            }
+
+            fn stats(&self) -> ::mau::MemoStats {
+                let stats = { #stats_impl };
+                ::mau::MemoStats {
+                    hits: stats.hits,
+                    misses: stats.misses,
+                    inserts: stats.inserts,
+                    len: stats.len,
+                    peak_len: stats.peak_len,
+                    clears: stats.clears,
+                    manual_clears: stats.manual_clears,
+                }
+            }
+
+            fn config(&self) -> ::mau::MemoConfig {
+                #config
+            }
+
+            fn call_start<'__mau>(&self, (#(#call_args,)*): Self::Args<'__mau>) -> Self::Output {
+                #start_impl
+            }
+        }
+    }
+}
+
+// FNV-1a：编译时计算 persist 文件的模式键，结果只取决于输入文本
+fn fnv1a_64(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// 生成 persist 所需的编解码 trait、缓存包装和退出回调，返回 (定义, 第一次调用时注册退出回调的语句)
+// 文件格式（整数均为小端序）：
+//   b"MAUC" | 格式版本 u32 | 模式键 u64 | 条目数 u64 | 条目 (键, 值)...
+// 模式键是函数签名和函数体的哈希：函数改动后旧文件被丢弃，而不是返回过时的结果。
+// 键和值通过 MemoCodec{Fn} 编码，内置整数、浮点数、bool、char、String、Vec、数组、
+// Option、Result、Box、Rc、Arc 和元组，其他类型可以由用户实现该 trait。
+// MemoPersist{Fn} 通过 Deref 暴露内部的 MemoCache，只替换 insert（标记待写回）、
+// remove / retain（记下删除的键）和 clear（先写回再清空）；写回时与文件中已有的条目合并，
+// _clear 之后文件中的结果仍然保留，_invalidate / _retain 删除的键不再写回。
+// single 模式下每个线程的缓存在线程退出时写回，主线程再由 atexit 回调写回。
+fn generate_persist_items(
+    input_fn: &ItemFn,
+    persist: &Persist,
+    cache_name: &Ident,
+    thread_mode: &str,
+    capacity: Option<usize>,
+    key_type: &proc_macro2::TokenStream,
+    value_type: &proc_macro2::TokenStream,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let fn_name = &input_fn.sig.ident;
+    let sig = &input_fn.sig;
+    let block = &input_fn.block;
+    let schema = fnv1a_64(&format!("{} | {} | {} | {}", quote!(#sig), quote!(#block), key_type, value_type));
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let codec = Ident::new(&format!("MemoCodec{}", camel), fn_name.span());
+    let persist_struct = Ident::new(&format!("MemoPersist{}", camel), fn_name.span());
+    let cache_struct = Ident::new(&format!("MemoCache{}", camel), fn_name.span());
+    let take_name = Ident::new(&format!("__mau_take_{}", fn_name), fn_name.span());
+    let save_name = Ident::new(&format!("__mau_save_{}", fn_name), fn_name.span());
+    let label = fn_name.to_string();
+    let path = &persist.path;
+
+    let integers = ["u8", "u16", "u32", "u64", "u128", "i8", "i16", "i32", "i64", "i128"]
+        .iter()
+        .map(|name| Ident::new(name, proc_macro2::Span::call_site()));
+    let tuples = (2..=8usize).map(|len| {
+        let params: Vec<_> = (0..len)
+            .map(|i| Ident::new(&format!("T{}", i), proc_macro2::Span::call_site()))
+            .collect();
+        let indices = (0..len).map(syn::Index::from);
+        quote! {
+            impl<#(#params: #codec),*> #codec for (#(#params,)*) {
+                fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                    #(#codec::encode(&self.#indices, out);)*
+                }
+                fn decode(input: &mut &[u8]) -> Option<Self> {
+                    Some((#(#params::decode(input)?,)*))
+                }
+            }
+        }
+    });
+
+    let interval_check = match persist.interval_ms {
+        Some(interval_ms) => quote! {
+            if self.last_save.elapsed() >= ::std::time::Duration::from_millis(#interval_ms) {
+                self.save();
+            }
+        },
+        None => quote! {},
+    };
+    let entry_limit = match capacity {
+        Some(capacity) => quote! { #capacity },
+        None => quote! { usize::MAX },
+    };
+
+    let (drop_impl, save_at_exit) = if thread_mode == "multi" {
+        (
+            quote! {},
+            quote! {
+                if let Ok(mut cache) = #cache_name.lock() {
+                    cache.save();
+                }
+            },
+        )
+    } else {
+        (
+            quote! {
+                impl Drop for #persist_struct {
+                    fn drop(&mut self) {
+                        self.save();
+                    }
+                }
+            },
+            quote! {
+                let _ = #cache_name.try_with(|cache| {
+                    if let Ok(mut cache) = cache.try_borrow_mut() {
+                        cache.save();
+                    }
+                });
+            },
+        )
+    };
+
+    let items = quote! {
+        trait #codec: Sized {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>);
+            fn decode(input: &mut &[u8]) -> Option<Self>;
+        }
+
+        fn #take_name<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+            if input.len() < len {
+                return None;
+            }
+            let (head, tail) = input.split_at(len);
+            *input = tail;
+            Some(head)
+        }
+
+        #(
+            impl #codec for #integers {
+                fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+                fn decode(input: &mut &[u8]) -> Option<Self> {
+                    let bytes = #take_name(input, ::std::mem::size_of::<Self>())?;
+                    Some(Self::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+
+        impl #codec for usize {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #codec::encode(&(*self as u64), out);
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                <u64 as #codec>::decode(input)?.try_into().ok()
+            }
+        }
+
+        impl #codec for isize {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #codec::encode(&(*self as i64), out);
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                <i64 as #codec>::decode(input)?.try_into().ok()
+            }
+        }
+
+        impl #codec for f32 {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #codec::encode(&self.to_bits(), out);
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                Some(Self::from_bits(<u32 as #codec>::decode(input)?))
+            }
+        }
+
+        impl #codec for f64 {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #codec::encode(&self.to_bits(), out);
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                Some(Self::from_bits(<u64 as #codec>::decode(input)?))
+            }
+        }
+
+        impl #codec for bool {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                out.push(*self as u8);
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                match <u8 as #codec>::decode(input)? {
+                    0 => Some(false),
+                    1 => Some(true),
+                    _ => None,
+                }
+            }
+        }
+
+        impl #codec for char {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #codec::encode(&(*self as u32), out);
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                char::from_u32(<u32 as #codec>::decode(input)?)
+            }
+        }
+
+        impl #codec for () {
+            fn encode(&self, _out: &mut ::std::vec::Vec<u8>) {}
+            fn decode(_input: &mut &[u8]) -> Option<Self> {
+                Some(())
+            }
+        }
+
+        impl #codec for ::std::string::String {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #codec::encode(&self.len(), out);
+                out.extend_from_slice(self.as_bytes());
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                let len = <usize as #codec>::decode(input)?;
+                ::std::string::String::from_utf8(#take_name(input, len)?.to_vec()).ok()
+            }
+        }
+
+        impl<T: #codec> #codec for ::std::vec::Vec<T> {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #codec::encode(&self.len(), out);
+                for item in self {
+                    #codec::encode(item, out);
+                }
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                let len = <usize as #codec>::decode(input)?;
+                // 长度来自文件，不能直接按它预分配
+                let mut items = ::std::vec::Vec::with_capacity(len.min(input.len()));
+                for _ in 0..len {
+                    items.push(T::decode(input)?);
+                }
+                Some(items)
+            }
+        }
+
+        impl<T: #codec, const N: usize> #codec for [T; N] {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                for item in self {
+                    #codec::encode(item, out);
+                }
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                let mut items = ::std::vec::Vec::with_capacity(N);
+                for _ in 0..N {
+                    items.push(T::decode(input)?);
+                }
+                items.try_into().ok()
+            }
+        }
+
+        impl<T: #codec> #codec for Option<T> {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                match self {
+                    None => out.push(0),
+                    Some(value) => {
+                        out.push(1);
+                        #codec::encode(value, out);
+                    }
+                }
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                match <u8 as #codec>::decode(input)? {
+                    0 => Some(None),
+                    1 => Some(Some(T::decode(input)?)),
+                    _ => None,
+                }
+            }
+        }
+
+        impl<T: #codec, E: #codec> #codec for Result<T, E> {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                match self {
+                    Ok(value) => {
+                        out.push(0);
+                        #codec::encode(value, out);
+                    }
+                    Err(error) => {
+                        out.push(1);
+                        #codec::encode(error, out);
+                    }
+                }
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                match <u8 as #codec>::decode(input)? {
+                    0 => Some(Ok(T::decode(input)?)),
+                    1 => Some(Err(E::decode(input)?)),
+                    _ => None,
+                }
+            }
+        }
+
+        impl<T: #codec> #codec for ::std::boxed::Box<T> {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #codec::encode(&**self, out);
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                Some(::std::boxed::Box::new(T::decode(input)?))
+            }
+        }
+
+        impl<T: #codec> #codec for ::std::rc::Rc<T> {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #codec::encode(&**self, out);
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                Some(::std::rc::Rc::new(T::decode(input)?))
+            }
+        }
+
+        impl<T: #codec> #codec for ::std::sync::Arc<T> {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #codec::encode(&**self, out);
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                Some(::std::sync::Arc::new(T::decode(input)?))
+            }
+        }
+
+        #(#tuples)*
+
+        struct #persist_struct {
+            cache: #cache_struct<#key_type, #value_type>,
+            // 有尚未写回文件的插入或删除
+            dirty: bool,
+            // 删除的键：写回时文件中的这些条目同样丢弃
+            removed: ::std::collections::HashSet<#key_type>,
+            last_save: ::std::time::Instant,
+        }
+
+        impl ::std::ops::Deref for #persist_struct {
+            type Target = #cache_struct<#key_type, #value_type>;
+            fn deref(&self) -> &Self::Target {
+                &self.cache
+            }
+        }
+
+        impl ::std::ops::DerefMut for #persist_struct {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.cache
+            }
+        }
+
+        impl #persist_struct {
+            const PATH: &'static str = #path;
+            const MAGIC: [u8; 4] = *b"MAUC";
+            const VERSION: u32 = 1;
+            const SCHEMA: u64 = #schema;
+
+            // 第一次使用缓存时读取文件；文件不存在、格式版本或模式键不符、内容损坏时从空缓存开始
+            fn load() -> Self {
+                let mut cache = #cache_struct::new();
+                for (key, value) in Self::read() {
+                    cache.store(key, value);
+                }
+                cache.stats.peak_len = cache.map.len();
+                Self {
+                    cache,
+                    dirty: false,
+                    removed: ::std::collections::HashSet::new(),
+                    last_save: ::std::time::Instant::now(),
+                }
+            }
+
+            fn read() -> ::std::vec::Vec<(#key_type, #value_type)> {
+                ::std::fs::read(Self::PATH)
+                    .ok()
+                    .and_then(|bytes| Self::decode(&mut bytes.as_slice()))
+                    .unwrap_or_default()
+            }
+
+            fn decode(input: &mut &[u8]) -> Option<::std::vec::Vec<(#key_type, #value_type)>> {
+                if <[u8; 4] as #codec>::decode(input)? != Self::MAGIC
+                    || <u32 as #codec>::decode(input)? != Self::VERSION
+                    || <u64 as #codec>::decode(input)? != Self::SCHEMA
+                {
+                    return None;
+                }
+                let entries = <::std::vec::Vec<(#key_type, #value_type)> as #codec>::decode(input)?;
+                input.is_empty().then_some(entries)
+            }
+
+            fn insert(&mut self, key: #key_type, value: #value_type) {
+                self.removed.remove(&key);
+                self.cache.insert(key, value);
+                self.dirty = true;
+                #interval_check
+            }
+
+            fn remove(&mut self, key: &#key_type) {
+                self.cache.remove(key);
+                self.removed.insert(key.clone());
+                self.dirty = true;
+            }
+
+            // 文件中不在内存里的条目（_clear 或淘汰之后）同样按回调决定是否保留
+            fn retain(&mut self, mut keep: impl FnMut(&#key_type, &#value_type) -> bool) {
+                let removed = &mut self.removed;
+                self.cache.retain(|key, value| keep(key, value) || {
+                    removed.insert(key.clone());
+                    false
+                });
+                for (key, value) in Self::read() {
+                    if !self.cache.map.contains_key(&key) && !keep(&key, &value) {
+                        self.removed.insert(key);
+                    }
+                }
+                self.dirty = true;
+            }
+
+            fn clear(&mut self, on_start: bool) {
+                self.save();
+                self.cache.clear(on_start);
+            }
+
+            // 与文件中已有的条目合并后写回（内存中的条目优先，删除的键丢弃），先写临时文件再重命名
+            fn save(&mut self) {
+                if !self.dirty {
+                    return;
+                }
+                self.dirty = false;
+                self.last_save = ::std::time::Instant::now();
+
+                let on_disk = Self::read();
+                let entries: ::std::vec::Vec<(&#key_type, &#value_type)> = self
+                    .cache
+                    .map
+                    .iter()
+                    .map(|(key, entry)| (key, &entry.value))
+                    .chain(
+                        on_disk
+                            .iter()
+                            .filter(|(key, _)| !self.cache.map.contains_key(key) && !self.removed.contains(key))
+                            .map(|(key, value)| (key, value)),
+                    )
+                    .take(#entry_limit)
+                    .collect();
+                let mut out = ::std::vec::Vec::new();
+                #codec::encode(&Self::MAGIC, &mut out);
+                #codec::encode(&Self::VERSION, &mut out);
+                #codec::encode(&Self::SCHEMA, &mut out);
+                #codec::encode(&entries.len(), &mut out);
+                for (key, value) in entries {
+                    #codec::encode(key, &mut out);
+                    #codec::encode(value, &mut out);
+                }
+
+                let path = ::std::path::Path::new(Self::PATH);
+                // 单线程模式下每个线程各有一份缓存，退出时可能同时保存：
+                // 临时文件名带上进程号与递增序号，避免互相覆盖写到一半的文件
+                static SEQ: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0);
+                let temp = format!(
+                    "{}.{}.{}.tmp",
+                    Self::PATH,
+                    ::std::process::id(),
+                    SEQ.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed),
+                );
+                let result = path
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .map_or(Ok(()), ::std::fs::create_dir_all)
+                    .and_then(|_| ::std::fs::write(&temp, &out))
+                    .and_then(|_| ::std::fs::rename(&temp, path));
+                match result {
+                    Ok(()) => self.removed.clear(),
+                    Err(error) => {
+                        use ::std::io::Write;
+                        let _ = ::std::fs::remove_file(&temp);
+                        let _ = writeln!(
+                            ::std::io::stderr(),
+                            "[mau] {}: 无法写入缓存文件 {}: {}",
+                            #label, Self::PATH, error,
+                        );
+                    }
+                }
+            }
+        }
+
+        #drop_impl
+
+        extern "C" fn #save_name() {
+            #save_at_exit
+        }
+    };
+
+    let register = quote! {
+        {
+            static __MAU_PERSIST_REGISTERED: ::std::sync::Once = ::std::sync::Once::new();
+            __MAU_PERSIST_REGISTERED.call_once(|| {
+                unsafe extern "C" {
+                    fn atexit(callback: extern "C" fn()) -> ::std::os::raw::c_int;
+                }
+                unsafe { atexit(#save_name); }
+            });
+        }
+    };
+
+    (items, register)
+}
+
+// 生成类型擦除缓存表所需的 trait 和辅助函数（泛型函数、key_with）
+// 静态变量不能使用函数的类型参数，键类型也可能无法在宏中写出，因此缓存表以 TypeId
+// 区分每个 (MemoCache<K, V>, 类型参数) 组合，值为 Box<dyn MemoErased{Fn}>：
+// 既可以 downcast 回具体的 MemoCache，也可以在不知道具体类型的情况下清除缓存、
+// 汇总统计（_clear / _stats 覆盖所有单态化）
+// __mau_open_{fn} 通过参数推断出 K，调用方只需给出 V 和类型参数元组 P
+fn generate_erased_cache_items(fn_name: &Ident, thread_mode: &str) -> proc_macro2::TokenStream {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let cache_struct = Ident::new(&format!("MemoCache{}", camel), fn_name.span());
+    let stats_struct = Ident::new(&format!("{}Stats", camel), fn_name.span());
+    let erased_trait = Ident::new(&format!("MemoErased{}", camel), fn_name.span());
+    let sum_stats = Ident::new(&format!("__mau_stats_{}", fn_name), fn_name.span());
+    let open_name = Ident::new(&format!("__mau_open_{}", fn_name), fn_name.span());
+    let send = if thread_mode == "multi" { quote! { + Send } } else { quote! {} };
+
+    quote! {
+        trait #erased_trait {
+            fn as_any_mut(&mut self) -> &mut dyn ::std::any::Any;
+            fn clear(&mut self, on_start: bool);
+            fn stats(&self) -> #stats_struct;
+        }
+
+        impl<K: ::std::hash::Hash + Eq + Clone + 'static, V: Clone + 'static> #erased_trait for #cache_struct<K, V> {
+            fn as_any_mut(&mut self) -> &mut dyn ::std::any::Any {
+                self
+            }
+
+            fn clear(&mut self, on_start: bool) {
+                #cache_struct::clear(self, on_start)
+            }
+
+            fn stats(&self) -> #stats_struct {
+                #cache_struct::stats(self)
+            }
+        }
+
+        fn #open_name<'a, K: ::std::hash::Hash + Eq + Clone #send + 'static, V: Clone #send + 'static, P: 'static>(
+            caches: &'a mut ::std::collections::HashMap<::std::any::TypeId, ::std::boxed::Box<dyn #erased_trait #send>>,
+            _key: &K,
+        ) -> &'a mut #cache_struct<K, V> {
+            caches
+                .entry(::std::any::TypeId::of::<(#cache_struct<K, V>, P)>())
+                .or_insert_with(|| ::std::boxed::Box::new(#cache_struct::<K, V>::new()))
+                .as_any_mut()
+                .downcast_mut::<#cache_struct<K, V>>()
+                .expect("memo: cache type mismatch")
+        }
+
+        // 汇总所有单态化的统计；clear 会同时清除所有单态化，因此 clears / manual_clears 取最大值
+        fn #sum_stats(
+            caches: &::std::collections::HashMap<::std::any::TypeId, ::std::boxed::Box<dyn #erased_trait #send>>,
+        ) -> #stats_struct {
+            let mut total = #stats_struct::default();
+            for cache in caches.values() {
+                let stats = cache.stats();
+                total.hits += stats.hits;
+                total.misses += stats.misses;
+                total.inserts += stats.inserts;
+                total.len += stats.len;
+                total.peak_len += stats.peak_len;
+                total.clears = total.clears.max(stats.clears);
+                total.manual_clears = total.manual_clears.max(stats.manual_clears);
+            }
+            total
+        }
+    }
+}
+
+// 生成 async 函数的中间层
+// 缓存保存 await 之后的结果；访问缓存时只在同步代码中短暂借用 RefCell / 持有 Mutex，
+// 不会跨越 .await。
+// 同一个键的并发调用共享一次计算：第一个调用者（leader）登记到 in-flight 表并计算，
+// 其余调用者等待 leader 的结果。leader 被取消或 panic 时，守卫在 drop 中把登记
+// 标记为 abandoned 并唤醒等待者，等待者重新查缓存 / 竞争成为新的 leader。
+fn generate_async_memo(
+    fn_name: &Ident,
+    thread_mode: &str,
+    // 缓存：single 模式为 thread_local 静态变量，multi 模式为可以直接 get / insert 的表达式
+    // （Mutex 加锁后的守卫，或自带锁的分片缓存）
+    cache: &proc_macro2::TokenStream,
+    key_type: &proc_macro2::TokenStream,
+    return_type: &proc_macro2::TokenStream,
+    compute: proc_macro2::TokenStream,
+    // 给写入缓存的语句加上 cache_if 条件
+    guard_store: impl Fn(proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let flight_struct = Ident::new(&format!("MemoFlight{}", camel), fn_name.span());
+    let guard_struct = Ident::new(&format!("MemoFlightGuard{}", camel), fn_name.span());
+    let wait_struct = Ident::new(&format!("MemoWait{}", camel), fn_name.span());
+    let table_name = Ident::new(&format!("{}_IN_FLIGHT", fn_name.to_string().to_uppercase()), fn_name.span());
+
+    // single 模式：Rc<RefCell<..>> + thread_local!；multi 模式：Arc<Mutex<..>> + 全局 static
+    let multi = thread_mode == "multi";
+    let (ptr, cell) = if multi {
+        (quote! { ::std::sync::Arc }, quote! { ::std::sync::Mutex })
+    } else {
+        (quote! { ::std::rc::Rc }, quote! { ::std::cell::RefCell })
+    };
+    let lock = if multi { quote! { .lock().unwrap() } } else { quote! { .borrow_mut() } };
+    let shared_flight = quote! { #ptr<#cell<#flight_struct<#return_type>>> };
+    let table_ty = quote! { ::std::collections::HashMap<#key_type, #shared_flight> };
+
+    let (create_table, cache_get, cache_put) = if multi {
+        (
+            quote! {
+                static #table_name: ::std::sync::LazyLock<::std::sync::Mutex<#table_ty>> =
+                    ::std::sync::LazyLock::new(|| ::std::sync::Mutex::new(::std::collections::HashMap::new()));
+            },
+            quote! { #cache.get(&__mau_key) },
+            guard_store(quote! { #cache.insert(__mau_key.clone(), __mau_result.clone()); }),
+        )
+    } else {
+        (
+            quote! {
+                ::std::thread_local! {
+                    static #table_name: ::std::cell::RefCell<#table_ty> = ::std::cell::RefCell::new(::std::collections::HashMap::new());
+                }
+            },
+            quote! { #cache.with(|cache| cache.borrow_mut().get(&__mau_key)) },
+            guard_store(quote! { #cache.with(|cache| cache.borrow_mut().insert(__mau_key.clone(), __mau_result.clone())); }),
+        )
+    };
+
+    // 在持有 in-flight 表（__mau_table）的作用域内执行 body
+    // 线程退出时 thread_local 可能已销毁，drop 中使用 try_with 忽略
+    let with_table = |body: proc_macro2::TokenStream, in_drop: bool| {
+        if multi {
+            quote! {{ let mut __mau_table = #table_name.lock().unwrap(); #body }}
+        } else if in_drop {
+            quote! { let _ = #table_name.try_with(|table| { let mut __mau_table = table.borrow_mut(); #body }); }
+        } else {
+            quote! { #table_name.with(|table| { let mut __mau_table = table.borrow_mut(); #body }) }
+        }
+    };
+    let unregister = with_table(quote! {
+        if __mau_table.get(&self.key).is_some_and(|flight| #ptr::ptr_eq(flight, &self.flight)) {
+            __mau_table.remove(&self.key);
+        }
+    }, true);
+    let register = with_table(quote! {
+        match __mau_table.get(&__mau_key) {
+            Some(flight) => (flight.clone(), false),
+            None => {
+                let flight: #shared_flight = #ptr::new(#cell::new(#flight_struct {
+                    result: None,
+                    abandoned: false,
+                    wakers: ::std::vec::Vec::new(),
+                }));
+                __mau_table.insert(__mau_key.clone(), flight.clone());
+                (flight, true)
+            }
+        }
+    }, false);
+
+    let items = quote! {
+        struct #flight_struct<V> {
+            result: ::std::option::Option<V>,
+            abandoned: bool,
+            wakers: ::std::vec::Vec<::std::task::Waker>,
+        }
+
+        #create_table
+
+        // leader 持有的守卫：完成或放弃时移除登记并唤醒等待者
+        struct #guard_struct {
+            key: #key_type,
+            flight: #shared_flight,
+            done: bool,
+        }
+
+        impl Drop for #guard_struct {
+            fn drop(&mut self) {
+                #unregister
+                let mut flight = self.flight #lock;
+                if !self.done {
+                    flight.abandoned = true;
+                }
+                for waker in flight.wakers.drain(..) {
+                    waker.wake();
+                }
+            }
+        }
+
+        // 等待 leader 结果的 future：Some(结果) 或 None（leader 放弃，需要重试）
+        struct #wait_struct(#shared_flight);
+
+        impl ::std::future::Future for #wait_struct {
+            type Output = ::std::option::Option<#return_type>;
+
+            fn poll(self: ::std::pin::Pin<&mut Self>, cx: &mut ::std::task::Context<'_>) -> ::std::task::Poll<Self::Output> {
+                let mut flight = self.0 #lock;
+                if let Some(result) = &flight.result {
+                    return ::std::task::Poll::Ready(Some(result.clone()));
+                }
+                if flight.abandoned {
+                    return ::std::task::Poll::Ready(None);
+                }
+                if !flight.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    flight.wakers.push(cx.waker().clone());
+                }
+                ::std::task::Poll::Pending
+            }
+        }
+    };
+
+    let body = quote! {
+        loop {
+            // 检查缓存
+            if let Some(result) = #cache_get {
+                return result;
+            }
+            // 查找或登记 in-flight 计算
+            let (__mau_flight, __mau_leader) = #register;
+            if __mau_leader {
+                let mut __mau_guard = #guard_struct { key: __mau_key.clone(), flight: __mau_flight, done: false };
+                // 计算并缓存结果
+                let __mau_result = #compute;
+                #cache_put
+                __mau_guard.flight #lock .result = Some(__mau_result.clone());
+                __mau_guard.done = true;
+                drop(__mau_guard);
+                return __mau_result;
+            }
+            if let Some(result) = #wait_struct(__mau_flight).await {
+                return result;
+            }
+        }
+    };
+
+    (items, body)
+}
+
+// 生成 multi 模式同步函数合并并发未命中（single-flight）所需的定义：
+//   - MemoFlight{Fn}<V>：一次进行中的计算，等待者在 Condvar 上阻塞，完成后得到结果的克隆；
+//     计算线程 panic 时等待者以相同的消息 panic
+//   - table_name：进行中的计算表，与缓存一样以 TypeId 区分每个 (键类型, 结果类型, 类型参数) 组合，
+//     泛型函数、key_with 和方法可以共用同一套代码
+//   - flights_name：取出当前单态化的 HashMap<K, Arc<MemoFlight{Fn}<V>>>（K 由参数推断）
+fn generate_single_flight_items(fn_name: &Ident, table_name: &Ident, flights_name: &Ident) -> proc_macro2::TokenStream {
+    let camel = to_upper_camel_case(&fn_name.to_string());
+    let flight_struct = Ident::new(&format!("MemoFlight{}", camel), fn_name.span());
+    let state_enum = Ident::new(&format!("MemoFlightState{}", camel), fn_name.span());
+    let label = fn_name.to_string();
+    let tables_ty = quote! {
+        ::std::collections::HashMap<::std::any::TypeId, ::std::boxed::Box<dyn ::std::any::Any + Send>>
+    };
+
+    quote! {
+        enum #state_enum<V> {
+            Running,
+            Done(V),
+            Panicked(::std::string::String),
+        }
+
+        struct #flight_struct<V> {
+            // 计算线程：同一线程对同一个键重入时不能等待自己
+            owner: ::std::thread::ThreadId,
+            state: ::std::sync::Mutex<#state_enum<V>>,
+            ready: ::std::sync::Condvar,
+        }
+
+        impl<V: Clone> #flight_struct<V> {
+            fn new() -> Self {
+                Self {
+                    owner: ::std::thread::current().id(),
+                    state: ::std::sync::Mutex::new(#state_enum::Running),
+                    ready: ::std::sync::Condvar::new(),
+                }
+            }
+
+            fn finish(&self, outcome: &::std::thread::Result<V>) {
+                let state = match outcome {
+                    Ok(result) => #state_enum::Done(result.clone()),
+                    Err(payload) => #state_enum::Panicked(
+                        payload
+                            .downcast_ref::<&str>()
+                            .map(|message| message.to_string())
+                            .or_else(|| payload.downcast_ref::<::std::string::String>().cloned())
+                            .unwrap_or_else(|| format!("{} panicked", #label)),
+                    ),
+                };
+                *self.state.lock().unwrap() = state;
+                self.ready.notify_all();
+            }
+
+            fn wait(&self) -> V {
+                let mut state = self.state.lock().unwrap();
+                let message = loop {
+                    match &*state {
+                        #state_enum::Running => state = self.ready.wait(state).unwrap(),
+                        #state_enum::Done(result) => return result.clone(),
+                        #state_enum::Panicked(message) => break message.clone(),
+                    }
+                };
+                // 先释放锁再 panic，避免毒化，其他等待者同样能取得消息
+                drop(state);
+                ::std::panic::resume_unwind(::std::boxed::Box::new(message))
+            }
+        }
+
+        static #table_name: ::std::sync::LazyLock<::std::sync::Mutex<#tables_ty>> =
+            ::std::sync::LazyLock::new(|| ::std::sync::Mutex::new(::std::collections::HashMap::new()));
+
+        fn #flights_name<'a, K: ::std::hash::Hash + Eq + Send + 'static, V: Send + 'static, P: 'static>(
+            tables: &'a mut #tables_ty,
+            _key: &K,
+        ) -> &'a mut ::std::collections::HashMap<K, ::std::sync::Arc<#flight_struct<V>>> {
+            tables
+                .entry(::std::any::TypeId::of::<(K, V, P)>())
+                .or_insert_with(|| ::std::boxed::Box::new(
+                    ::std::collections::HashMap::<K, ::std::sync::Arc<#flight_struct<V>>>::new()
+                ))
+                .downcast_mut()
+                .expect("memo: in-flight table type mismatch")
+        }
+    }
+}
+
+// multi 模式同步函数在未命中之后的流程（表达式，值为结果）：
+//   - 没有进行中的计算：登记为计算者，再查一次缓存（登记之前可能有别的线程刚完成），
+//     计算并写入缓存，移除登记后把结果（或 panic）交给等待者
+//   - 别的线程正在计算同一个键：阻塞等待它的结果
+//   - 本线程正在计算同一个键（重入）：直接计算，不参与合并
+struct SingleFlight<'a> {
+    fn_name: &'a Ident,
+    table_name: &'a Ident,
+    // 带 turbofish 的 flights 函数，如 __mau_flights_fib::<_, u64, ()>
+    flights: proc_macro2::TokenStream,
+    key: &'a Ident,
+    result: &'a Ident,
+    // 再次查询缓存（Option<结果>，不计入命中统计）、计算结果、把 result 写入缓存（键需要克隆）
+    lookup: proc_macro2::TokenStream,
+    compute: proc_macro2::TokenStream,
+    store: proc_macro2::TokenStream,
+}
+
+fn generate_single_flight(flight: SingleFlight) -> proc_macro2::TokenStream {
+    let SingleFlight { fn_name, table_name, flights, key, result, lookup, compute, store } = flight;
+    let flight_struct = Ident::new(
+        &format!("MemoFlight{}", to_upper_camel_case(&fn_name.to_string())),
+        fn_name.span()
+    );
+
+    quote! {{
+        let (__mau_flight, __mau_leader) = {
+            let mut __mau_tables = #table_name.lock().unwrap();
+            let __mau_flights = #flights(&mut __mau_tables, &#key);
+            match __mau_flights.get(&#key) {
+                Some(flight) if flight.owner == ::std::thread::current().id() => (None, false),
+                Some(flight) => (Some(flight.clone()), false),
+                None => {
+                    let flight = ::std::sync::Arc::new(#flight_struct::new());
+                    __mau_flights.insert(#key.clone(), flight.clone());
+                    (Some(flight), true)
+                }
+            }
+        };
+        match __mau_flight {
+            Some(__mau_flight) if __mau_leader => {
+                // 先取出查询结果，释放查询时持有的锁
+                let __mau_cached = #lookup;
+                let __mau_outcome = match __mau_cached {
+                    Some(#result) => Ok(#result),
+                    None => {
+                        let __mau_outcome = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| #compute));
+                        if let Ok(#result) = &__mau_outcome {
+                            #store
+                        }
+                        __mau_outcome
+                    }
+                };
+                #flights(&mut #table_name.lock().unwrap(), &#key).remove(&#key);
+                __mau_flight.finish(&__mau_outcome);
+                match __mau_outcome {
+                    Ok(#result) => #result,
+                    Err(payload) => ::std::panic::resume_unwind(payload),
+                }
+            }
+            Some(__mau_flight) => __mau_flight.wait(),
+            None => {
+                let #result = #compute;
+                #store
+                #result
+            }
+        }
+    }}
+}
+
+// 生成方法版本所需的信息
+struct MethodMemo<'a> {
+    input_fn: &'a ItemFn,
+    thread_mode: &'a str,
+    helpers: &'a Helpers,
+    should_clear_in_start: bool,
+    // 补充了 'static 等约束的泛型参数、调用自身时的 turbofish、方法自身的类型参数
+    generics: &'a syn::Generics,
+    turbofish: &'a proc_macro2::TokenStream,
+    type_params: &'a [Ident],
+    // 解构参数替换为隐藏标识符、去掉 mut 之后的参数列表，以及闭包中重新绑定原模式的语句
+    memo_inputs: &'a Punctuated<FnArg, syn::Token![,]>,
+    rebindings: &'a [proc_macro2::TokenStream],
+    cache_if: Option<&'a CacheIf>,
+    predicate_args: &'a [proc_macro2::TokenStream],
+    call_args: &'a [proc_macro2::TokenStream],
+    key_tuple: &'a proc_macro2::TokenStream,
+    // share 时的共享指针路径（Rc / Arc）
+    share: Option<&'a proc_macro2::TokenStream>,
+    // stack=grow 时新线程的栈大小（字节）
+    stack_bytes: Option<usize>,
+    // 未命中后、计算前的循环调用检测语句
+    cycle_check: proc_macro2::TokenStream,
+    // RefKey、MemoCache 等结构体定义，放在方法体内
+    support_items: proc_macro2::TokenStream,
+}
+
+// 生成方法（带 self 接收者）的记忆化代码
+// impl 块中只能出现关联函数，因此：
+//   - 结构体定义放在方法体内
+//   - 缓存以 Box<dyn Any> 类型擦除后放在隐藏的访问函数 __mau_cache_{name} 中，
+//     按 (TypeId, Self 的类型名) 区分泛型 impl / 泛型方法的每个单态化：访问函数中的静态变量
+//     由泛型 impl 的所有单态化共享，而 Self 可能带有生命周期参数，不能放进 TypeId（要求 'static），
+//     因此用 type_name::<Self>() 区分（生命周期不影响单态化）；方法体内的 __mau_open
+//     （键类型由参数推断）downcast 回具体类型，_clear 直接丢弃所有缓存
+//   - 原函数体放在闭包中执行（可以使用 self）
+// helpers=none 时访问函数定义在方法体内，只生成方法本身（用于 trait impl）
+fn generate_method_memo(memo: MethodMemo) -> proc_macro2::TokenStream {
+    let MethodMemo {
+        input_fn, thread_mode, helpers, should_clear_in_start, generics, turbofish, type_params,
+        memo_inputs, rebindings, cache_if, predicate_args, call_args, key_tuple, share, stack_bytes,
+        cycle_check, support_items,
+    } = memo;
+    let where_clause = &generics.where_clause;
+    let fn_name = &input_fn.sig.ident;
+    let fn_vis = &input_fn.vis;
+    let fn_block = &input_fn.block;
+    let fn_output = &input_fn.sig.output;
+    let fn_attrs = &input_fn.attrs;
+    let value_type = match fn_output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => quote! { #ty },
+    };
+    let (return_type, memo_output) = match share {
+        Some(ptr) => {
+            let return_type = quote! { #ptr<#value_type> };
+            (return_type.clone(), quote! { -> #return_type })
+        }
+        None => (value_type.clone(), quote! { #fn_output }),
+    };
+
+    let accessor_name = Ident::new(&format!("__mau_cache_{}", fn_name), fn_name.span());
+    let cache_name = Ident::new(&format!("{}_CACHE", fn_name.to_string().to_uppercase()), fn_name.span());
+
+    let accessor = if thread_mode == "multi" {
+        let map_ty = quote! { ::std::collections::HashMap<(::std::any::TypeId, &'static str), ::std::boxed::Box<dyn ::std::any::Any + Send>> };
+        quote! {
+            fn #accessor_name() -> &'static ::std::sync::Mutex<#map_ty> {
+                static #cache_name: ::std::sync::LazyLock<::std::sync::Mutex<#map_ty>> =
+                    ::std::sync::LazyLock::new(|| ::std::sync::Mutex::new(::std::collections::HashMap::new()));
+                &#cache_name
+            }
+        }
+    } else {
+        let map_ty = quote! { ::std::collections::HashMap<(::std::any::TypeId, &'static str), ::std::boxed::Box<dyn ::std::any::Any>> };
+        quote! {
+            fn #accessor_name() -> &'static ::std::thread::LocalKey<::std::cell::RefCell<#map_ty>> {
+                ::std::thread_local! {
+                    static #cache_name: ::std::cell::RefCell<#map_ty> = ::std::cell::RefCell::new(::std::collections::HashMap::new());
+                }
+                &#cache_name
+            }
+        }
+    };
+    let accessor_call = if helpers.enabled {
+        quote! { Self::#accessor_name() }
+    } else {
+        quote! { #accessor_name() }
+    };
+
+    // 从类型擦除的缓存中取出具体的 MemoCache（第一次使用时创建）
+    let cache_struct = Ident::new(
+        &format!("MemoCache{}", to_upper_camel_case(&fn_name.to_string())),
+        fn_name.span()
+    );
+    let send = if thread_mode == "multi" { quote! { + Send } } else { quote! {} };
+    let open_cache = quote! {
+        fn __mau_open<'a, K: ::std::hash::Hash + Eq + Clone #send + 'static, V: Clone #send + 'static, P: 'static>(
+            caches: &'a mut ::std::collections::HashMap<(::std::any::TypeId, &'static str), ::std::boxed::Box<dyn ::std::any::Any #send>>,
+            self_type: &'static str,
+            _key: &K,
+        ) -> &'a mut #cache_struct<K, V> {
+            caches
+                .entry((::std::any::TypeId::of::<(#cache_struct<K, V>, P)>(), self_type))
+                .or_insert_with(|| ::std::boxed::Box::new(#cache_struct::<K, V>::new()))
+                .downcast_mut::<#cache_struct<K, V>>()
+                .expect("memo: cache type mismatch")
+        }
+    };
+    let downcast = quote! {
+        __mau_open::<_, #return_type, (#(#type_params,)*)>(&mut __mau_cell, ::std::any::type_name::<Self>(), &__mau_key)
+    };
+
+    let (lookup, store, clear_impl) = if thread_mode == "multi" {
+        (
+            quote! { { let mut __mau_cell = #accessor_call.lock().unwrap(); #downcast.get(&__mau_key) } },
+            quote! { { let mut __mau_cell = #accessor_call.lock().unwrap(); #downcast.insert(__mau_key.clone(), __mau_result.clone()); } },
+            quote! { Self::#accessor_name().lock().unwrap().clear(); },
+        )
+    } else {
+        (
+            quote! { #accessor_call.with(|__mau_cell| { let mut __mau_cell = __mau_cell.borrow_mut(); #downcast.get(&__mau_key) }) },
+            quote! { #accessor_call.with(|__mau_cell| { let mut __mau_cell = __mau_cell.borrow_mut(); #downcast.insert(__mau_key, __mau_result.clone()); }); },
+            quote! { Self::#accessor_name().with(|__mau_cell| __mau_cell.borrow_mut().clear()); },
+        )
+    };
+
+    let local_accessor = if helpers.enabled { quote! {} } else { accessor.clone() };
+    let store = guard_cache_store(
+        cache_if,
+        predicate_args,
+        &Ident::new("__mau_result", proc_macro2::Span::call_site()),
+        store,
+    );
+
+    // 原函数体：语句直接放进闭包体，单个表达式的函数体不会多出一层花括号（unused_braces）
+    let fn_stmts = &fn_block.stmts;
+    let compute = quote! {
+        (|| -> #value_type {
+            #(#rebindings)*
+            #(#fn_stmts)*
+        })()
+    };
+    let compute = match share {
+        Some(ptr) => quote! { #ptr::new(#compute) },
+        None => compute,
+    };
+    let result_ident = Ident::new("__mau_result", proc_macro2::Span::call_site());
+    let (flight_items, compute_and_store) = if thread_mode == "multi" {
+        // 同一个键的并发调用只计算一次；进行中的计算表放在方法体内
+        let table_name = Ident::new("__MAU_IN_FLIGHT", proc_macro2::Span::call_site());
+        let flights_name = Ident::new("__mau_flights", proc_macro2::Span::call_site());
+        let single_flight = generate_single_flight(SingleFlight {
+            fn_name,
+            table_name: &table_name,
+            flights: quote! { #flights_name::<_, #return_type, (#(#type_params,)*)> },
+            key: &Ident::new("__mau_key", proc_macro2::Span::call_site()),
+            result: &result_ident,
+            lookup: quote! { { let mut __mau_cell = #accessor_call.lock().unwrap(); #downcast.lookup(&__mau_key) } },
+            compute,
+            store,
+        });
+        (
+            generate_single_flight_items(fn_name, &table_name, &flights_name),
+            quote! {
+                #[allow(clippy::redundant_closure_call)]
+                let __mau_result = #single_flight;
+                __mau_result
+            },
+        )
+    } else {
+        (
+            quote! {},
+            quote! {
+                #[allow(clippy::redundant_closure_call)]
+                let __mau_result = #compute;
+                #store
+                __mau_result
+            },
+        )
+    };
+
+    let method = quote! {
+        #(#fn_attrs)*
+        #fn_vis fn #fn_name #generics (#memo_inputs) #memo_output #where_clause {
+            #support_items
+            #open_cache
+            #local_accessor
+            #flight_items
+
+            let __mau_key = #key_tuple;
+            if let Some(__mau_result) = #lookup {
+                return __mau_result;
+            }
+            #cycle_check
+            #compute_and_store
+        }
+    };
+
+    if !helpers.enabled {
+        return method;
+    }
+
+    let start_impl = if should_clear_in_start {
+        quote! {
+            let result = Self::#fn_name #turbofish (self, #(#call_args),*);
+            #clear_impl
+            result
+        }
+    } else {
+        quote! {
+            Self::#fn_name #turbofish (self, #(#call_args),*)
+        }
+    };
+    let start_impl = match stack_bytes {
+        Some(stack_bytes) => grow_stack(fn_name, stack_bytes, start_impl),
+        None => start_impl,
+    };
+
+    // share：{name}_with(参数.., |&T| ..) 把缓存值的借用交给回调
+    let with_fn = if let (Some(_), Some(with_name)) = (share, helpers.name(fn_name, "with")) {
+        let mut with_generics = generics.clone();
+        with_generics.params.push(syn::parse_quote!(__MauR));
+        let mut with_inputs = memo_inputs.clone();
+        with_inputs.push(syn::parse_quote!(__mau_with: impl FnOnce(&#value_type) -> __MauR));
+        quote! {
+            #fn_vis fn #with_name #with_generics (#with_inputs) -> __MauR #where_clause {
+                __mau_with(&Self::#fn_name #turbofish (self, #(#call_args),*))
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // 清除缓存函数
+    let clear_fn = helpers.name(fn_name, "clear").map(|clear_name| quote! {
+        #fn_vis fn #clear_name() {
+            #clear_impl
+        }
+    });
+    // 最外层函数：根据 lifetime 决定是否清除缓存
+    let start_fn = helpers.name(fn_name, "start").map(|start_name| quote! {
+        #fn_vis fn #start_name #generics (#memo_inputs) #memo_output #where_clause {
+            #start_impl
+        }
+    });
+
+    quote! {
+        #[doc(hidden)]
+        #accessor
+
+        #clear_fn
+
+        #method
+
+        #start_fn
+
+        #with_fn
+    }
+}
+
+/// memo 宏：为函数添加记忆化缓存
+#[proc_macro_attribute]
+pub fn memo(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut input_fn = parse_macro_input!(item as ItemFn);
+    let key_args = parse_macro_input!(attr as KeyArgs);
+    desugar_impl_trait_args(&mut input_fn.sig);
+    
+    // 解析线程模式、键模式和生命周期模式
+    let (thread_mode, index_mode, lifetime_mode) = parse_memo_modes(&key_args);
+    // 解析缓存结构体配置（容量、淘汰策略、过期时间）
+    let cache_options = parse_cache_options(&key_args);
+    // 解析结果写入缓存的条件
+    let cache_if = parse_cache_if(&key_args);
+    // 解析自定义缓存键
+    let key_canon = parse_key_canon(&key_args);
+    // 解析 persist：缓存保存到文件
+    let persist = parse_persist(&key_args);
+    // 解析 stack=grow：_start 在大栈的新线程中执行
+    let stack_bytes = parse_stack(&key_args);
+    // 解析 on_cycle：循环调用的处理方式
+    let on_cycle = parse_on_cycle(&key_args);
+    // 解析 fixpoint：循环的递归定义按最小不动点求值
+    let fixpoint = parse_fixpoint(&key_args);
+    // 解析 share：缓存共享指针而不是值
+    let share = parse_share(&key_args, &thread_mode);
+    // 解析 scope=instance：缓存由调用者持有
+    let instance_scope = parse_scope(&key_args);
+    // handle：生成实现 ::mau::Memo 的句柄 Memo{Fn}
+    let handle = key_args.flags.contains("handle");
+    if instance_scope && key_args.named_args.contains_key("lifetime") {
+        panic!("scope=instance 的缓存随 {{Fn}}Cache 一起释放，不能再指定 lifetime");
+    }
+    // 解析统计输出：stats=print 在程序退出时打印统计摘要
+    let stats_report = match key_args.named_args.get("stats").map(|s| s.as_str()) {
+        None => false,
+        Some("print") => true,
+        Some(s) => panic!("无效的 stats 选项 '{}'. 只支持 'print'", s),
+    };
+
+    let fn_name = &input_fn.sig.ident;
+    let fn_vis = &input_fn.vis;
+    let fn_block = &input_fn.block;
+    let fn_inputs = &input_fn.sig.inputs;
+    let fn_output = &input_fn.sig.output;
+    let fn_generics = &input_fn.sig.generics;
+
+    // 三层函数名（使用双下划线前缀避免与用户代码冲突）
+    let inner_name = Ident::new(&format!("__mau_inner_{}", fn_name), fn_name.span());  // 最内层：原函数体
+    // 中间层保持原名 fn_name，最外层 {fn}_start 和其余辅助函数的名字由 helpers 决定
+
+    // 哈希表的名字 - 使用更安全的命名避免冲突
+    let cache_name = Ident::new(&format!("{}_CACHE", fn_name.to_string().to_uppercase()), fn_name.span());
+
+    // 方法接收者（&self / self / self: Box<Self> 等），单独处理
+    let receiver = input_fn.sig.inputs.iter().find_map(|arg| match arg {
+        FnArg::Receiver(receiver) => Some(receiver),
+        FnArg::Typed(_) => None,
+    });
+    let receiver_mode = parse_receiver_mode(&key_args, &index_mode, receiver.is_some());
+    let helpers = parse_memo_helpers(&key_args);
+
+    // 泛型参数：类型参数通过 TypeId 区分缓存，const 参数作为值参与键，生命周期参数不影响缓存
+    let type_params: Vec<Ident> = fn_generics.type_params().map(|param| param.ident.clone()).collect();
+    let const_params: Vec<(Ident, Type)> = fn_generics
+        .const_params()
+        .map(|param| (param.ident.clone(), param.ty.clone()))
+        .collect();
+    let generic_args: Vec<&Ident> = fn_generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            syn::GenericParam::Type(param) => Some(&param.ident),
+            syn::GenericParam::Const(param) => Some(&param.ident),
+            syn::GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+    let turbofish = if generic_args.is_empty() {
+        quote! {}
+    } else {
+        quote! { ::<#(#generic_args),*> }
+    };
+    // 缓存在静态变量中，类型参数需要 'static（multi 模式还需要 Send）
+    // 约束追加到已有的 where 子句谓词上（若有），否则追加到参数声明处
+    let mut cached_generics = fn_generics.clone();
+    let extra_bounds: Vec<syn::TypeParamBound> = if thread_mode == "multi" {
+        vec![syn::parse_quote! { Send }, syn::parse_quote! { 'static }]
+    } else {
+        vec![syn::parse_quote! { 'static }]
+    };
+    let (params, where_clause) = (&mut cached_generics.params, &mut cached_generics.where_clause);
+    for param in params.iter_mut() {
+        let syn::GenericParam::Type(param) = param else { continue };
+        let predicate = where_clause
+            .iter_mut()
+            .flat_map(|clause| clause.predicates.iter_mut())
+            .find_map(|predicate| match predicate {
+                syn::WherePredicate::Type(predicate) => match &predicate.bounded_ty {
+                    Type::Path(path) if path.qself.is_none() && path.path.is_ident(&param.ident) => Some(predicate),
+                    _ => None,
+                },
+                _ => None,
+            });
+        match predicate {
+            Some(predicate) => predicate.bounds.extend(extra_bounds.iter().cloned()),
+            None => param.bounds.extend(extra_bounds.iter().cloned()),
+        }
+    }
+    let cached_where = &cached_generics.where_clause;
+
+    // 提取参数和类型
+    // 非简单标识符的参数（元组、结构体、切片等解构模式）绑定到隐藏标识符 __mau_arg_{i}：
+    // 中间层和最外层使用隐藏标识符（用于生成键），最内层保持原模式重新解构
+    // 中间层和最外层不修改参数，mut 绑定同样去掉，由最内层（方法为闭包）重新绑定
+    // cache_if 谓词在计算之后还要使用参数：非引用参数克隆后再交给原函数体
+    // fixpoint 的循环入口会重复计算：非引用参数同样克隆
+    let has_predicate = matches!(cache_if, Some(CacheIf::Predicate(_)));
+    let mut memo_inputs = input_fn.sig.inputs.clone();
+    let mut rebindings = Vec::new();
+    let mut args = Vec::new();
+    let mut param_types = Vec::new();
+    for (i, arg) in memo_inputs.iter_mut().enumerate() {
+        if let FnArg::Typed(pat_type) = arg {
+            let pat = (*pat_type.pat).clone();
+            let ident = match &mut *pat_type.pat {
+                Pat::Ident(pat_ident @ PatIdent { subpat: None, .. }) => {
+                    pat_ident.mutability = None;
+                    pat_ident.ident.clone()
+                }
+                _ => {
+                    let hidden = Ident::new(&format!("__mau_arg_{}", i), pat.span());
+                    *pat_type.pat = syn::parse_quote! { #hidden };
+                    hidden
+                }
+            };
+            let is_reference = matches!(&*pat_type.ty, Type::Reference(_));
+            let is_ignored = key_canon.ignored.contains(&ident);
+            if has_predicate && !is_reference && !is_ignored {
+                rebindings.push(quote! { let #pat = ::std::clone::Clone::clone(&#ident); });
+            } else if !matches!(&pat, Pat::Ident(PatIdent { mutability: None, subpat: None, .. })) {
+                rebindings.push(quote! { let #pat = #ident; });
+            }
+            args.push(ident);
+            param_types.push((*pat_type.ty).clone());
+        }
+    }
+    for name in &key_canon.ignored {
+        if !args.contains(name) {
+            return syn::Error::new(name.span(), format!("ignore: 没有名为 {} 的参数", name)).to_compile_error().into();
+        }
+    }
+
+    // cache_if 谓词和 key_with 键函数的参数：非引用参数传引用，引用参数原样传入，被忽略的参数不传
+    let predicate_args: Vec<_> = args
+        .iter()
+        .zip(&param_types)
+        .filter(|(arg, _)| !key_canon.ignored.contains(arg))
+        .map(|(arg, ty)| match ty {
+            Type::Reference(_) => quote! { #arg },
+            _ => quote! { &#arg },
+        })
+        .collect();
+
+    // 提取参数中的不可变引用
+    // 被忽略的参数不参与键，允许 &mut
+    let mut immutable_references = HashSet::<String>::new();
+    for arg in memo_inputs.iter() {
+        if let FnArg::Typed(pat_type) = arg {
+            if matches!(&*pat_type.pat, Pat::Ident(pat_ident) if key_canon.ignored.contains(&pat_ident.ident)) {
+                continue;
+            }
+            if let Type::Reference(ty_ref) = &*pat_type.ty {
+                if ty_ref.mutability.is_none() {
+                    if let Pat::Ident(pat_ident) = &*pat_type.pat {
+                        immutable_references.insert(pat_ident.ident.to_string());
+                    }
+                } else {
+                    if let Pat::Ident(pat_ident) = &*pat_type.pat {
+                        return syn::Error::new(ty_ref.span(), format!("memo supports only immutable references in parameters, but {} is mutable (use ignore({}) to leave it out of the key)", pat_ident.ident, pat_ident.ident))
+                            .to_compile_error()
+                            .into();
+                    }
+                }
+            }
+        }
+    }
+
+    // 根据索引模式处理引用参数
+    let mut key_args = Vec::new();
+    let mut key_types = Vec::new();
+    let mut key_exprs = Vec::new();
+
+    // 接收者作为键的第一部分（receiver=ignore 时不参与）
+    let mut receiver_is_reference = false;
+    if let (Some(receiver), Some(mode)) = (receiver, receiver_mode.as_deref()) {
+        let self_ident = Ident::new("self", receiver.self_token.span);
+        if let Type::Reference(ty_ref) = &*receiver.ty {
+            if ty_ref.mutability.is_some() {
+                return syn::Error::new(receiver.span(), "memo supports only immutable receivers, use receiver=ignore for &mut self")
+                    .to_compile_error()
+                    .into();
+            }
+            receiver_is_reference = true;
+            match mode {
+                "ref" => generate_normal_mode_key(fn_name, &self_ident, &receiver.ty, &mut key_types, &mut key_exprs),
+                "val" => generate_heavy_mode_key(&self_ident, &receiver.ty, &mut key_types, &mut key_exprs),
+                _ => generate_ptr_mode_key(&self_ident, &receiver.ty, &mut key_types, &mut key_exprs),
+            }
+        } else {
+            // self / self: Box<Self> 等按值接收者：克隆
+            let ty = &receiver.ty;
+            key_types.push(quote! { #ty });
+            key_exprs.push(quote! { self.clone() });
+        }
+    }
+    
+    // 每个参数的键在 key_types / key_exprs 中的位置（自定义键时替换）
+    let mut arg_key_index = Vec::new();
+    for (arg, ty) in args.iter().zip(&param_types) {
+        key_args.push(arg.clone());
+        arg_key_index.push(key_types.len());
+        if key_canon.ignored.contains(arg) {
+            // 上下文参数：不参与键
+            continue;
+        }
+        
+        if immutable_references.contains(&arg.to_string()) {
+            // 根据键模式处理引用参数
+            match index_mode.as_str() {
+                "ptr" => {
+                    // ptr 模式：使用 (地址, 长度) 作为键（原 light）
+                    generate_ptr_mode_key(arg, ty, &mut key_types, &mut key_exprs);
+                },
+                "ref" => {
+                    // ref 模式：解开一层引用（原 normal，默认）
+                    generate_normal_mode_key(fn_name, arg, ty, &mut key_types, &mut key_exprs);
+                },
+                "val" => {
+                    // val 模式：完全还原（原 heavy）
+                    generate_heavy_mode_key(arg, ty, &mut key_types, &mut key_exprs);
+                },
+                _ => {
+                    // 默认使用 ptr 模式
+                    generate_ptr_mode_key(arg, ty, &mut key_types, &mut key_exprs);
+                }
+            }
+        } else {
+            // 对于非引用参数，克隆参数以避免移动
+            key_types.push(quote! { #ty });
+            key_exprs.push(quote! { #arg.clone() });
+        }
+    }
+
+    // 自定义缓存键：替换对应参数的键
+    let key_index_of = |name: &Ident| -> Option<usize> {
+        args.iter().position(|arg| arg == name).map(|i| arg_key_index[i])
+    };
+    for name in &key_canon.sorted {
+        let Some(index) = key_index_of(name) else {
+            return syn::Error::new(name.span(), format!("sorted: 没有名为 {} 的参数", name)).to_compile_error().into();
+        };
+        let ty = &param_types[args.iter().position(|arg| arg == name).unwrap()];
+        let Some(elem) = sorted_element_type(ty) else {
+            return syn::Error::new(name.span(), "sorted 只支持 Vec<T>、[T; N]、&[T]、&Vec<T> 或 &[T; N] 类型的参数")
+                .to_compile_error()
+                .into();
+        };
+        key_types[index] = quote! { ::std::vec::Vec<#elem> };
+        key_exprs[index] = quote! {{
+            let mut items: ::std::vec::Vec<#elem> = #name.iter().cloned().collect();
+            items.sort_unstable();
+            items
+        }};
+    }
+    if !key_canon.symmetric.is_empty() {
+        let mut indices = Vec::new();
+        for name in &key_canon.symmetric {
+            match key_index_of(name) {
+                Some(index) => indices.push(index),
+                None => {
+                    return syn::Error::new(name.span(), format!("symmetric: 没有名为 {} 的参数", name)).to_compile_error().into();
+                }
+            }
+        }
+        // 按参数值排序，交换参数后得到同一个键
+        let names = &key_canon.symmetric;
+        let parts: Vec<_> = indices.iter().map(|&index| key_exprs[index].clone()).collect();
+        let sorted_parts: Vec<_> = (0..names.len()).map(|i| Ident::new(&format!("__mau_sym_{}", i), proc_macro2::Span::call_site())).collect();
+        let part_ty = key_types[indices[0]].clone();
+        let part_tys = vec![part_ty; names.len()];
+        let first = indices[0];
+        key_types[first] = quote! { (#(#part_tys),*) };
+        key_exprs[first] = quote! {{
+            let mut __mau_sym = [#((&#names, #parts)),*];
+            __mau_sym.sort_by(|a, b| a.0.partial_cmp(b.0).unwrap_or(::std::cmp::Ordering::Equal));
+            let [#((_, #sorted_parts)),*] = __mau_sym;
+            (#(#sorted_parts),*)
+        }};
+        let mut removed: Vec<_> = indices[1..].to_vec();
+        removed.sort_unstable_by(|a, b| b.cmp(a));
+        for index in removed {
+            key_types.remove(index);
+            key_exprs.remove(index);
+        }
+    }
+    if let Some(key_fn) = &key_canon.key_with {
+        // 键类型由键函数决定，由类型擦除的缓存表推断
+        let start = arg_key_index.first().copied().unwrap_or(key_types.len());
+        key_types.truncate(start);
+        key_exprs.truncate(start);
+        key_types.push(quote! { _ });
+        key_exprs.push(quote! { #key_fn(#(#predicate_args),*) });
+    }
+
+    for (param, ty) in &const_params {
+        key_types.push(quote! { #ty });
+        key_exprs.push(quote! { #param });
+    }
+
+    // dense(...) / tabulate(...)：键必须恰好由声明了范围的参数组成，按参数顺序排列各维度
+    let mut cache_options = cache_options;
+    if let Some(dense) = &mut cache_options.dense {
+        let option = if dense.tabulate.is_some() { "tabulate" } else { "dense" };
+        if key_canon.key_with.is_some() || !key_canon.symmetric.is_empty() || !key_canon.sorted.is_empty() {
+            panic!("{}(...) 不能与 key_with / symmetric / sorted 同时使用", option);
+        }
+        if receiver_mode.is_some() {
+            return syn::Error::new(receiver.span(), format!("{}(...) 要求接收者不参与键，请使用 receiver=ignore", option))
+                .to_compile_error()
+                .into();
+        }
+        if !const_params.is_empty() {
+            return syn::Error::new(fn_generics.span(), format!("{}(...) 不支持 const 泛型参数", option))
+                .to_compile_error()
+                .into();
+        }
+        for (name, _, _) in &dense.dims {
+            let Some(i) = args.iter().position(|arg| arg == name) else {
+                return syn::Error::new(name.span(), format!("{}: 没有名为 {} 的参数", option, name)).to_compile_error().into();
+            };
+            if key_canon.ignored.contains(name) {
+                panic!("参数 {} 被 ignore 忽略，不能再用于 {}", name, option);
+            }
+            if matches!(param_types[i], Type::Reference(_)) {
+                return syn::Error::new(name.span(), format!("{} 只支持按值传入的整数参数，{} 是引用", option, name))
+                    .to_compile_error()
+                    .into();
+            }
+        }
+        let mut dims = Vec::new();
+        for arg in args.iter().filter(|arg| !key_canon.ignored.contains(arg)) {
+            let Some(position) = dense.dims.iter().position(|(name, _, _)| name == arg) else {
+                return syn::Error::new(arg.span(), format!("参数 {} 没有声明 {} 范围（不参与键的参数请使用 ignore({})）", arg, option, arg))
+                    .to_compile_error()
+                    .into();
+            };
+            dims.push(dense.dims.swap_remove(position));
+        }
+        if let Some((name, _, _)) = dense.dims.first() {
+            return syn::Error::new(name.span(), format!("{}: 参数 {} 重复声明", option, name)).to_compile_error().into();
+        }
+        dense.dims = dims;
+    }
+
+    if key_types.is_empty() { return quote! { #input_fn }.into(); }
+
+    let key_type = if key_types.len() == 1 {
+        quote! { #(#key_types)* }
+    } else {
+        quote! { (#(#key_types),*) }
+    };
+    
+    let key_tuple = quote! { (#(#key_exprs),*) };
+    // 循环调用的路径中，多个部分的键直接显示为参数列表
+    let key_is_tuple = key_types.len() != 1 && key_canon.key_with.is_none();
+
+    // 对于函数调用，我们直接使用原始参数名（收集到 Vec 以便多次使用）
+    let call_args: Vec<_> = args.iter().map(|arg| quote! { #arg }).collect();
+
+    let inner_args: Vec<_> = args
+        .iter()
+        .zip(&param_types)
+        .map(|(arg, ty)| match ty {
+            Type::Reference(_) => quote! { #arg },
+            _ if has_predicate && !key_canon.ignored.contains(arg) => quote! { #arg.clone() },
+            _ if fixpoint.is_some() => quote! { #arg.clone() },
+            _ => quote! { #arg },
+        })
+        .collect();
+    let result_ident = Ident::new("result", proc_macro2::Span::call_site());
+
+    let value_type = match fn_output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => quote! { #ty },
+    };
+    // share：缓存和对外签名使用共享指针，原函数体仍然返回值本身
+    let (return_type, memo_output) = match &share {
+        Some(ptr) => {
+            let return_type = quote! { #ptr<#value_type> };
+            (return_type.clone(), quote! { -> #return_type })
+        }
+        None => (value_type.clone(), quote! { #fn_output }),
+    };
+    let call_inner = |call: proc_macro2::TokenStream| match &share {
+        Some(ptr) => quote! { #ptr::new(#call) },
+        None => call,
+    };
+
+    // 先判断是否应该清除缓存
+    // key_with / sorted 的参数按内容参与键，不算作地址
+    let has_ref_params = key_canon.key_with.is_none()
+        && immutable_references
+            .iter()
+            .any(|name| !key_canon.sorted.iter().any(|sorted| sorted == name));
+    let key_contains_address = (has_ref_params && (index_mode == "ptr" || index_mode == "ref"))
+        || (receiver_is_reference && matches!(receiver_mode.as_deref(), Some("ptr" | "ref")));
+    
+    // 每个函数专用的缓存结构体（容量、淘汰策略、过期都在结构体内部实现）
+    let cache_struct = match &cache_options.dense {
+        Some(dense) => generate_dense_cache_struct(fn_name, dense, &cache_options.hasher, &key_type),
+        None if cache_options.shards.is_some() => {
+            let cache_struct = generate_cache_struct(fn_name, &cache_options);
+            let shards_struct = generate_sharded_cache_struct(fn_name, &cache_options);
+            quote! { #cache_struct #shards_struct }
+        }
+        None => generate_cache_struct(fn_name, &cache_options),
+    };
+    let cache_struct_name = Ident::new(
+        &format!("MemoCache{}", to_upper_camel_case(&fn_name.to_string())),
+        fn_name.span()
+    );
+    let cache_ty = quote! { #cache_struct_name<#key_type, #return_type> };
+
+    // 检查是否需要生成 RefKey 结构体
+    // 只要 key_type 中包含 "RefKey"，就需要生成结构体定义
+    let key_type_str = key_type.to_string();
+    let ref_key_struct = if key_type_str.contains("RefKey") {
+        generate_ref_key_struct(fn_name)
+    } else {
+        quote! {}
+    };
+
+    // 根据 lifetime 模式和 key 模式决定 _start 是否清除缓存
+    // 缓存清除策略：
+    // - 如果使用 ptr 或 ref 模式：总是清除缓存（无论 program 还是 problem）
+    // - 如果使用 val 模式或没有引用参数：
+    //   * problem 模式：清除缓存
+    //   * program 模式：保留缓存（相同输入可以跨问题复用）
+    let should_clear_in_start = key_contains_address || lifetime_mode == "problem";
+
+    let fn_async = &input_fn.sig.asyncness;
+    let fn_await = if fn_async.is_some() { quote! { .await } } else { quote! {} };
+
+    if fn_async.is_some() && !matches!(on_cycle, OnCycle::Off) {
+        panic!("on_cycle / cycle_check 暂不支持 async 函数");
+    }
+    if fixpoint.is_some() {
+        if thread_mode == "multi" {
+            panic!("fixpoint 暂不支持 thread=multi");
+        }
+        if receiver.is_some() {
+            panic!("fixpoint 暂不支持方法");
+        }
+        if fn_async.is_some() {
+            panic!("fixpoint 暂不支持 async 函数");
+        }
+        if cache_if.is_some() {
+            panic!("fixpoint 不能与 cache_if 同时使用：收敛的结果必须全部写入缓存");
+        }
+        if !matches!(on_cycle, OnCycle::Off) {
+            panic!("fixpoint 不能与 on_cycle / cycle_check 同时使用：再次进入时返回当前的近似值");
+        }
+    }
+    let tabulate = cache_options.dense.as_ref().and_then(|dense| dense.tabulate.as_ref());
+    if tabulate.is_some() {
+        if thread_mode == "multi" {
+            panic!("tabulate 暂不支持 thread=multi");
+        }
+        if receiver.is_some() {
+            panic!("tabulate 暂不支持方法");
+        }
+        if fn_async.is_some() {
+            panic!("tabulate 暂不支持 async 函数");
+        }
+        if !type_params.is_empty() {
+            panic!("tabulate 暂不支持泛型函数");
+        }
+        if cache_if.is_some() || fixpoint.is_some() {
+            panic!("tabulate 不能与 cache_if 或 fixpoint 同时使用：整张表的每一项都要写入缓存");
+        }
+    }
+    // 循环调用检测只在设置 cycle_check 或 on_cycle 时进行（async 函数和 fixpoint 已在上面拒绝）
+    let detect_cycles = !matches!(on_cycle, OnCycle::Off);
+    let sentinel = match &on_cycle {
+        OnCycle::Value(value) => match &share {
+            Some(ptr) => quote! { #ptr::new(#value) },
+            None => quote! { #value },
+        },
+        _ => quote! {},
+    };
+    let cycle_check = |key: &str| {
+        if !detect_cycles {
+            return quote! {};
+        }
+        generate_cycle_check(
+            fn_name,
+            &on_cycle,
+            key_is_tuple,
+            &Ident::new(key, proc_macro2::Span::call_site()),
+            &type_params,
+            sentinel.clone(),
+        )
+    };
+    let cycle_items = if detect_cycles {
+        generate_cycle_items(fn_name, &on_cycle)
+    } else if fixpoint.is_some() {
+        generate_fixpoint_items(fn_name)
+    } else {
+        quote! {}
+    };
+    // 循环调用路径、fixpoint 和 tabulate 的错误信息中显示键（默认的循环检测只在 debug 构建中需要）
+    let describe_items = if fixpoint.is_some() || tabulate.is_some() || (detect_cycles && !matches!(on_cycle, OnCycle::Debug)) {
+        generate_key_describe_items(fn_name, key_is_tuple, &quote! {})
+    } else if detect_cycles {
+        generate_key_describe_items(fn_name, key_is_tuple, &quote! { #[cfg(debug_assertions)] })
+    } else {
+        quote! {}
+    };
+    let tabulate_items = if tabulate.is_some() {
+        generate_tabulate_items(fn_name)
+    } else {
+        quote! {}
+    };
+    let cycle_items = quote! {
+        #cycle_items
+        #tabulate_items
+        #describe_items
+    };
+
+    if stack_bytes.is_some() {
+        if !helpers.enabled {
+            panic!("stack=grow 作用于 _start，不能与 helpers=none 同时使用");
+        }
+        if fn_async.is_some() {
+            panic!("stack=grow 暂不支持 async 函数");
+        }
+        if thread_mode == "single" && !should_clear_in_start {
+            panic!("stack=grow 在 thread=single 时需要 lifetime=problem：新线程的缓存在调用结束后随线程丢弃");
+        }
+    }
+
+    let sharded = cache_options.shards.is_some();
+    if receiver.is_some() {
+        if stats_report {
+            panic!("stats=print 不支持方法");
+        }
+        if sharded {
+            panic!("shards 暂不支持方法");
+        }
+        if persist.is_some() {
+            panic!("persist 暂不支持方法");
+        }
+        if handle {
+            panic!("handle 暂不支持方法");
+        }
+        if instance_scope {
+            panic!("scope=instance 暂不支持方法");
+        }
+        if fn_async.is_some() {
+            return syn::Error::new(fn_async.span(), "memo does not support async methods yet")
+                .to_compile_error()
+                .into();
+        }
+        let stats_struct = generate_stats_struct(fn_name, &syn::Visibility::Inherited);
+        return generate_method_memo(MethodMemo {
+            input_fn: &input_fn,
+            thread_mode: &thread_mode,
+            helpers: &helpers,
+            should_clear_in_start,
+            generics: &cached_generics,
+            turbofish: &turbofish,
+            type_params: &type_params,
+            memo_inputs: &memo_inputs,
+            rebindings: &rebindings,
+            cache_if: cache_if.as_ref(),
+            predicate_args: &predicate_args,
+            call_args: &call_args,
+            key_tuple: &key_tuple,
+            share: share.as_ref(),
+            stack_bytes,
+            cycle_check: cycle_check("__mau_key"),
+            support_items: quote! {
+                #ref_key_struct
+                #stats_struct
+                #cache_struct
+                #cycle_items
+            },
+        })
+        .into();
+    }
+
+    let stats_struct = generate_stats_struct(fn_name, fn_vis);
+    let stats_struct_name = Ident::new(
+        &format!("{}Stats", to_upper_camel_case(&fn_name.to_string())),
+        fn_name.span()
+    );
+
+    // 泛型函数（含 impl Trait 参数）：静态变量不能使用类型参数，
+    // 缓存表以 TypeId 区分每个单态化，每个单态化是一个独立的逻辑缓存
+    // key_with：键类型由用户函数决定，宏中无法写出，同样使用类型擦除的缓存表
+    let erased_cache = !type_params.is_empty() || key_canon.key_with.is_some();
+    if fn_async.is_some() && !type_params.is_empty() {
+        return syn::Error::new(fn_generics.span(), "memo does not support generic async functions yet")
+            .to_compile_error()
+            .into();
+    }
+    if fn_async.is_some() && key_canon.key_with.is_some() {
+        return syn::Error::new(fn_name.span(), "memo does not support key_with on async functions yet")
+            .to_compile_error()
+            .into();
+    }
+    if sharded && erased_cache {
+        panic!("shards 暂不支持泛型函数和 key_with");
+    }
+    if persist.is_some() {
+        if erased_cache {
+            panic!("persist 暂不支持泛型函数和 key_with");
+        }
+        if fn_async.is_some() {
+            panic!("persist 暂不支持 async 函数");
+        }
+        if sharded || cache_options.dense.is_some() || cache_options.ttl_ms.is_some() {
+            panic!("persist 不能与 shards、dense(...) 或 ttl_ms 同时使用");
+        }
+        if should_clear_in_start {
+            panic!("persist 需要 lifetime=program，且键不包含地址（引用参数使用 key=val）");
+        }
+    }
+    if instance_scope {
+        if thread_mode == "multi" {
+            panic!("scope=instance 的缓存由调用者独占（&mut），不支持 thread=multi");
+        }
+        if erased_cache {
+            panic!("scope=instance 暂不支持泛型函数和 key_with");
+        }
+        if fn_async.is_some() {
+            panic!("scope=instance 暂不支持 async 函数");
+        }
+        if persist.is_some() || stats_report || stack_bytes.is_some() || handle {
+            panic!("scope=instance 不能与 persist、stats=print、stack=grow 或 handle 同时使用");
+        }
+        if has_ref_params && index_mode == "ptr" {
+            panic!("scope=instance 的缓存可能长期保留，引用参数需要使用 key=ref 或 key=val");
+        }
+    }
+    let erased_items = if erased_cache {
+        generate_erased_cache_items(fn_name, &thread_mode)
+    } else {
+        quote! {}
+    };
+    let sum_stats = Ident::new(&format!("__mau_stats_{}", fn_name), fn_name.span());
+    // clear_stmt / clear_impl 中的 __mau_on_start 由使用处绑定：_start 中为 true（计入 clears），
+    // _clear 和句柄中为 false（计入 manual_clears）
+    let (store_ty, store_init, clear_stmt, stats_expr) = if erased_cache {
+        let erased_trait = Ident::new(
+            &format!("MemoErased{}", to_upper_camel_case(&fn_name.to_string())),
+            fn_name.span()
+        );
+        let send = if thread_mode == "multi" { quote! { + Send } } else { quote! {} };
+        (
+            quote! { ::std::collections::HashMap<::std::any::TypeId, ::std::boxed::Box<dyn #erased_trait #send>> },
+            quote! { ::std::collections::HashMap::new() },
+            quote! {
+                for cache in cache.values_mut() {
+                    cache.clear(__mau_on_start);
+                }
+            },
+            quote! { #sum_stats(&cache) },
+        )
+    } else if sharded {
+        // 分片缓存自带锁，静态变量中不再包一层 Mutex
+        let shards_struct = Ident::new(
+            &format!("MemoShards{}", to_upper_camel_case(&fn_name.to_string())),
+            fn_name.span()
+        );
+        (
+            quote! { #shards_struct<#key_type, #return_type> },
+            quote! { #shards_struct::new() },
+            quote! { #cache_name.clear(__mau_on_start); },
+            quote! { #cache_name.stats() },
+        )
+    } else if persist.is_some() {
+        // MemoPersist 包装 MemoCache，第一次使用时从文件加载
+        let persist_struct = Ident::new(
+            &format!("MemoPersist{}", to_upper_camel_case(&fn_name.to_string())),
+            fn_name.span()
+        );
+        (quote! { #persist_struct }, quote! { #persist_struct::load() }, quote! { cache.clear(__mau_on_start); }, quote! { cache.stats() })
+    } else {
+        (cache_ty.clone(), quote! { #cache_struct_name::new() }, quote! { cache.clear(__mau_on_start); }, quote! { cache.stats() })
+    };
+    // 从缓存静态变量的守卫（RefMut / MutexGuard）取得当前单态化的 MemoCache
+    let open_name = Ident::new(&format!("__mau_open_{}", fn_name), fn_name.span());
+    let open_cache = |guard: proc_macro2::TokenStream| {
+        if erased_cache {
+            quote! { #open_name::<_, #return_type, (#(#type_params,)*)>(&mut #guard, &cache_key) }
+        } else {
+            guard
+        }
+    };
+
+    // multi 模式：查缓存 → 未命中时合并同一个键的并发计算（single-flight）
+    let flight_table = Ident::new(&format!("{}_IN_FLIGHT", fn_name.to_string().to_uppercase()), fn_name.span());
+    let flights_name = Ident::new(&format!("__mau_flights_{}", fn_name), fn_name.span());
+    let multi_cache_impl = |cache: proc_macro2::TokenStream, store: proc_macro2::TokenStream| {
+        let cycle_check = cycle_check("cache_key");
+        let single_flight = generate_single_flight(SingleFlight {
+            fn_name,
+            table_name: &flight_table,
+            flights: quote! { #flights_name::<_, #return_type, (#(#type_params,)*)> },
+            key: &Ident::new("cache_key", proc_macro2::Span::call_site()),
+            result: &result_ident,
+            lookup: quote! { #cache.lookup(&cache_key) },
+            compute: call_inner(quote! { #inner_name #turbofish (#(#inner_args),*) }),
+            store: guard_cache_store(cache_if.as_ref(), &predicate_args, &result_ident, store),
+        });
+        quote! {
+            let cache_key = #key_tuple;
+            // 检查缓存
+            if let Some(result) = #cache.get(&cache_key) {
+                return result;
+            }
+            #cycle_check
+            // 计算并缓存结果，同一个键的并发调用只计算一次
+            #single_flight
+        }
+    };
+    let flight_items = if thread_mode == "multi" && fn_async.is_none() {
+        generate_single_flight_items(fn_name, &flight_table, &flights_name)
+    } else {
+        quote! {}
+    };
+
+    let (create_cache, clear_impl, stats_impl, cache_impl) = if sharded {
+        // Multi 模式 + shards：LazyLock<MemoShards>，每个分片一个 RwLock
+        let create_cache = quote! {
+            static #cache_name: ::std::sync::LazyLock<#store_ty> = ::std::sync::LazyLock::new(|| #store_init);
+        };
+        let cache_impl = multi_cache_impl(
+            quote! { #cache_name },
+            quote! { #cache_name.insert(cache_key.clone(), result.clone()); },
+        );
+
+        (create_cache, clear_stmt.clone(), stats_expr.clone(), cache_impl)
+    } else if thread_mode == "multi" {
+        // Multi 模式：使用 Mutex<MemoCache>，支持多线程
+        let create_cache = quote! {
+            static #cache_name: ::std::sync::LazyLock<::std::sync::Mutex<#store_ty>> = ::std::sync::LazyLock::new(|| {
+                ::std::sync::Mutex::new(#store_init)
+            });
+        };
+        
+        let clear_impl = quote! {
+            let mut cache = #cache_name.lock().unwrap();
+            #clear_stmt
+        };
+
+        let stats_impl = quote! {
+            let cache = #cache_name.lock().unwrap();
+            #stats_expr
+        };
+        
+        // 锁只在每条语句内持有，计算期间不持有
+        let cached = open_cache(quote! { #cache_name.lock().unwrap() });
+        let cache_impl = multi_cache_impl(
+            cached.clone(),
+            quote! { #cached.insert(cache_key.clone(), result.clone()); },
+        );
+        
+        (create_cache, clear_impl, stats_impl, cache_impl)
+    } else {
+        // Single 模式（默认）：使用 thread_local!，真正的单线程，无锁
+        // scope=instance：thread_local 中是 {fn}_in 正在使用的缓存（嵌套调用时为栈），栈顶是当前缓存
+        let create_cache = if instance_scope {
+            quote! {
+                ::std::thread_local! {
+                    static #cache_name: ::std::cell::RefCell<::std::vec::Vec<#store_ty>> =
+                        const { ::std::cell::RefCell::new(::std::vec::Vec::new()) };
+                }
+            }
+        } else {
+            quote! {
+                ::std::thread_local! {
+                    static #cache_name: ::std::cell::RefCell<#store_ty> = ::std::cell::RefCell::new(#store_init);
+                }
+            }
+        };
+        
+        let clear_impl = quote! {
+            #cache_name.with(|cache| {
+                let mut cache = cache.borrow_mut();
+                #clear_stmt
+            });
+        };
+
+        let stats_impl = quote! {
+            #cache_name.with(|cache| {
+                let cache = cache.borrow();
+                #stats_expr
+            })
+        };
+        
+        let cached = if instance_scope {
+            quote! { cache.borrow_mut().last_mut().expect("memo: no active instance cache") }
+        } else {
+            open_cache(quote! { cache.borrow_mut() })
+        };
+        let store = guard_cache_store(cache_if.as_ref(), &predicate_args, &result_ident, quote! {
+            #cached.insert(cache_key, result.clone());
+        });
+        let compute = call_inner(quote! { #inner_name #turbofish (#(#inner_args),*) });
+        let cycle_check = cycle_check("cache_key");
+        let tabulate_fill = match tabulate {
+            Some(tabulate) => generate_tabulate_fill(TabulateFill {
+                fn_name,
+                tabulate,
+                key_is_tuple,
+                dim_types: tabulate
+                    .dims
+                    .iter()
+                    .map(|(name, _, _)| &param_types[args.iter().position(|arg| arg == name).unwrap()])
+                    .collect(),
+                cached: &cached,
+                key_tuple: &key_tuple,
+                compute: compute.clone(),
+            }),
+            None => quote! {},
+        };
+        let compute_and_store = match &fixpoint {
+            // 计算到收敛，收敛的结果全部写入缓存
+            Some(fixpoint) => {
+                let bottom = &fixpoint.bottom;
+                generate_fixpoint(FixpointFlow {
+                    fn_name,
+                    fixpoint,
+                    key_is_tuple,
+                    key: &Ident::new("cache_key", proc_macro2::Span::call_site()),
+                    result: &result_ident,
+                    value_type: &return_type,
+                    type_params: &type_params,
+                    bottom: call_inner(quote! { #bottom }),
+                    compute,
+                    store,
+                    store_settled: quote! { #cached.insert(__mau_key, __mau_value); },
+                })
+            }
+            None => quote! {
+                // 计算并缓存结果
+                let result = #compute;
+                #store
+                result
+            },
+        };
+        // scope=instance：不在 {fn}_in 之中时使用一个临时缓存
+        let instance_fallback = if instance_scope {
+            let in_name = Ident::new(&format!("{}_in", fn_name), fn_name.span());
+            let instance_struct = Ident::new(&format!("{}Cache", to_upper_camel_case(&fn_name.to_string())), fn_name.span());
+            quote! {
+                if #cache_name.with(|cache| cache.borrow().is_empty()) {
+                    return #in_name(&mut #instance_struct::new(), #(#call_args),*);
+                }
+            }
+        } else {
+            quote! {}
+        };
+        let cache_impl = quote! {
+            #instance_fallback
+            let cache_key = #key_tuple;
+            // 检查缓存
+            #cache_name.with(|cache| {
+                // 先检查缓存
+                if let Some(result) = #cached.get(&cache_key) {
+                    return result;
+                }
+                #tabulate_fill
+                #cycle_check
+                #compute_and_store
+            })
+        };
+        
+        (create_cache, clear_impl, stats_impl, cache_impl)
+    };
+
+    // async 函数：缓存 await 之后的结果，并合并同一个键的并发调用
+    let (async_items, cache_impl) = if fn_async.is_some() {
+        let async_cache = if thread_mode == "multi" && !sharded {
+            quote! { #cache_name.lock().unwrap() }
+        } else {
+            quote! { #cache_name }
+        };
+        let (items, body) = generate_async_memo(
+            fn_name,
+            &thread_mode,
+            &async_cache,
+            &key_type,
+            &return_type,
+            call_inner(quote! { #inner_name #turbofish (#(#inner_args),*).await }),
+            |cache_put| guard_cache_store(
+                cache_if.as_ref(),
+                &predicate_args,
+                &Ident::new("__mau_result", proc_macro2::Span::call_site()),
+                cache_put,
+            ),
+        );
+        (items, quote! {
+            let __mau_key = #key_tuple;
+            #body
+        })
+    } else {
+        (quote! {}, cache_impl)
+    };
+
+    // stats=print：程序退出时打印统计摘要
+    let (report_items, report_register) = if stats_report {
+        generate_stats_report(fn_name, &cache_name, &thread_mode, sharded, &stats_expr)
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    // persist：编解码、文件读写和退出时写回
+    let (persist_items, persist_register) = match &persist {
+        Some(persist) => generate_persist_items(
+            &input_fn,
+            persist,
+            &cache_name,
+            &thread_mode,
+            cache_options.capacity.as_ref().map(|(capacity, _)| *capacity),
+            &key_type,
+            &return_type,
+        ),
+        None => (quote! {}, quote! {}),
+    };
+
+    // 生成 _start 函数的实现
+    let start_impl = if should_clear_in_start {
+        // ptr/ref 模式或 problem 模式：总是清除缓存
+        quote! {
+            let result = #fn_name #turbofish (#(#call_args),*) #fn_await;
+            {
+                let __mau_on_start = true;
+                #clear_impl
+            }
+            result
+        }
+    } else {
+        // program 模式 + val 模式：保留缓存
+        quote! {
+            #fn_name #turbofish (#(#call_args),*) #fn_await
+        }
+    };
+    let start_impl = match stack_bytes {
+        Some(stack_bytes) => grow_stack(fn_name, stack_bytes, start_impl),
+        None => start_impl,
+    };
+
+    // share：{fn}_with(参数.., |&T| ..) 把缓存值的借用交给回调
+    let with_fn = match (&share, helpers.name(fn_name, "with")) {
+        (Some(_), Some(with_name)) => {
+            let mut with_generics = cached_generics.clone();
+            with_generics.params.push(syn::parse_quote!(__MauR));
+            let mut with_inputs = memo_inputs.clone();
+            with_inputs.push(syn::parse_quote!(__mau_with: impl FnOnce(&#value_type) -> __MauR));
+            quote! {
+                #fn_vis #fn_async fn #with_name #with_generics (#with_inputs) -> __MauR #cached_where {
+                    __mau_with(&#fn_name #turbofish (#(#call_args),*) #fn_await)
+                }
+            }
+        }
+        _ => quote! {},
+    };
+
+    // 生成三层函数结构
+    let expanded = quote! {
+        #ref_key_struct
+        #stats_struct
+        #cache_struct
+        #erased_items
+        #create_cache
+        #flight_items
+        #report_items
+        #persist_items
+        #cycle_items
+        #async_items
+        
+        // 最内层函数：原函数体
+        #fn_async fn #inner_name #cached_generics (#fn_inputs) #fn_output #cached_where #fn_block
+        
+        // 中间层函数（保持原名）：查缓存 → 调用内层 → 存缓存
+        #fn_vis #fn_async fn #fn_name #cached_generics (#memo_inputs) #memo_output #cached_where {
+            #report_register
+            #persist_register
+            #cache_impl
+        }
+    };
+
+    // 快照和导出：键的每个部分对应一个参数（解构的参数显示为 arg{i}），之后是 const 泛型参数
+    let key_names = args
+        .iter()
+        .enumerate()
+        .filter(|(_, arg)| !key_canon.ignored.contains(arg))
+        .map(|(i, arg)| if arg.to_string().starts_with("__mau_arg_") { format!("arg{}", i) } else { arg.to_string() })
+        .chain(const_params.iter().map(|(param, _)| param.to_string()));
+    // 辅助函数的名字：scope=instance 时为 {Fn}Cache 的方法名
+    let free_name = |suffix: &str| helpers.name(fn_name, suffix);
+    let method_name = |suffix: &str| Some(Ident::new(suffix, fn_name.span()));
+    let snapshot = |source: proc_macro2::TokenStream,
+                    receiver: Option<proc_macro2::TokenStream>,
+                    name: &dyn Fn(&str) -> Option<Ident>| {
+        generate_snapshot_items(SnapshotItems {
+            fn_name,
+            fn_vis,
+            key_type: &key_type,
+            value_type: &return_type,
+            key_parts: key_types.iter().zip(key_names.clone()).collect(),
+            key_is_tuple,
+            source,
+            receiver,
+            name,
+        })
+    };
+
+    // _cached / _insert / _invalidate / _retain 的参数：参与键的参数
+    let key_inputs: Vec<_> = memo_inputs
+        .iter()
+        .filter(|arg| !matches!(arg, FnArg::Typed(pat_type)
+            if matches!(&*pat_type.pat, Pat::Ident(pat_ident) if key_canon.ignored.contains(&pat_ident.ident))))
+        .collect();
+    let entries = |access: &dyn Fn(proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+                   receiver: Option<proc_macro2::TokenStream>,
+                   name: &dyn Fn(&str) -> Option<Ident>| {
+        generate_entry_items(EntryItems {
+            fn_vis,
+            generics: &cached_generics,
+            key_inputs: key_inputs.clone(),
+            key_tuple: &key_tuple,
+            key_type: &key_type,
+            value_type: &return_type,
+            access,
+            erased: erased_cache,
+            receiver,
+            name,
+        })
+    };
+
+    if instance_scope {
+        let instance_struct = Ident::new(&format!("{}Cache", to_upper_camel_case(&fn_name.to_string())), fn_name.span());
+        let (snapshot_methods, snapshot_helpers) = snapshot(quote! { self.inner().entries() }, Some(quote! { &self }), &method_name);
+        let entry_methods = entries(&|call| quote! { self.inner_mut().#call }, Some(quote! { &mut self }), &method_name);
+        let instance_items = generate_instance_items(InstanceScope {
+            fn_name,
+            fn_vis,
+            cache_name: &cache_name,
+            generics: &cached_generics,
+            store_ty: &store_ty,
+            store_init: &store_init,
+            stats_struct: &stats_struct_name,
+            memo_inputs: &memo_inputs,
+            call_args: &call_args,
+            memo_output: &memo_output,
+        });
+        return quote! {
+            #expanded
+            #instance_items
+            #with_fn
+
+            impl #instance_struct {
+                #snapshot_methods
+                #entry_methods
+            }
+
+            // 两个缓存中的条目（键和值）完全相同时相等，与插入顺序和统计无关；
+            // 约束写成 for<'__mau> V: PartialEq，值没有实现 PartialEq 时只是不能比较
+            impl ::std::cmp::PartialEq for #instance_struct
+            where
+                for<'__mau> #return_type: ::std::cmp::PartialEq,
+            {
+                fn eq(&self, other: &Self) -> bool {
+                    let entries = self.inner().entries();
+                    let other: ::std::collections::HashMap<#key_type, #return_type> =
+                        other.inner().entries().into_iter().collect();
+                    entries.len() == other.len()
+                        && entries.iter().all(|(key, value)| other.get(key) == ::std::option::Option::Some(value))
+                }
+            }
+
+            #snapshot_helpers
+        }
+        .into();
+    }
+
+    // 句柄 Memo{Fn}：泛型函数和 async 函数没有统一的参数和返回值类型
+    let handle = if handle {
+        if fn_async.is_some() || !type_params.is_empty() || !const_params.is_empty() {
+            panic!("handle 暂不支持 async 函数和带类型参数或 const 参数的泛型函数");
+        }
+        let (capacity, evict) = match (cache_options.declared_capacity, &cache_options.capacity) {
+            (Some(capacity), Some((_, evict))) => (quote! { Some(#capacity) }, quote! { Some(#evict) }),
+            _ => (quote! { None }, quote! { None }),
+        };
+        let ttl_ms = match cache_options.ttl_ms {
+            Some(ttl_ms) => quote! { Some(#ttl_ms) },
+            None => quote! { None },
+        };
+        let shards = match cache_options.shards {
+            Some(shards) => quote! { Some(#shards) },
+            None => quote! { None },
+        };
+        let (label, hasher, dense) = (fn_name.to_string(), &cache_options.hasher, cache_options.dense.is_some());
+        generate_memo_handle(MemoHandle {
+            fn_name,
+            fn_vis,
+            generics: fn_generics,
+            arg_types: &param_types,
+            call_args: &call_args,
+            output: &return_type,
+            clear_impl: &clear_impl,
+            stats_impl: &stats_impl,
+            start_impl: &start_impl,
+            config: quote! {
+                ::mau::MemoConfig {
+                    name: #label,
+                    thread: #thread_mode,
+                    key: #index_mode,
+                    lifetime: #lifetime_mode,
+                    capacity: #capacity,
+                    evict: #evict,
+                    ttl_ms: #ttl_ms,
+                    shards: #shards,
+                    dense: #dense,
+                    hasher: #hasher,
+                    clears_on_start: #should_clear_in_start,
+                }
+            },
+        })
+    } else {
+        quote! {}
+    };
+
+    // 泛型函数和 key_with 的键类型无法写出，不生成快照
+    let (snapshot_fns, snapshot_helpers) = if erased_cache {
+        (quote! {}, quote! {})
+    } else if sharded {
+        snapshot(quote! { #cache_name.entries() }, None, &free_name)
+    } else if thread_mode == "multi" {
+        snapshot(quote! { #cache_name.lock().unwrap().entries() }, None, &free_name)
+    } else {
+        snapshot(quote! { #cache_name.with(|cache| cache.borrow().entries()) }, None, &free_name)
+    };
+    let entry_fns = if sharded {
+        entries(&|call| quote! { #cache_name.#call }, None, &free_name)
+    } else if thread_mode == "multi" {
+        let cached = open_cache(quote! { #cache_name.lock().unwrap() });
+        entries(&|call| quote! { #cached.#call }, None, &free_name)
+    } else {
+        let cached = open_cache(quote! { cache.borrow_mut() });
+        entries(&|call| quote! { #cache_name.with(|cache| #cached.#call) }, None, &free_name)
+    };
+
+    // 清除缓存函数
+    let clear_fn = free_name("clear").map(|clear_name| quote! {
+        #fn_vis fn #clear_name() {
+            let __mau_on_start = false;
+            #clear_impl
+        }
+    });
+    // 统计信息函数（single 模式为当前线程的统计）
+    let stats_fn = free_name("stats").map(|stats_name| quote! {
+        #fn_vis fn #stats_name() -> #stats_struct_name {
+            #stats_impl
+        }
+    });
+    // 最外层函数：根据 lifetime 决定是否清除缓存
+    let start_fn = free_name("start").map(|start_name| quote! {
+        #fn_vis #fn_async fn #start_name #cached_generics (#memo_inputs) #memo_output #cached_where {
+            #start_impl
+        }
+    });
+
+    let expanded = quote! {
+        #expanded
+        #handle
+
+        #snapshot_fns
+        #snapshot_helpers
+        #entry_fns
+        #clear_fn
+        #stats_fn
+        #start_fn
+        #with_fn
+    };
+    
+    expanded.into()
+}
+
+/// each! 宏：对指定范围内的每个索引执行闭包
+///
+/// 语法：each!(|i| { statements }, start..end)
+///
+/// # 参数
+/// - `closure`: 处理每个索引的闭包，如 `|i| { show!(data[i]) }`
+/// - `range`: 范围表达式，如 `0..data.len()` 或 `2..6`
+///
+/// # 示例
+/// ```rust
+/// use mau::each;
+///
+/// let data = vec![3, 1, 4, 1, 5, 9];
+/// each!(|i| { println!("{}", data[i]); }, 0..data.len());
+/// // 等价于：
+/// // for i in 0..data.len() {
+/// //     println!("{}", data[i]);
+/// // }
+/// ```
+#[proc_macro]
+pub fn each(input: TokenStream) -> TokenStream {
+    // 解析range语法
+    if let Ok(range_macro) = syn::parse::<RangeMacro>(input.clone()) {
+        let closure = &range_macro.closure;
+        let range = &range_macro.range;
+        
+        // 检查第一个参数是否真的是闭包
+        let is_closure = matches!(closure, syn::Expr::Closure(_));
+        if !is_closure {
+            return syn::Error::new(
+                proc_macro2::Span::call_site(),
+                "each! macro requires a closure as the first argument"
+            )
+            .to_compile_error()
+            .into();
+        }
+        
+        // 检查range是否是范围表达式（如 0..10 或 0..=10）
+        let is_range_expr = matches!(range, syn::Expr::Range(_));
+        
+        let expanded = if is_range_expr {
+            // 处理范围表达式：0..10 或 0..=10
+            if let syn::Expr::Range(range_expr) = range {
+                let start = range_expr.start.as_ref().map(|s| quote! { #s }).unwrap_or(quote! { 0 });
+                let end = range_expr.end.as_ref().map(|e| quote! { #e });
+                
+                if let Some(end_expr) = end {
+                    // 有结束范围的情况：[start..end] 或 [start..=end]
+                    let range_expr = match range_expr.limits {
+                        syn::RangeLimits::HalfOpen(_) => quote! { #start..#end_expr },
+                        syn::RangeLimits::Closed(_) => quote! { #start..=#end_expr },
+                    };
+                    
+                    quote! {{
+                        for __mau_idx in #range_expr {
+                            (#closure)(__mau_idx);
+                        }
+                    }}
+                } else {
+                    // 无结束范围的情况：start..
+                    quote! {{
+                        let mut __mau_idx = #start;
+                        loop {
+                            (#closure)(__mau_idx);
+                            __mau_idx += 1;
+                        }
+                    }}
+                }
+            } else {
+                unreachable!()
+            }
+        } else {
+            // 处理迭代器表达式：任何实现了IntoIterator的类型
+            quote! {{
+                for __mau_item in #range {
+                    (#closure)(__mau_item);
+                }
+            }}
+        };
+        
+        return expanded.into();
+    }
+    
+    // 如果解析失败，返回错误
+    syn::Error::new(
+        proc_macro2::Span::call_site(),
+        "Invalid syntax for each! macro. Use each!(|i| { statements }, range)"
+    )
+    .to_compile_error()
+    .into()
+}
+
+/// solve! 宏：将函数调用替换为 _start 版本
+/// 用法：solve!(func(args))
+#[proc_macro]
+pub fn solve(input: TokenStream) -> TokenStream {
+    use syn::visit_mut::{self, VisitMut};
+    use syn::{Expr, ExprCall};
+    
+    // 解析输入表达式
+    let expr: Expr = match syn::parse(input) {
+        Ok(e) => e,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    
+    // 创建替换 visitor
+    struct StartReplacer;
+    
+    impl VisitMut for StartReplacer {
+        fn visit_expr_call_mut(&mut self, node: &mut ExprCall) {
+            // 递归访问子表达式
+            visit_mut::visit_expr_call_mut(self, node);
+            
+            // 检查是否是函数调用
+            if let Expr::Path(expr_path) = &*node.func {
+                if let Some(ident) = expr_path.path.get_ident() {
+                    // 替换为 _start 版本
+                    let start_name = Ident::new(
+                        &format!("{}_start", ident),
+                        ident.span()
+                    );
+                    let mut new_path = expr_path.clone();
+                    new_path.path.segments.last_mut().unwrap().ident = start_name;
+                    *node.func = Expr::Path(new_path);
+                }
+            }
+        }
+    }
+    
+    let mut visitor = StartReplacer;
+    let mut modified_expr = expr.clone();
+    visitor.visit_expr_mut(&mut modified_expr);
+    
+    quote::quote! {
+        #modified_expr
+    }.into()
+}
+
+
+/// memo_fn! 宏：定义带缓存的递归闭包
+///
+/// 语法：memo_fn!(|rec, 参数: 类型, ..| -> 返回类型 { 函数体 })
+///
+/// 函数体中通过 `rec(..)` 递归调用自己。闭包按引用捕获环境中的变量（写成 `move |rec, ..|` 时按值捕获），
+/// 缓存属于返回的闭包本身，随闭包一起释放。
+///
+/// 示例：
+/// ```rust
+/// use mau::memo_fn;
+///
+/// let grid = vec![vec![1, 3, 1], vec![1, 5, 1], vec![4, 2, 1]];
+/// let min_path = memo_fn!(|rec, i: usize, j: usize| -> u64 {
+///     let here = grid[i][j];
+///     match (i, j) {
+///         (0, 0) => here,
+///         (0, _) => here + rec(0, j - 1),
+///         (_, 0) => here + rec(i - 1, 0),
+///         _ => here + rec(i - 1, j).min(rec(i, j - 1)),
+///     }
+/// });
+/// assert_eq!(min_path(2, 2), 7);
+/// ```
+#[proc_macro]
+pub fn memo_fn(input: TokenStream) -> TokenStream {
+    let closure = parse_macro_input!(input as syn::ExprClosure);
+    let syn::ExprClosure { capture, inputs, output, body, .. } = &closure;
+
+    if closure.asyncness.is_some() || closure.constness.is_some() || closure.lifetimes.is_some() {
+        return syn::Error::new(closure.span(), "memo_fn! 只支持普通闭包 |rec, 参数: 类型, ..| -> 返回类型 { .. }")
+            .to_compile_error()
+            .into();
+    }
+    let ReturnType::Type(_, value_type) = output else {
+        return syn::Error::new(closure.or1_token.span(), "memo_fn! 需要写出返回类型：|rec, ..| -> T { .. }")
+            .to_compile_error()
+            .into();
+    };
+
+    // 第一个参数是递归调用自己的名字，其余参数都需要写出类型，全部参与键
+    let mut params = inputs.iter();
+    let rec = match params.next() {
+        Some(Pat::Ident(PatIdent { ident, by_ref: None, mutability: None, subpat: None, .. })) => quote! { #ident },
+        Some(Pat::Wild(wild)) => quote! { #wild },
+        other => {
+            return syn::Error::new(other.span(), "memo_fn! 的第一个参数是递归调用的名字，如 |rec, n: u64|")
+                .to_compile_error()
+                .into();
+        }
+    };
+    let mut pats = Vec::new();
+    let mut types = Vec::new();
+    for param in params {
+        let Pat::Type(pat_type) = param else {
+            return syn::Error::new(param.span(), "memo_fn! 的参数需要写出类型，如 n: u64")
+                .to_compile_error()
+                .into();
+        };
+        pats.push(&pat_type.pat);
+        types.push(&pat_type.ty);
+    }
+    if pats.is_empty() {
+        return syn::Error::new(closure.span(), "memo_fn! 至少需要一个参数（不含 rec）")
+            .to_compile_error()
+            .into();
+    }
+
+    // 单个参数直接作为键，多个参数组成元组
+    let (key_type, key_pat) = if types.len() == 1 {
+        (quote! { #(#types)* }, quote! { #(#pats)* })
+    } else {
+        (quote! { (#(#types),*) }, quote! { (#(#pats),*) })
+    };
+    let fresh: Vec<_> = (0..types.len())
+        .map(|i| Ident::new(&format!("__mau_arg_{}", i), proc_macro2::Span::call_site()))
+        .collect();
+    let fresh_key = if fresh.len() == 1 { quote! { #(#fresh)* } } else { quote! { (#(#fresh),*) } };
+
+    // 递归通过 &dyn Fn(K) -> V 传给函数体：闭包不能直接调用自己
+    // __mau_memo_call 只依赖泛型参数 K / V / F，在泛型函数中使用时也不需要外层的类型参数
+    quote! {{
+        fn __mau_memo_call<K, V, F>(cache: &::std::cell::RefCell<::std::collections::HashMap<K, V>>, body: &F, key: K) -> V
+        where
+            K: ::std::hash::Hash + Eq + Clone,
+            V: Clone,
+            F: Fn(&dyn Fn(K) -> V, K) -> V,
+        {
+            if let Some(value) = cache.borrow().get(&key) {
+                return value.clone();
+            }
+            let value = body(&|key| __mau_memo_call(cache, body, key), key.clone());
+            cache.borrow_mut().insert(key, value.clone());
+            value
+        }
+
+        let __mau_cache = ::std::cell::RefCell::new(::std::collections::HashMap::<#key_type, #value_type>::new());
+        let __mau_body = #capture |__mau_rec: &dyn Fn(#key_type) -> #value_type, #key_pat: #key_type| -> #value_type {
+            #[allow(unused_variables)]
+            let #rec = |#(#fresh: #types),*| -> #value_type { __mau_rec(#fresh_key) };
+            #body
+        };
+        move |#(#fresh: #types),*| -> #value_type { __mau_memo_call(&__mau_cache, &__mau_body, #fresh_key) }
+    }}
+    .into()
+}
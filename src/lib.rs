@@ -1,1825 +1,128 @@
 //! # Mau - 宏工具库
-//! 
+//!
 //! 提供各种实用的过程宏，包括范围操作、记忆化缓存等。
-
-use proc_macro::TokenStream;
-use quote::quote;
-use syn::{
-    Expr, Token, Type, Ident, Pat, PatIdent, FnArg,
-    parse::Parse, parse::ParseStream,
-};
-use std::collections::HashSet;
-
-// 辅助函数：将下划线命名转为大驼峰命名
-fn to_upper_camel_case(s: &str) -> String {
-    s.split('_')
-        .map(|word| {
-            let mut chars = word.chars();
-            match chars.next() {
-                None => String::new(),
-                Some(first) => first.to_uppercase().chain(chars).collect(),
-            }
-        })
-        .collect()
-}
-
-// ref 模式专用：生成自定义键类型的代码
-// RefKey<T>: 统一处理所有 &T 类型
-//   - addr: 地址，用于快速比较
-//   - len: 长度，区分相同地址不同长度的切片（关键！）
-//   - content: T 的克隆，用于慢速比较（不同地址但内容相同）
-// 为每个函数生成唯一的 RefKey 类型，避免冲突
-fn generate_ref_key_struct(fn_name: &Ident) -> proc_macro2::TokenStream {
-    let ref_key_name = Ident::new(
-        &format!("RefKey{}", to_upper_camel_case(&fn_name.to_string())),
-        fn_name.span()
-    );
-    quote! {
-        #[derive(Clone)]
-        struct #ref_key_name<T> {
-            addr: usize,
-            len: usize,
-            content: T,
-        }
-
-        impl<T: PartialEq> PartialEq for #ref_key_name<T> {
-            fn eq(&self, other: &Self) -> bool {
-                // 先比地址和长度（快速路径）
-                // 对于切片，必须同时比较地址和长度！
-                if self.addr == other.addr && self.len == other.len {
-                    return true;
-                }
-                // 地址或长度不同，比较内容（慢速路径）
-                self.content == other.content
-            }
-        }
-
-        impl<T: Eq> Eq for #ref_key_name<T> {}
-
-        impl<T: std::hash::Hash> std::hash::Hash for #ref_key_name<T> {
-            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-                // 只基于内容 hash，内容相同进入同一个 HashMap 桶
-                self.content.hash(state);
-            }
-        }
-    }
-}
-
-// 辅助函数：生成空迭代器时返回边界值的代码
-fn generate_empty_handler(operation: &str) -> proc_macro2::TokenStream {
-    match operation {
-        "min" => quote! {
-            match result {
-                Some(v) => v,
-                None => {
-                    // 只有空迭代器才会执行到这里
-                    // 运行时类型检查，返回对应类型的 MAX 值
-                    use ::std::any::TypeId;
-                    
-                    fn get_max_value<T: 'static + Copy>() -> T {
-                        let tid = TypeId::of::<T>();
-                        
-                        macro_rules! try_return {
-                            ($t:ty) => {
-                                if tid == TypeId::of::<$t>() {
-                                    let val = <$t>::MAX;
-                                    return unsafe { ::std::mem::transmute_copy(&val) };
-                                }
-                            };
-                        }
-                        
-                        try_return!(i8);
-                        try_return!(i16);
-                        try_return!(i32);
-                        try_return!(i64);
-                        try_return!(i128);
-                        try_return!(isize);
-                        try_return!(u8);
-                        try_return!(u16);
-                        try_return!(u32);
-                        try_return!(u64);
-                        try_return!(u128);
-                        try_return!(usize);
-                        try_return!(f32);
-                        try_return!(f64);
-                        
-                        if tid == TypeId::of::<char>() {
-                            let val = '\u{10FFFF}';
-                            return unsafe { ::std::mem::transmute_copy(&val) };
-                        }
-                        
-                        panic!("min! macro: type does not have a MAX value, empty iterator not supported")
-                    }
-                    
-                    get_max_value()
-                }
-            }
-        },
-        "max" => quote! {
-            match result {
-                Some(v) => v,
-                None => {
-                    // 只有空迭代器才会执行到这里
-                    // 运行时类型检查，返回对应类型的 MIN 值
-                    use ::std::any::TypeId;
-                    
-                    fn get_min_value<T: 'static + Copy>() -> T {
-                        let tid = TypeId::of::<T>();
-                        
-                        macro_rules! try_return {
-                            ($t:ty) => {
-                                if tid == TypeId::of::<$t>() {
-                                    let val = <$t>::MIN;
-                                    return unsafe { ::std::mem::transmute_copy(&val) };
-                                }
-                            };
-                        }
-                        
-                        try_return!(i8);
-                        try_return!(i16);
-                        try_return!(i32);
-                        try_return!(i64);
-                        try_return!(i128);
-                        try_return!(isize);
-                        try_return!(u8);
-                        try_return!(u16);
-                        try_return!(u32);
-                        try_return!(u64);
-                        try_return!(u128);
-                        try_return!(usize);
-                        try_return!(f32);
-                        try_return!(f64);
-                        
-                        if tid == TypeId::of::<char>() {
-                            let val = '\0';
-                            return unsafe { ::std::mem::transmute_copy(&val) };
-                        }
-                        
-                        panic!("max! macro: type does not have a MIN value, empty iterator not supported")
-                    }
-                    
-                    get_min_value()
-                }
-            }
-        },
-        "sum" => quote! {
-            match result {
-                Some(v) => v,
-                None => {
-                    // 只有空迭代器才会执行到这里
-                    // 运行时类型检查，返回对应类型的零值
-                    use ::std::any::TypeId;
-                    
-                    fn get_zero_value<T: 'static + Copy>() -> T {
-                        let tid = TypeId::of::<T>();
-                        
-                        // 对于所有数值类型，零值都是 0
-                        macro_rules! try_return_zero {
-                            ($t:ty) => {
-                                if tid == TypeId::of::<$t>() {
-                                    let val: $t = 0 as $t;
-                                    return unsafe { ::std::mem::transmute_copy(&val) };
-                                }
-                            };
-                        }
-                        
-                        try_return_zero!(i8);
-                        try_return_zero!(i16);
-                        try_return_zero!(i32);
-                        try_return_zero!(i64);
-                        try_return_zero!(i128);
-                        try_return_zero!(isize);
-                        try_return_zero!(u8);
-                        try_return_zero!(u16);
-                        try_return_zero!(u32);
-                        try_return_zero!(u64);
-                        try_return_zero!(u128);
-                        try_return_zero!(usize);
-                        try_return_zero!(f32);
-                        try_return_zero!(f64);
-                        
-                        panic!("sum! macro: type does not support zero value, empty iterator not supported")
-                    }
-                    
-                    get_zero_value()
-                }
-            }
-        },
-        _ => quote! {
-            result.expect("Iterator cannot be empty")
-        }
-    }
-}
-
-// 辅助函数：生成 ptr 模式的键（地址 + 长度）
-fn generate_ptr_mode_key(
-    arg: &Ident,
-    ty: &Type,
-    key_types: &mut Vec<proc_macro2::TokenStream>,
-    key_exprs: &mut Vec<proc_macro2::TokenStream>,
-) {
-    if let Type::Reference(ty_ref) = ty {
-        if let Type::Slice(_slice_ty) = &*ty_ref.elem {
-            // 对于切片 &[T]，使用 (地址, 长度) 作为键
-            key_types.push(quote! { (usize, usize) });
-            key_exprs.push(quote! { (#arg.as_ptr() as usize, #arg.len()) });
-        } else if let Type::Array(_array_ty) = &*ty_ref.elem {
-            // 对于数组 &[T; N]，使用 (地址, 长度) 作为键
-            key_types.push(quote! { (usize, usize) });
-            key_exprs.push(quote! { (#arg.as_ptr() as usize, #arg.len()) });
-        } else {
-            // 对于其他引用类型（&f64, &String 等），使用 (地址, 长度=1)
-            key_types.push(quote! { (usize, usize) });
-            key_exprs.push(quote! { (#arg as *const _ as usize, 1) });
-        }
-    } else {
-        // 对于非引用类型，直接使用
-        key_types.push(quote! { #ty });
-        key_exprs.push(quote! { #arg });
-    }
-}
-
-// 辅助函数：生成 ref 模式的键（使用 RefKey 包装类型）
-// RefKey<T> 实现了自定义的 Eq 和 Hash：
-//   - Hash: 只基于内容（内容相同进入同一个 HashMap 桶）
-//   - Eq: 先比地址（快），地址相同直接返回 true；地址不同再比内容（慢）
-// 
-// 统一处理逻辑：
-//   - 对于 &[T] 或 &[T; N]：使用 RefKey<Vec<T>>（因为 [T] 不是 Sized）
-//   - 对于 &T（其他）：使用 RefKey<T>
-//   - 对于 T（非引用）：直接使用 T
-fn generate_normal_mode_key(
-    fn_name: &Ident,
-    arg: &Ident,
-    ty: &Type,
-    key_types: &mut Vec<proc_macro2::TokenStream>,
-    key_exprs: &mut Vec<proc_macro2::TokenStream>,
-) {
-    if let Type::Reference(ty_ref) = ty {
-        let ref_key_name = Ident::new(
-            &format!("RefKey{}", to_upper_camel_case(&fn_name.to_string())),
-            fn_name.span()
-        );
-        let inner_ty = &ty_ref.elem;
-        
-        // 检查内部类型并生成对应的 RefKey
-        match &**inner_ty {
-            Type::Slice(slice_ty) => {
-                // 对于 &[T]，检查元素类型
-                let elem_ty = &*slice_ty.elem;  // 解引用 Box
-                
-                // 特殊处理 f64
-            if let Type::Path(type_path) = elem_ty {
-                if let Some(ident) = type_path.path.get_ident() {
-                    if ident == "f64" {
-                            key_types.push(quote! { #ref_key_name<Vec<u64>> });
-                            key_exprs.push(quote! {
-                                #ref_key_name {
-                                    addr: #arg.as_ptr() as usize,
-                                    len: #arg.len(),
-                                    content: #arg.iter().map(|x| x.to_bits()).collect(),
-                                }
-                            });
-                            return;
-                        }
-                    }
-                }
-                
-                // 特殊处理 &[[f64; N]] 类型
-                if let Type::Array(inner_array) = elem_ty {
-                    if let Type::Path(type_path) = &*inner_array.elem {
-                    if let Some(ident) = type_path.path.get_ident() {
-                        if ident == "f64" {
-                                key_types.push(quote! { #ref_key_name<Vec<Vec<u64>>> });
-                                key_exprs.push(quote! {
-                                    #ref_key_name {
-                                        addr: #arg.as_ptr() as usize,
-                                    len: #arg.len(),
-                                        content: #arg.iter().map(|row| row.iter().map(|x| x.to_bits()).collect()).collect(),
-                                    }
-                                });
-                                return;
-                            }
-                        }
-                    }
-                }
-                
-                // 其他类型
-                key_types.push(quote! { #ref_key_name<Vec<#elem_ty>> });
-                key_exprs.push(quote! {
-                    #ref_key_name {
-                        addr: #arg.as_ptr() as usize,
-                                    len: #arg.len(),
-                        content: #arg.to_vec(),
-                    }
-                });
-            }
-            Type::Array(array_ty) => {
-                // 对于 &[T; N]，检查元素类型
-                let elem_ty = &*array_ty.elem;  // 解引用 Box
-                
-                // 特殊处理 f64
-            if let Type::Path(type_path) = elem_ty {
-                if let Some(ident) = type_path.path.get_ident() {
-                    if ident == "f64" {
-                            key_types.push(quote! { #ref_key_name<Vec<u64>> });
-                            key_exprs.push(quote! {
-                                #ref_key_name {
-                                    addr: #arg.as_ptr() as usize,
-                                    len: #arg.len(),
-                                    content: #arg.iter().map(|x| x.to_bits()).collect(),
-                                }
-                            });
-                            return;
-                        }
-                    }
-                }
-                
-                // 特殊处理 &[[f64; M]; N] 类型（嵌套数组）
-                if let Type::Array(inner_array) = elem_ty {
-                    if let Type::Path(type_path) = &*inner_array.elem {
-                if let Some(ident) = type_path.path.get_ident() {
-                    if ident == "f64" {
-                                key_types.push(quote! { #ref_key_name<Vec<Vec<u64>>> });
-                                key_exprs.push(quote! {
-                                    #ref_key_name {
-                                        addr: #arg.as_ptr() as usize,
-                                    len: #arg.len(),
-                                        content: #arg.iter().map(|row| row.iter().map(|x| x.to_bits()).collect()).collect(),
-                                    }
-                                });
-                                return;
-                            }
-                        }
-                    }
-                }
-                
-                // 其他类型
-                key_types.push(quote! { #ref_key_name<Vec<#elem_ty>> });
-                key_exprs.push(quote! {
-                    #ref_key_name {
-                        addr: #arg.as_ptr() as usize,
-                                    len: #arg.len(),
-                        content: #arg.to_vec(),
-                    }
-                });
-            }
-            _ => {
-                // 对于其他类型（如 &i32, &String），使用 RefKey<T>
-                
-                // 特殊处理 f64
-                if let Type::Path(type_path) = &**inner_ty {
-                    if let Some(ident) = type_path.path.get_ident() {
-                        if ident == "f64" {
-                            key_types.push(quote! { #ref_key_name<u64> });
-                            key_exprs.push(quote! {
-                                #ref_key_name {
-                                    addr: #arg as *const _ as usize,
-                        len: 1,
-                                    content: #arg.to_bits(),
-                                }
-                            });
-                            return;
-                        }
-                    }
-                }
-                
-                // 其他类型
-                key_types.push(quote! { #ref_key_name<#inner_ty> });
-                key_exprs.push(quote! {
-                    #ref_key_name {
-                        addr: #arg as *const _ as usize,
-                        len: 1,
-                        content: (*#arg).clone(),
-                    }
-                });
-            }
-        }
-    } else {
-        // 对于非引用类型，直接使用
-        key_types.push(quote! { #ty });
-        key_exprs.push(quote! { #arg });
-    }
-}
-
-// 辅助函数：生成 heavy 模式的键
-fn generate_heavy_mode_key(
-    arg: &Ident,
-    ty: &Type,
-    key_types: &mut Vec<proc_macro2::TokenStream>,
-    key_exprs: &mut Vec<proc_macro2::TokenStream>,
-) {
-    if let Type::Reference(ty_ref) = ty {
-        if let Type::Slice(slice_ty) = &*ty_ref.elem {
-            // 对于 &[T] 类型，完全还原为 [T]
-            let elem_ty = &*slice_ty.elem;
-            if let Type::Path(type_path) = elem_ty {
-                if let Some(ident) = type_path.path.get_ident() {
-                    if ident == "f64" {
-                        // 对于 &[f64] 类型，使用 Vec<u64> 作为键
-                        key_types.push(quote! { Vec<u64> });
-                        key_exprs.push(quote! { #arg.iter().map(|x| x.to_bits()).collect() });
-                    } else {
-                        // 对于其他切片类型，使用 Vec<T> 作为键
-                        key_types.push(quote! { Vec<#elem_ty> });
-                        key_exprs.push(quote! { #arg.to_vec() });
-                    }
-                } else {
-                    key_types.push(quote! { Vec<#elem_ty> });
-                    key_exprs.push(quote! { #arg.to_vec() });
-                }
-            } else if let Type::Array(array_ty) = elem_ty {
-                // 对于 &[[T; N]] 类型，完全还原为 [[T; N]]
-                let inner_elem_ty = &*array_ty.elem;
-                if let Type::Path(type_path) = inner_elem_ty {
-                    if let Some(ident) = type_path.path.get_ident() {
-                        if ident == "f64" {
-                            // 对于 &[[f64; N]] 类型，使用 Vec<Vec<u64>> 作为键
-                            key_types.push(quote! { Vec<Vec<u64>> });
-                            key_exprs.push(quote! { #arg.iter().map(|row| row.iter().map(|x| x.to_bits()).collect()).collect() });
-                        } else {
-                            // 对于其他嵌套数组类型，使用 Vec<Vec<T>> 作为键
-                            key_types.push(quote! { Vec<Vec<#inner_elem_ty>> });
-                            key_exprs.push(quote! { #arg.iter().map(|row| row.to_vec()).collect() });
-                        }
-                    } else {
-                        key_types.push(quote! { Vec<Vec<#inner_elem_ty>> });
-                        key_exprs.push(quote! { #arg.iter().map(|row| row.to_vec()).collect() });
-                    }
-                } else {
-                    key_types.push(quote! { Vec<Vec<#inner_elem_ty>> });
-                    key_exprs.push(quote! { #arg.iter().map(|row| row.to_vec()).collect() });
-                }
-            } else {
-                key_types.push(quote! { Vec<#elem_ty> });
-                key_exprs.push(quote! { #arg.to_vec() });
-            }
-        } else if let Type::Array(array_ty) = &*ty_ref.elem {
-            // 对于 &[T; N] 类型，完全还原为 [T; N]
-            let elem_ty = &*array_ty.elem;
-            if let Type::Path(type_path) = elem_ty {
-                if let Some(ident) = type_path.path.get_ident() {
-                    if ident == "f64" {
-                        // 对于 &[f64; N] 类型，使用 Vec<u64> 作为键
-                        key_types.push(quote! { Vec<u64> });
-                        key_exprs.push(quote! { #arg.iter().map(|x| x.to_bits()).collect() });
-                    } else {
-                        // 对于其他数组类型，使用 Vec<T> 作为键
-                        key_types.push(quote! { Vec<#elem_ty> });
-                        key_exprs.push(quote! { #arg.to_vec() });
-                    }
-                } else {
-                    key_types.push(quote! { Vec<#elem_ty> });
-                    key_exprs.push(quote! { #arg.to_vec() });
-                }
-            } else {
-                key_types.push(quote! { Vec<#elem_ty> });
-                key_exprs.push(quote! { #arg.to_vec() });
-            }
-        } else {
-            // 对于其他引用类型，完全还原
-            let elem_ty = &ty_ref.elem;
-            if let Type::Path(type_path) = &**elem_ty {
-                if let Some(ident) = type_path.path.get_ident() {
-                    if ident == "f64" {
-                        // 对于 f64 类型，使用 u64 作为键（通过位模式转换）
-                        key_types.push(quote! { u64 });
-                        key_exprs.push(quote! { #arg.to_bits() });
-                    } else {
-                        // 对于其他类型，直接克隆
-                        key_types.push(quote! { #elem_ty });
-                        key_exprs.push(quote! { #arg.clone() });
-                    }
-                } else {
-                    key_types.push(quote! { #elem_ty });
-                    key_exprs.push(quote! { #arg.clone() });
-                }
-            } else {
-                key_types.push(quote! { #elem_ty });
-                key_exprs.push(quote! { #arg.clone() });
-            }
-        }
-    } else {
-        // 对于非引用类型，直接使用
-        key_types.push(quote! { #ty });
-        key_exprs.push(quote! { #arg });
-    }
-}
-
-// 范围宏的解析结构
-struct RangeMacro {
-    closure: Expr,
-    range: Expr,  // 改为Expr，支持任何可迭代的表达式
-}
-
-impl Parse for RangeMacro {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        // 解析闭包 |i| d[i]
-        let closure = input.parse::<Expr>()?;
-        
-        // 解析逗号
-        input.parse::<Token![,]>()?;
-        
-        // 直接解析迭代器表达式，不再支持中括号
-        let range = input.parse::<Expr>()?;
-        Ok(RangeMacro { closure, range })
-    }
-}
-
-// 多参数宏的解析结构
-struct MultiArgsMacro {
-    args: Vec<Expr>,
-}
-
-impl Parse for MultiArgsMacro {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let mut args = Vec::new();
-        
-        // 解析所有参数
-        while !input.is_empty() {
-            let arg = input.parse::<Expr>()?;
-            args.push(arg);
-            
-            if input.peek(Token![,]) {
-                input.parse::<Token![,]>()?;
-            } else {
-                break;
-            }
-        }
-        
-        Ok(MultiArgsMacro { args })
-    }
-}
-
-// 通用宏生成器
-fn generate_macro_from_reduce(
-    input: TokenStream,
-    reduce_operation: &str,
-    reduce_closure: proc_macro2::TokenStream,
-) -> TokenStream {
-    // 检查是否是短路操作
-    let is_short_circuit = reduce_operation == "and" || reduce_operation == "or";
-    
-    // 生成空迭代器处理代码
-    let empty_handler = generate_empty_handler(reduce_operation);
-    
-    // 首先尝试解析为范围语法
-    if let Ok(range_macro) = syn::parse::<RangeMacro>(input.clone()) {
-        // 范围语法：macro!(|i| d[i], [iterator]) 或 macro!(|i| d[i], [start..end])
-        let closure = &range_macro.closure;
-        let range = &range_macro.range;
-        
-        // 检查第一个参数是否真的是闭包，如果不是，则不应该走范围语法路径
-        let is_closure = matches!(closure, syn::Expr::Closure(_));
-        if !is_closure {
-            // 第一个参数不是闭包，尝试多参数语法
-            // 这里不return，让代码继续往下走到多参数解析
-        } else {
-        
-        // 检查range是否是范围表达式（如 0..10 或 0..=10）
-        let is_range_expr = match range {
-            syn::Expr::Range(_range_expr) => true,
-            _ => false,
-        };
-        
-        let expanded = if is_range_expr {
-            // 处理范围表达式：0..10 或 0..=10
-            if let syn::Expr::Range(range_expr) = range {
-                let start = range_expr.start.as_ref().map(|s| quote! { #s }).unwrap_or(quote! { 0 });
-                let end = range_expr.end.as_ref().map(|e| quote! { #e });
-                
-                if let Some(end_expr) = end {
-                    // 有结束范围的情况：[start..end] 或 [start..=end]
-                    let range_expr = match range_expr.limits {
-                        syn::RangeLimits::HalfOpen(_) => quote! { #start..#end_expr },
-                        syn::RangeLimits::Closed(_) => quote! { #start..=#end_expr },
-                    };
-                    
-                    if is_short_circuit {
-                        // 短路优化版本
-                        if reduce_operation == "and" {
-                            quote! {{
-                                let mut result = true;
-                                for __mau_idx in #range_expr {
-                                    let current_val = (#closure)(__mau_idx);
-                                    if !current_val {
-                                        result = false;
-                                        break;
-                                    }
-                                }
-                                result
-                            }}
-                        } else { // or
-                            quote! {{
-                                let mut result = false;
-                                for __mau_idx in #range_expr {
-                                    let current_val = (#closure)(__mau_idx);
-                                    if current_val {
-                                        result = true;
-                                        break;
-                                    }
-                                }
-                                result
-                            }}
-                        }
-                    } else {
-                        // 普通归约版本
-                        quote! {{
-                            let mut result = None;
-                            
-                            for __mau_idx in #range_expr {
-                                let current_val = (#closure)(__mau_idx);
-                                result = match result {
-                                    None => Some(current_val),
-                                    Some(acc) => Some((#reduce_closure)(acc, current_val)),
-                                };
-                            }
-                            
-                            #empty_handler
-                        }}
-                    }
-                } else {
-                    // 无结束范围的情况：start..
-                    if is_short_circuit {
-                        // 短路优化版本
-                        if reduce_operation == "and" {
-                            quote! {{
-                                let mut result = true;
-                                let mut __mau_idx = #start;
-                                loop {
-                                    let current_val = (#closure)(__mau_idx);
-                                    if !current_val {
-                                        result = false;
-                                        break;
-                                    }
-                                    __mau_idx += 1;
-                                }
-                                result
-                            }}
-                        } else { // or
-                            quote! {{
-                                let mut result = false;
-                                let mut __mau_idx = #start;
-                                loop {
-                                    let current_val = (#closure)(__mau_idx);
-                                    if current_val {
-                                        result = true;
-                                        break;
-                                    }
-                                    __mau_idx += 1;
-                                }
-                                result
-                            }}
-                        }
-                    } else {
-                        // 普通归约版本
-                        quote! {{
-                            let mut result = None;
-                            let mut __mau_idx = #start;
-                            
-                            loop {
-                                let current_val = (#closure)(__mau_idx);
-                                result = match result {
-                                    None => Some(current_val),
-                                    Some(acc) => Some((#reduce_closure)(acc, current_val)),
-                                };
-                                __mau_idx += 1;
-                            }
-                        }}
-                    }
-                }
-            } else {
-                // 这不应该发生，因为我们已经检查了is_range_expr
-                unreachable!()
-            }
-        } else {
-            // 处理迭代器表达式：任何实现了IntoIterator的类型
-            // 直接解引用，让闭包接收值而不是引用
-            if is_short_circuit {
-                // 短路优化版本
-                if reduce_operation == "and" {
-                    quote! {{
-                        let mut result = true;
-                        for __mau_item in #range {
-                            let current_val = (#closure)(*__mau_item);
-                            if !current_val {
-                                result = false;
-                                break;
-                            }
-                        }
-                        result
-                    }}
-                } else { // or
-                    quote! {{
-                        let mut result = false;
-                        for __mau_item in #range {
-                            let current_val = (#closure)(*__mau_item);
-                            if current_val {
-                                result = true;
-                                break;
-                            }
-                        }
-                        result
-                    }}
-                }
-            } else {
-                // 普通归约版本
-                quote! {{
-                    let mut result = None;
-                    
-                    for __mau_item in #range {
-                        let current_val = (#closure)(*__mau_item);
-                        result = match result {
-                            None => Some(current_val),
-                            Some(acc) => Some((#reduce_closure)(acc, current_val)),
-                        };
-                    }
-                    
-                    #empty_handler
-                }}
-            }
-        };
-        
-        return expanded.into();
-        }  // 结束 is_closure 的 else 分支
-    }
-    
-    // 如果范围语法解析失败或第一个参数不是闭包，尝试解析为多参数语法
-    if let Ok(multi_args) = syn::parse::<MultiArgsMacro>(input.clone()) {
-        if multi_args.args.is_empty() {
-            return syn::Error::new(proc_macro2::Span::call_site(), &format!("{}! macro requires at least one argument", reduce_operation)).to_compile_error().into();
-        }
-        
-        if multi_args.args.len() == 1 {
-            // 只有一个参数，检查是否为数组类型
-            let arg = &multi_args.args[0];
-            
-            // 检查参数是否是数组/切片类型
-            let is_array_like = match arg {
-                syn::Expr::Path(_) => true,  // 变量名
-                syn::Expr::Array(_) => true,  // 数组字面量
-                syn::Expr::Call(call) => {
-                    if let syn::Expr::Path(path) = &*call.func {
-                        path.path.is_ident("vec") || path.path.is_ident("Vec")
-                    } else {
-                        false
-                    }
-                }
-                _ => false,
-            };
-            
-            if is_array_like {
-                // 简写语法：macro!(array) -> macro!(|i| array[i], [0..array.len()])
-                // 统一使用range语法处理
-                let closure = quote! { |__mau_idx| #arg[__mau_idx] };
-                let range_expr = quote! { 0..#arg.len() };
-                
-                if is_short_circuit {
-                    // 短路优化版本
-                    if reduce_operation == "and" {
-                        return quote! {{
-                            let mut result = true;
-                            for __mau_idx in #range_expr {
-                                let current_val = (#closure)(__mau_idx);
-                                if !current_val {
-                                    result = false;
-                                    break;
-                                }
-                            }
-                            result
-                        }}.into();
-                    } else { // or
-                        return quote! {{
-                            let mut result = false;
-                            for __mau_idx in #range_expr {
-                                let current_val = (#closure)(__mau_idx);
-                                if current_val {
-                                    result = true;
-                                    break;
-                                }
-                            }
-                            result
-                        }}.into();
-                    }
-                } else {
-                    // 普通归约版本
-                    return quote! {{
-                        let mut result = None;
-                        
-                        for __mau_idx in #range_expr {
-                            let current_val = (#closure)(__mau_idx);
-                            result = match result {
-                                None => Some(current_val),
-                                Some(acc) => Some((#reduce_closure)(acc, current_val)),
-                            };
-                        }
-                        
-                        #empty_handler
-                    }}.into();
-                }
-            } else {
-                // 单个非数组参数，直接返回
-                return quote! { #arg }.into();
-            }
-        } else {
-            // 多个参数：先生成数组，然后使用range语法统一处理
-            let args = &multi_args.args;
-            let array_len = args.len();
-            
-            // 生成数组：let __mau_array = [arg1, arg2, arg3, ...];
-            let array_expr = quote! { [#(#args),*] };
-            let closure = quote! { |__mau_idx| __mau_array[__mau_idx] };
-            let range_expr = quote! { 0..#array_len };
-            
-            if is_short_circuit {
-                // 短路优化版本
-                if reduce_operation == "and" {
-                    return quote! {{
-                        let __mau_array = #array_expr;
-                        let mut result = true;
-                        for __mau_idx in #range_expr {
-                            let current_val = (#closure)(__mau_idx);
-                            if !current_val {
-                                result = false;
-                                break;
-                            }
-                        }
-                        result
-                    }}.into();
-                } else { // or
-                    return quote! {{
-                        let __mau_array = #array_expr;
-                        let mut result = false;
-                        for __mau_idx in #range_expr {
-                            let current_val = (#closure)(__mau_idx);
-                            if current_val {
-                                result = true;
-                                break;
-                            }
-                        }
-                        result
-                    }}.into();
-                }
-            } else {
-                // 普通归约版本
-                return quote! {{
-                    let __mau_array = #array_expr;
-                    let mut result = None;
-                    
-                    for __mau_idx in #range_expr {
-                        let current_val = (#closure)(__mau_idx);
-                        result = match result {
-                            None => Some(current_val),
-                            Some(acc) => Some((#reduce_closure)(acc, current_val)),
-                        };
-                    }
-                    
-                        #empty_handler
-                }}.into();
-            }
-        }
-    }
-    
-    // 如果所有解析都失败，返回错误
-    syn::Error::new(proc_macro2::Span::call_site(), &format!("Invalid syntax for {}! macro. Use either {}!(a, b, c), {}!(array), or {}!(|i| expr, [start..end])", reduce_operation, reduce_operation, reduce_operation, reduce_operation)).to_compile_error().into()
-}
-
-/// min! 宏：在指定范围内找到最小值
-/// 
-/// 语法：min!(|i| d[i], [start..end])
-/// 
-/// 示例：
-/// ```rust
-/// use mau::min;
+//!
+//! 过程宏在 `mau-macros` 中实现，这里重新导出。过程宏 crate 只能导出宏，
+//! `#[memo(handle)]` 生成的句柄使用的 [`Memo`] trait、[`MemoStats`] 和 [`MemoConfig`] 因此定义在这里；
+//! 其余生成的代码不引用本 crate 的路径。
+
+pub use mau_macros::*;
+
+/// `#[memo]` 函数的缓存统计信息，字段与 `{函数名大驼峰}Stats` 相同
+///
+/// 各函数的 `{函数名大驼峰}Stats` 是不同的类型；[`Memo::stats`] 返回这个共同的类型，`&dyn Memo` 才能取得统计信息
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoStats {
+    /// 缓存命中次数
+    pub hits: u64,
+    /// 缓存未命中次数
+    pub misses: u64,
+    /// 写入缓存的次数
+    pub inserts: u64,
+    /// 当前条目数
+    pub len: usize,
+    /// 历史最大条目数
+    pub peak_len: usize,
+    /// `_start` 返回前自动清除缓存的次数
+    pub clears: u64,
+    /// 手动清除缓存的次数（`_clear` 和 [`Memo::clear`]）
+    pub manual_clears: u64,
+}
+
+impl MemoStats {
+    /// 命中率，没有任何查询时返回 0.0
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 { 0.0 } else { self.hits as f64 / total as f64 }
+    }
+}
+
+/// `#[memo]` 函数展开后的配置，取值与属性中的写法相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoConfig {
+    /// 函数名
+    pub name: &'static str,
+    /// `"single"` 或 `"multi"`
+    pub thread: &'static str,
+    /// `"ptr"`、`"ref"` 或 `"val"`
+    pub key: &'static str,
+    /// `"problem"` 或 `"program"`
+    pub lifetime: &'static str,
+    /// 容量上限，`None` 表示不限容量
+    pub capacity: Option<usize>,
+    /// 淘汰策略（`"lru"`、`"lfu"` 或 `"fifo"`），不限容量时为 `None`
+    pub evict: Option<&'static str>,
+    /// 条目过期时间（毫秒），`None` 表示永不过期
+    pub ttl_ms: Option<u64>,
+    /// `thread=multi` 的分片数，`None` 表示不分片
+    pub shards: Option<usize>,
+    /// 是否使用 `dense(...)` / `tabulate(...)` 的稠密表
+    pub dense: bool,
+    /// 哈希函数：`"std"`、`"fx"`、`"ahash_like"` 或 `"identity"`
+    pub hasher: &'static str,
+    /// `call_start` / `_start` 返回前是否清除缓存
+    pub clears_on_start: bool,
+}
+
+/// `#[memo(handle)]` 生成的句柄 `Memo{函数名大驼峰}` 实现的 trait
+///
+/// 句柄是零大小的单元结构体，可以不依赖函数名统一地管理多个函数的缓存：
 ///
-/// let d = vec![3, 1, 4, 1, 5, 9];
-/// let min_val = min!(|i| d[i], 0..d.len());
-/// assert_eq!(min_val, 1);
-/// ```
-#[proc_macro]
-pub fn min(input: TokenStream) -> TokenStream {
-    generate_macro_from_reduce(input, "min", quote! { |a, b| if a < b { a } else { b } })
-}
-
-/// max! 宏：在指定范围内找到最大值
-///
-/// 语法：max!(|i| d[i], [start..end])
-///
-/// 示例：
 /// ```rust
-/// use mau::max;
-///
-/// let d = vec![3, 1, 4, 1, 5, 9];
-/// let max_val = max!(|i| d[i], 0..d.len());
-/// assert_eq!(max_val, 9);
+/// use mau::{memo, Memo};
+///
+/// #[memo(handle)]
+/// fn fib(n: u64) -> u64 {
+///     if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+/// }
+///
+/// #[memo(handle)]
+/// fn paths(i: u32, j: u32) -> u64 {
+///     if i == 0 || j == 0 { 1 } else { paths(i - 1, j) + paths(i, j - 1) }
+/// }
+///
+/// fib(30);
+/// paths(5, 5);
+/// let handles: [&dyn Memo; 2] = [&MemoFib, &MemoPaths];
+/// for handle in handles {
+///     println!("{}: {} 条", handle.config().name, handle.len());
+///     handle.clear();
+/// }
+/// assert_eq!(MemoFib.call_start((10,)), 55);
 /// ```
-#[proc_macro]
-pub fn max(input: TokenStream) -> TokenStream {
-    generate_macro_from_reduce(input, "max", quote! { |a, b| if a > b { a } else { b } })
-}
-
-/// sum! 宏：在指定范围内求和
 ///
-/// 语法：sum!(|i| d[i], [start..end])
-///
-/// 示例：
-/// ```rust
-/// use mau::sum;
-///
-/// let d = vec![3, 1, 4, 1, 5, 9];
-/// let sum_val = sum!(|i| d[i], 0..d.len());
-/// assert_eq!(sum_val, 23);
-/// ```
-#[proc_macro]
-pub fn sum(input: TokenStream) -> TokenStream {
-    generate_macro_from_reduce(input, "sum", quote! { |a, b| a + b })
-}
+/// `Args`、`Output` 和 `call_start` 要求 `Self: Sized`，不影响以 `dyn Memo` 使用其余方法
+pub trait Memo {
+    /// 参数元组，引用参数的生命周期为 `'a`
+    type Args<'a>
+    where
+        Self: Sized;
+    /// 返回值类型
+    type Output
+    where
+        Self: Sized;
 
-/// and! 宏：在指定范围内进行逻辑与运算
-///
-/// 语法：and!(|i| d[i], [start..end])
-///
-/// 示例：
-/// ```rust
-/// use mau::and;
-///
-/// let bools = vec![true, true, false, true];
-/// let and_val = and!(|i| bools[i], 0..bools.len());
-/// assert_eq!(and_val, false);
-/// ```
-#[proc_macro]
-pub fn and(input: TokenStream) -> TokenStream {
-    generate_macro_from_reduce(input, "and", quote! { |a, b| a && b })
-}
+    /// 清除缓存，与 `{函数名}_clear()` 相同
+    fn clear(&self);
 
-/// or! 宏：在指定范围内进行逻辑或运算
-///
-/// 语法：or!(|i| d[i], [start..end])
-///
-/// 示例：
-/// ```rust
-/// use mau::or;
-///
-/// let bools = vec![true, true, false, true];
-/// let or_val = or!(|i| bools[i], 0..bools.len());
-/// assert_eq!(or_val, true);
-/// ```
-#[proc_macro]
-pub fn or(input: TokenStream) -> TokenStream {
-    generate_macro_from_reduce(input, "or", quote! { |a, b| a || b })
-}
+    /// 缓存统计，与 `{函数名}_stats()` 相同
+    fn stats(&self) -> MemoStats;
 
-// Reduce宏的解析结构
-struct ReduceMacro {
-    data_closure: Expr,
-    range: Expr,  // 改为Expr，支持任何可迭代的表达式
-    reduce_closure: Expr,
-}
+    /// `#[memo(...)]` 的配置
+    fn config(&self) -> MemoConfig;
 
-impl Parse for ReduceMacro {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        // 解析数据闭包 |i| data[i]
-        let data_closure = input.parse::<Expr>()?;
-        
-        // 解析逗号
-        input.parse::<Token![,]>()?;
-        
-        // 直接解析迭代器表达式，不再支持中括号
-        let range = input.parse::<Expr>()?;
-        
-        // 解析逗号
-        input.parse::<Token![,]>()?;
-        
-        // 解析归约闭包 |a, b| if a > b { a } else { b }
-        let reduce_closure = input.parse::<Expr>()?;
-        
-        Ok(ReduceMacro { 
-            data_closure,
-            range,
-            reduce_closure,
-        })
+    /// 当前条目数
+    fn len(&self) -> usize {
+        self.stats().len
     }
-}
-
-// Fold宏的解析结构
-struct FoldMacro {
-    init_value: Expr,
-    data_closure: Expr,
-    range: Expr,
-    fold_closure: Expr,
-}
 
-impl Parse for FoldMacro {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        // 解析初始值
-        let init_value = input.parse::<Expr>()?;
-        
-        // 解析逗号
-        input.parse::<Token![,]>()?;
-        
-        // 解析数据闭包 |i| data[i]
-        let data_closure = input.parse::<Expr>()?;
-        
-        // 解析逗号
-        input.parse::<Token![,]>()?;
-        
-        // 解析迭代器表达式
-        let range = input.parse::<Expr>()?;
-        
-        // 解析逗号
-        input.parse::<Token![,]>()?;
-        
-        // 解析折叠闭包 |acc, val| acc + val
-        let fold_closure = input.parse::<Expr>()?;
-        
-        Ok(FoldMacro {
-            init_value,
-            data_closure,
-            range,
-            fold_closure,
-        })
+    /// 缓存是否为空
+    fn is_empty(&self) -> bool {
+        self.len() == 0
     }
-}
 
-/// reduce! 宏：在指定范围内进行归约操作
-///
-/// 语法：reduce!(|i| data[i], [start..end], |a, b| if a > b { a } else { b })
-///
-/// # 参数
-/// - `data_closure`: 数据访问闭包，如 `|i| data[i]`
-/// - `range`: 范围表达式，如 `[0..data.len()]` 或 `[2..6]`
-/// - `reduce_closure`: 归约操作闭包，如 `|a, b| if a > b { a } else { b }`
-///
-/// # 示例
-/// ```rust
-/// use mau::reduce;
-///
-/// let data = vec![3, 1, 4, 1, 5, 9, 2, 6];
-/// let max_val = reduce!(|i| data[i], 0..data.len(), |a, b| if a > b { a } else { b });
-/// assert_eq!(max_val, 9);
-///
-/// // 也可以用于求和
-/// let sum_val = reduce!(|i| data[i], 0..data.len(), |a, b| a + b);
-/// assert_eq!(sum_val, 31);
-///
-/// // 部分范围
-/// let partial_max = reduce!(|i| data[i], 2..6, |a, b| if a > b { a } else { b });
-/// assert_eq!(partial_max, 9); // 只检查索引2到5: [4, 1, 5, 9]
-/// ```
-#[proc_macro]
-pub fn reduce(input: TokenStream) -> TokenStream {
-    // 解析reduce宏语法
-    if let Ok(reduce_macro) = syn::parse::<ReduceMacro>(input) {
-        let data_closure = &reduce_macro.data_closure;
-        let range = &reduce_macro.range;
-        let reduce_closure = &reduce_macro.reduce_closure;
-        
-        // 检查range是否是范围表达式（如 0..10 或 0..=10）
-        let is_range_expr = match range {
-            syn::Expr::Range(_) => true,
-            _ => false,
-        };
-
-        let expanded = if is_range_expr {
-            // 处理范围表达式：0..10 或 0..=10
-            if let syn::Expr::Range(range_expr) = range {
-                let start = range_expr.start.as_ref().map(|s| quote! { #s }).unwrap_or(quote! { 0 });
-                let end = range_expr.end.as_ref().map(|e| quote! { #e });
-
-                if let Some(end_expr) = end {
-                    // 有结束范围的情况：[start..end] 或 [start..=end]
-                    let range_expr = match range_expr.limits {
-                        syn::RangeLimits::HalfOpen(_) => quote! { #start..#end_expr },
-                        syn::RangeLimits::Closed(_) => quote! { #start..=#end_expr },
-                    };
-
-                    quote! {{
-                        let mut result = None;
-
-                        for __mau_idx in #range_expr {
-                            let current_val = (#data_closure)(__mau_idx);
-                            result = match result {
-                                None => Some(current_val),
-                                Some(acc) => Some((#reduce_closure)(acc, current_val)),
-                            };
-                        }
-
-                        result.expect("Range cannot be empty")
-                    }}
-                } else {
-                    // 无结束范围的情况：start..
-                    quote! {{
-                        let mut result = None;
-                        let mut __mau_idx = #start;
-
-                        loop {
-                            let current_val = (#data_closure)(__mau_idx);
-                            result = match result {
-                                None => Some(current_val),
-                                Some(acc) => Some((#reduce_closure)(acc, current_val)),
-                            };
-                            __mau_idx += 1;
-                        }
-                    }}
-                }
-            } else {
-                // 这不应该发生，因为我们已经检查了is_range_expr
-                unreachable!()
-            }
-        } else {
-            // 处理迭代器表达式：任何实现了IntoIterator的类型
-            quote! {{
-                let mut result = None;
-
-                for __mau_item in #range {
-                    let current_val = (#data_closure)(__mau_item);
-                    result = match result {
-                        None => Some(current_val),
-                        Some(acc) => Some((#reduce_closure)(acc, current_val)),
-                    };
-                }
-
-                result.expect("Iterator cannot be empty")
-            }}
-        };
-        
-        return expanded.into();
-    }
-    
-    // 如果解析失败，返回错误
-    syn::Error::new(proc_macro2::Span::call_site(), "Invalid syntax for reduce! macro. Use reduce!(|i| data[i], [start..end], |a, b| operation)").to_compile_error().into()
-}
-
-/// fold! 宏：从初始值开始进行折叠操作
-///
-/// 语法：fold!(init_value, |i| data[i], start..end, |acc, val| acc + val)
-///
-/// # 参数
-/// - `init_value`: 初始累加器值
-/// - `data_closure`: 数据访问闭包，如 `|i| data[i]`
-/// - `range`: 范围表达式，如 `0..data.len()` 或 `2..6`
-/// - `fold_closure`: 折叠操作闭包，如 `|acc, val| acc + val`
-///
-/// # 与 reduce! 的区别
-/// - `fold!` 需要提供初始值，可以处理空迭代器（返回初始值）
-/// - `reduce!` 使用第一个元素作为初始值，空迭代器会 panic
-/// - `fold!` 的累加器类型可以与元素类型不同
-///
-/// # 示例
-/// ```rust
-/// use mau::fold;
-///
-/// let data = vec![1, 2, 3, 4, 5];
-/// 
-/// // 求和，初始值为 0
-/// let sum = fold!(0, |i| data[i], 0..data.len(), |acc, val| acc + val);
-/// assert_eq!(sum, 15);
-///
-/// // 求积，初始值为 1
-/// let product = fold!(1, |i| data[i], 0..data.len(), |acc, val| acc * val);
-/// assert_eq!(product, 120);
-///
-/// // 空范围返回初始值
-/// let empty = fold!(100, |i| data[i], 0..0, |acc, val| acc + val);
-/// assert_eq!(empty, 100);
-///
-/// // 构建字符串
-/// let data = vec!["Hello", "World", "Rust"];
-/// let sentence = fold!(String::new(), |i| data[i], 0..data.len(), |mut acc: String, val| {
-///     if !acc.is_empty() { acc.push(' '); }
-///     acc.push_str(val);
-///     acc
-/// });
-/// assert_eq!(sentence, "Hello World Rust");
-/// ```
-#[proc_macro]
-pub fn fold(input: TokenStream) -> TokenStream {
-    // 解析fold宏语法
-    if let Ok(fold_macro) = syn::parse::<FoldMacro>(input) {
-        let init_value = &fold_macro.init_value;
-        let data_closure = &fold_macro.data_closure;
-        let range = &fold_macro.range;
-        let fold_closure = &fold_macro.fold_closure;
-        
-        // 检查range是否是范围表达式（如 0..10 或 0..=10）
-        let is_range_expr = match range {
-            syn::Expr::Range(_) => true,
-            _ => false,
-        };
-
-        let expanded = if is_range_expr {
-            // 处理范围表达式：0..10 或 0..=10
-            if let syn::Expr::Range(range_expr) = range {
-                let start = range_expr.start.as_ref().map(|s| quote! { #s }).unwrap_or(quote! { 0 });
-                let end = range_expr.end.as_ref().map(|e| quote! { #e });
-
-                if let Some(end_expr) = end {
-                    // 有结束范围的情况：[start..end] 或 [start..=end]
-                    let range_expr = match range_expr.limits {
-                        syn::RangeLimits::HalfOpen(_) => quote! { #start..#end_expr },
-                        syn::RangeLimits::Closed(_) => quote! { #start..=#end_expr },
-                    };
-
-                    quote! {{
-                        let mut acc = #init_value;
-
-                        for __mau_idx in #range_expr {
-                            let current_val = (#data_closure)(__mau_idx);
-                            acc = (#fold_closure)(acc, current_val);
-                        }
-
-                        acc
-                    }}
-                } else {
-                    // 无结束范围的情况：start..（无限循环）
-                    quote! {{
-                        let mut acc = #init_value;
-                        let mut __mau_idx = #start;
-
-                        loop {
-                            let current_val = (#data_closure)(__mau_idx);
-                            acc = (#fold_closure)(acc, current_val);
-                            __mau_idx += 1;
-                        }
-                    }}
-                }
-            } else {
-                // 这不应该发生，因为我们已经检查了is_range_expr
-                unreachable!()
-            }
-        } else {
-            // 处理迭代器表达式：任何实现了IntoIterator的类型
-            quote! {{
-                let mut acc = #init_value;
-
-                for __mau_item in #range {
-                    let current_val = (#data_closure)(__mau_item);
-                    acc = (#fold_closure)(acc, current_val);
-                }
-
-                acc
-            }}
-        };
-        
-        return expanded.into();
-    }
-    
-    // 如果解析失败，返回错误
-    syn::Error::new(proc_macro2::Span::call_site(), "Invalid syntax for fold! macro. Use fold!(init_value, |i| data[i], start..end, |acc, val| operation)").to_compile_error().into()
-}
-
-// 从备份文件中提取memo宏的实现
-use syn::{
-    parse_macro_input, ItemFn, ReturnType,
-    punctuated::Punctuated, spanned::Spanned,
-};
-
-// KeyArgs 结构用于解析 memo 宏的属性参数
-struct KeyArgs {
-    args: Punctuated<Ident, syn::Token![,]>,
-    named_args: std::collections::HashMap<String, String>,
-}
-
-impl syn::parse::Parse for KeyArgs {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let mut args = Punctuated::new();
-        let mut named_args = std::collections::HashMap::new();
-        
-        while !input.is_empty() {
-            // 尝试解析命名参数 key=value
-            if input.peek(Ident) && input.peek2(syn::Token![=]) {
-                let key: Ident = input.parse()?;
-                input.parse::<syn::Token![=]>()?;
-                
-                // 解析值（可能是关键字 ref）
-                let value_str = if input.peek(syn::Token![ref]) {
-                    input.parse::<syn::Token![ref]>()?;
-                    "ref".to_string()
-                } else {
-                    let value: Ident = input.parse()?;
-                    value.to_string()
-                };
-                
-                named_args.insert(key.to_string(), value_str);
-            } else if input.peek(Ident) {
-                // 位置参数（向后兼容）
-                let arg: Ident = input.parse()?;
-                args.push(arg);
-            } else {
-                break;
-            }
-            
-            // 解析逗号
-            if input.peek(syn::Token![,]) {
-                input.parse::<syn::Token![,]>()?;
-            } else {
-                break;
-            }
-        }
-        
-        Ok(KeyArgs { args, named_args })
-    }
+    /// 以参数元组调用函数，与 `{函数名}_start(..)` 相同（按 `lifetime` 决定返回前是否清除缓存）
+    fn call_start<'a>(&self, args: Self::Args<'a>) -> Self::Output
+    where
+        Self: Sized;
 }
-
-// 解析线程模式、键模式和生命周期的辅助函数
-fn parse_memo_modes(key_args: &KeyArgs) -> (String, String, String) {
-    // 首先检查命名参数
-    if !key_args.named_args.is_empty() {
-        // 验证只有 thread、key 和 lifetime 三个参数
-        for (k, _) in &key_args.named_args {
-            if k != "thread" && k != "key" && k != "lifetime" {
-                panic!("无效的参数名 '{}'. 只支持 'thread'、'key' 和 'lifetime'", k);
-            }
-        }
-        
-        let thread_mode = key_args
-            .named_args
-            .get("thread")
-            .map(|s| {
-                // 验证 thread 模式
-                match s.as_str() {
-                    "single" | "multi" => s.clone(),
-                    _ => panic!("无效的 thread 模式 '{}'. 只支持 'single' 或 'multi'", s),
-                }
-            })
-            .unwrap_or_else(|| "single".to_string());
-        
-        let key_mode = key_args
-            .named_args
-            .get("key")
-            .map(|s| {
-                // 验证 key 模式
-                match s.as_str() {
-                    "ptr" | "ref" | "val" => s.clone(),
-                    _ => panic!("无效的 key 模式 '{}'. 只支持 'ptr', 'ref' 或 'val'", s),
-                }
-            })
-            .unwrap_or_else(|| "ptr".to_string());
-        
-        let lifetime_mode = key_args
-            .named_args
-            .get("lifetime")
-            .map(|s| {
-                // 验证 lifetime 模式
-                match s.as_str() {
-                    "program" | "problem" => s.clone(),
-                    _ => panic!("无效的 lifetime 模式 '{}'. 只支持 'program' 或 'problem'", s),
-                }
-            })
-            .unwrap_or_else(|| "problem".to_string());
-        
-        return (thread_mode, key_mode, lifetime_mode);
-    }
-    
-    // 向后兼容：位置参数（映射旧名称到新名称）
-    if key_args.args.is_empty() {
-        return ("single".to_string(), "ptr".to_string(), "problem".to_string());
-    }
-    
-    // 检查第一个参数
-        if let Some(first_arg) = key_args.args.first() {
-            let arg_str = first_arg.to_string();
-        
-        // 映射旧的线程模式名称
-        let thread_mode = match arg_str.as_str() {
-            "local" => "single".to_string(),  // local -> single
-            "single" => {
-                // 旧的 single -> 现在不存在，报错或使用默认
-                eprintln!("警告：旧的 'single' 模式已被移除，请使用 'single'(原local) 或 'multi'");
-                "single".to_string()
-            }
-            "multi" => "multi".to_string(),
-            // 检查是否是键模式
-            "light" => {
-                // 这是键模式，使用默认线程和生命周期
-                return ("single".to_string(), "ptr".to_string(), "problem".to_string());
-            }
-            "normal" => {
-                return ("single".to_string(), "ref".to_string(), "problem".to_string());
-            }
-            "heavy" => {
-                return ("single".to_string(), "val".to_string(), "problem".to_string());
-            }
-            "ptr" | "ref" | "val" => {
-                // 新的键模式名称
-                let key_mode = match arg_str.as_str() {
-                    "ptr" => "ptr".to_string(),
-                    "ref" => "ref".to_string(),
-                    "val" => "val".to_string(),
-                    _ => "ptr".to_string(),
-                };
-                return ("single".to_string(), key_mode, "problem".to_string());
-            }
-            _ => "single".to_string(),
-        };
-        
-        // 检查第二个参数
-        let key_mode = if key_args.args.len() > 1 {
-                    let second_arg = &key_args.args[1];
-                    let second_str = second_arg.to_string();
-            match second_str.as_str() {
-                // 旧名称映射
-                "light" => "ptr".to_string(),
-                "normal" => "ref".to_string(),
-                "heavy" => "val".to_string(),
-                // 新名称
-                "ptr" | "ref" | "val" => second_str,
-                _ => "ptr".to_string(),
-                    }
-                } else {
-            "ptr".to_string()
-        };
-        
-        (thread_mode, key_mode, "problem".to_string())
-            } else {
-        ("single".to_string(), "ptr".to_string(), "problem".to_string())
-    }
-}
-
-/// memo 宏：为函数添加记忆化缓存
-#[proc_macro_attribute]
-pub fn memo(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let input_fn = parse_macro_input!(item as ItemFn);
-    let key_args = parse_macro_input!(attr as KeyArgs);
-    
-    // 解析线程模式、键模式和生命周期模式
-    let (thread_mode, index_mode, lifetime_mode) = parse_memo_modes(&key_args);
-
-    let fn_name = &input_fn.sig.ident;
-    let fn_vis = &input_fn.vis;
-    let fn_block = &input_fn.block;
-    let fn_inputs = &input_fn.sig.inputs;
-    let fn_output = &input_fn.sig.output;
-
-    // 三层函数名（使用双下划线前缀避免与用户代码冲突）
-    let inner_name = Ident::new(&format!("__mau_inner_{}", fn_name), fn_name.span());  // 最内层：原函数体
-    let start_name = Ident::new(&format!("{}_start", fn_name), fn_name.span());  // 最外层：清除缓存
-    // 中间层保持原名 fn_name
-
-    // 哈希表的名字 - 使用更安全的命名避免冲突
-    let cache_name = Ident::new(&format!("{}_CACHE", fn_name.to_string().to_uppercase()), fn_name.span());
-    
-    // 清除缓存函数名
-    let clear_name = Ident::new(&format!("{}_clear", fn_name), fn_name.span());
-
-    // 提取参数和类型
-    let (args, param_types): (Vec<_>, Vec<_>) = input_fn
-        .sig
-        .inputs
-        .iter()
-        .map(|arg| match arg {
-            FnArg::Typed(pat_type) => {
-                let ident = match &*pat_type.pat {
-                    Pat::Ident(PatIdent { ident, .. }) => ident.clone(),
-                    _ => {
-                        return Err(syn::Error::new(
-                            pat_type.span(),
-                            "only simple identifiers are supported",
-                        ))
-                    }
-                };
-                let ty = &*pat_type.ty;
-                Ok((ident, ty))
-            }
-            _ => Err(syn::Error::new(arg.span(), "self parameters are not supported")),
-        })
-        .collect::<Result<Vec<_>, _>>()
-        .unwrap()
-        .into_iter()
-        .unzip();
-
-    // 提取参数中的不可变引用
-    let mut immutable_references = HashSet::<String>::new();
-    for arg in input_fn.sig.inputs.iter() {
-        if let FnArg::Typed(pat_type) = arg {
-            if let Type::Reference(ty_ref) = &*pat_type.ty {
-                if ty_ref.mutability.is_none() {
-                    if let Pat::Ident(pat_ident) = &*pat_type.pat {
-                        immutable_references.insert(pat_ident.ident.to_string());
-                    }
-                } else {
-                    if let Pat::Ident(pat_ident) = &*pat_type.pat {
-                        return syn::Error::new(ty_ref.span(), format!("memo supports only immutable references in parameters, but {} is mutable", pat_ident.ident))
-                            .to_compile_error()
-                            .into();
-                    }
-                }
-            }
-        }
-    }
-
-    if args.is_empty() { return quote! {#fn_vis #fn_name(#fn_inputs) #fn_output #fn_block}.into(); }
-
-    // 根据索引模式处理引用参数
-    let mut key_args = Vec::new();
-    let mut key_types = Vec::new();
-    let mut key_exprs = Vec::new();
-    
-    for (arg, ty) in args.iter().zip(param_types.into_iter()) {
-        key_args.push(arg.clone());
-        
-        if immutable_references.contains(&arg.to_string()) {
-            // 根据键模式处理引用参数
-            match index_mode.as_str() {
-                "ptr" => {
-                    // ptr 模式：使用 (地址, 长度) 作为键（原 light）
-                    generate_ptr_mode_key(&arg, &ty, &mut key_types, &mut key_exprs);
-                },
-                "ref" => {
-                    // ref 模式：解开一层引用（原 normal，默认）
-                    generate_normal_mode_key(fn_name, &arg, &ty, &mut key_types, &mut key_exprs);
-                },
-                "val" => {
-                    // val 模式：完全还原（原 heavy）
-                    generate_heavy_mode_key(&arg, &ty, &mut key_types, &mut key_exprs);
-                },
-                _ => {
-                    // 默认使用 ptr 模式
-                    generate_ptr_mode_key(&arg, &ty, &mut key_types, &mut key_exprs);
-                }
-            }
-        } else {
-            // 对于非引用参数，克隆参数以避免移动
-            key_types.push(quote! { #ty });
-            key_exprs.push(quote! { #arg.clone() });
-        }
-    }
-
-    let key_type = if key_types.len() == 1 {
-        quote! { #(#key_types)* }
-    } else {
-        quote! { (#(#key_types),*) }
-    };
-    
-    let key_tuple = quote! { (#(#key_exprs),*) };
-
-    // 对于函数调用，我们直接使用原始参数名（收集到 Vec 以便多次使用）
-    let call_args: Vec<_> = args.iter().map(|arg| quote! { #arg }).collect();
-
-    let return_type = match fn_output {
-        ReturnType::Default => quote! { () },
-        ReturnType::Type(_, ty) => quote! { #ty },
-    };
-
-    // 先判断是否应该清除缓存
-    let has_ref_params = !immutable_references.is_empty();
-    let key_contains_address = has_ref_params && (index_mode == "ptr" || index_mode == "ref");
-    
-    let (create_cache, clear_impl, cache_impl) = if thread_mode == "multi" {
-        // Multi 模式：使用 Mutex<HashMap>，支持多线程
-        let create_cache = quote! {
-            static #cache_name: ::std::sync::LazyLock<::std::sync::Mutex<::std::collections::HashMap<#key_type, #return_type>>> = ::std::sync::LazyLock::new(|| {
-                ::std::sync::Mutex::new(::std::collections::HashMap::new())
-            });
-        };
-        
-        let clear_impl = quote! {
-            #cache_name.lock().unwrap().clear();
-        };
-        
-        let cache_impl = quote! {
-            let cache_key = #key_tuple;
-            // 检查缓存
-            {   
-                let cache = #cache_name.lock().unwrap();
-                if let Some(result) = cache.get(&cache_key) {
-                    return result.clone();
-                }
-            }
-            // 计算并缓存结果
-            let result = #inner_name(#(#call_args),*);
-            let mut cache = #cache_name.lock().unwrap();
-            cache.insert(cache_key, result.clone());
-            result
-        };
-        
-        (create_cache, clear_impl, cache_impl)
-    } else {
-        // Single 模式（默认）：使用 thread_local!，真正的单线程，无锁
-        let create_cache = quote! {
-            ::std::thread_local! {
-                static #cache_name: ::std::cell::RefCell<::std::collections::HashMap<#key_type, #return_type>> = ::std::cell::RefCell::new(::std::collections::HashMap::new());
-            }
-        };
-        
-        let clear_impl = quote! {
-            #cache_name.with(|cache| cache.borrow_mut().clear());
-        };
-        
-        let cache_impl = quote! {
-            let cache_key = #key_tuple;
-            // 检查缓存
-            #cache_name.with(|cache| {
-                // 先检查缓存
-                if let Some(result) = cache.borrow().get(&cache_key) {
-                    return result.clone();
-                }
-                // 计算并缓存结果
-                let result = #inner_name(#(#call_args),*);
-                cache.borrow_mut().insert(cache_key, result.clone());
-                result
-            })
-        };
-        
-        (create_cache, clear_impl, cache_impl)
-    };
-
-    // 检查是否需要生成 RefKey 结构体
-    // 只要 key_type 中包含 "RefKey"，就需要生成结构体定义
-    let key_type_str = key_type.to_string();
-    let ref_key_struct = if key_type_str.contains("RefKey") {
-        generate_ref_key_struct(fn_name)
-    } else {
-        quote! {}
-    };
-
-    // 根据 lifetime 模式和 key 模式生成 _start 函数的实现
-    // 缓存清除策略：
-    // - 如果使用 ptr 或 ref 模式：总是清除缓存（无论 program 还是 problem）
-    // - 如果使用 val 模式或没有引用参数：
-    //   * problem 模式：清除缓存
-    //   * program 模式：保留缓存（相同输入可以跨问题复用）
-    let should_clear_in_start = key_contains_address || lifetime_mode == "problem";
-    
-    let start_impl = if should_clear_in_start {
-        // ptr/ref 模式或 problem 模式：总是清除缓存
-        quote! {
-            let result = #fn_name(#(#call_args),*);
-            #clear_name();
-            result
-        }
-    } else {
-        // program 模式 + val 模式：保留缓存
-        quote! {
-            #fn_name(#(#call_args),*)
-        }
-    };
-
-    // 生成三层函数结构
-    let expanded = quote! {
-        #ref_key_struct
-        #create_cache
-        
-        // 最内层函数：原函数体
-        fn #inner_name(#fn_inputs) #fn_output #fn_block
-        
-        // 清除缓存函数
-        #fn_vis fn #clear_name() {
-            #clear_impl
-        }
-        
-        // 中间层函数（保持原名）：查缓存 → 调用内层 → 存缓存
-        #fn_vis fn #fn_name(#fn_inputs) #fn_output {
-            #cache_impl
-        }
-        
-        // 最外层函数：根据 lifetime 决定是否清除缓存
-        #fn_vis fn #start_name(#fn_inputs) #fn_output {
-            #start_impl
-        }
-    };
-    
-    expanded.into()
-}
-
-/// each! 宏：对指定范围内的每个索引执行闭包
-///
-/// 语法：each!(|i| { statements }, start..end)
-///
-/// # 参数
-/// - `closure`: 处理每个索引的闭包，如 `|i| { show!(data[i]) }`
-/// - `range`: 范围表达式，如 `0..data.len()` 或 `2..6`
-///
-/// # 示例
-/// ```rust
-/// use mau::each;
-///
-/// let data = vec![3, 1, 4, 1, 5, 9];
-/// each!(|i| { println!("{}", data[i]); }, 0..data.len());
-/// // 等价于：
-/// // for i in 0..data.len() {
-/// //     println!("{}", data[i]);
-/// // }
-/// ```
-#[proc_macro]
-pub fn each(input: TokenStream) -> TokenStream {
-    // 解析range语法
-    if let Ok(range_macro) = syn::parse::<RangeMacro>(input.clone()) {
-        let closure = &range_macro.closure;
-        let range = &range_macro.range;
-        
-        // 检查第一个参数是否真的是闭包
-        let is_closure = matches!(closure, syn::Expr::Closure(_));
-        if !is_closure {
-            return syn::Error::new(
-                proc_macro2::Span::call_site(),
-                "each! macro requires a closure as the first argument"
-            )
-            .to_compile_error()
-            .into();
-        }
-        
-        // 检查range是否是范围表达式（如 0..10 或 0..=10）
-        let is_range_expr = match range {
-            syn::Expr::Range(_range_expr) => true,
-            _ => false,
-        };
-        
-        let expanded = if is_range_expr {
-            // 处理范围表达式：0..10 或 0..=10
-            if let syn::Expr::Range(range_expr) = range {
-                let start = range_expr.start.as_ref().map(|s| quote! { #s }).unwrap_or(quote! { 0 });
-                let end = range_expr.end.as_ref().map(|e| quote! { #e });
-                
-                if let Some(end_expr) = end {
-                    // 有结束范围的情况：[start..end] 或 [start..=end]
-                    let range_expr = match range_expr.limits {
-                        syn::RangeLimits::HalfOpen(_) => quote! { #start..#end_expr },
-                        syn::RangeLimits::Closed(_) => quote! { #start..=#end_expr },
-                    };
-                    
-                    quote! {{
-                        for __mau_idx in #range_expr {
-                            (#closure)(__mau_idx);
-                        }
-                    }}
-                } else {
-                    // 无结束范围的情况：start..
-                    quote! {{
-                        let mut __mau_idx = #start;
-                        loop {
-                            (#closure)(__mau_idx);
-                            __mau_idx += 1;
-                        }
-                    }}
-                }
-            } else {
-                unreachable!()
-            }
-        } else {
-            // 处理迭代器表达式：任何实现了IntoIterator的类型
-            quote! {{
-                for __mau_item in #range {
-                    (#closure)(__mau_item);
-                }
-            }}
-        };
-        
-        return expanded.into();
-    }
-    
-    // 如果解析失败，返回错误
-    syn::Error::new(
-        proc_macro2::Span::call_site(),
-        "Invalid syntax for each! macro. Use each!(|i| { statements }, range)"
-    )
-    .to_compile_error()
-    .into()
-}
-
-/// solve! 宏：将函数调用替换为 _start 版本
-/// 用法：solve!(func(args))
-#[proc_macro]
-pub fn solve(input: TokenStream) -> TokenStream {
-    use syn::visit_mut::{self, VisitMut};
-    use syn::{Expr, ExprCall};
-    
-    // 解析输入表达式
-    let expr: Expr = match syn::parse(input) {
-        Ok(e) => e,
-        Err(e) => return e.to_compile_error().into(),
-    };
-    
-    // 创建替换 visitor
-    struct StartReplacer;
-    
-    impl VisitMut for StartReplacer {
-        fn visit_expr_call_mut(&mut self, node: &mut ExprCall) {
-            // 递归访问子表达式
-            visit_mut::visit_expr_call_mut(self, node);
-            
-            // 检查是否是函数调用
-            if let Expr::Path(expr_path) = &*node.func {
-                if let Some(ident) = expr_path.path.get_ident() {
-                    // 替换为 _start 版本
-                    let start_name = Ident::new(
-                        &format!("{}_start", ident),
-                        ident.span()
-                    );
-                    let mut new_path = expr_path.clone();
-                    new_path.path.segments.last_mut().unwrap().ident = start_name;
-                    node.func = Box::new(Expr::Path(new_path));
-                }
-            }
-        }
-    }
-    
-    let mut visitor = StartReplacer;
-    let mut modified_expr = expr.clone();
-    visitor.visit_expr_mut(&mut modified_expr);
-    
-    quote::quote! {
-        #modified_expr
-    }.into()
-}
-
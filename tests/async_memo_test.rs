@@ -0,0 +1,254 @@
+use mau::memo;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+// ========== 最小的本地执行器 ==========
+
+struct ThreadWaker(std::thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = Box::pin(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::park();
+    }
+}
+
+// 在同一个线程上并发执行多个 future
+fn join_all<F: Future>(futs: Vec<F>) -> Vec<F::Output> {
+    struct JoinAll<F: Future> {
+        futs: Vec<Option<Pin<Box<F>>>>,
+        outputs: Vec<Option<F::Output>>,
+    }
+
+    impl<F: Future> Future for JoinAll<F> {
+        type Output = Vec<F::Output>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let this = unsafe { self.get_unchecked_mut() };
+            for (slot, output) in this.futs.iter_mut().zip(this.outputs.iter_mut()) {
+                if let Some(fut) = slot {
+                    if let Poll::Ready(value) = fut.as_mut().poll(cx) {
+                        *output = Some(value);
+                        *slot = None;
+                    }
+                }
+            }
+            if this.futs.iter().all(Option::is_none) {
+                Poll::Ready(this.outputs.iter_mut().map(|o| o.take().unwrap()).collect())
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    let outputs = futs.iter().map(|_| None).collect();
+    block_on(JoinAll { futs: futs.into_iter().map(|f| Some(Box::pin(f))).collect(), outputs })
+}
+
+// 让出一次执行权
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+fn yield_now() -> YieldNow {
+    YieldNow(false)
+}
+
+// ========== 递归 async 函数 ==========
+
+#[memo]
+async fn fib(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    yield_now().await;
+    Box::pin(fib(n - 1)).await + Box::pin(fib(n - 2)).await
+}
+
+#[test]
+fn test_async_recursive() {
+    assert_eq!(block_on(fib(80)), 23416728348467685);
+    assert_eq!(fib_stats().len, 81);
+    assert_eq!(block_on(fib_start(10)), 55);
+    assert_eq!(fib_stats().len, 0, "_start 之后缓存被清除");
+}
+
+// ========== 同一个键的并发调用共享一次计算 ==========
+
+static SHARED_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo]
+async fn slow_square(n: u64) -> u64 {
+    SHARED_CALLS.fetch_add(1, Ordering::SeqCst);
+    for _ in 0..3 {
+        yield_now().await;
+    }
+    n * n
+}
+
+#[test]
+fn test_async_single_flight() {
+    let results = join_all(vec![slow_square(7), slow_square(7), slow_square(7), slow_square(8)]);
+    assert_eq!(results, vec![49, 49, 49, 64]);
+    assert_eq!(SHARED_CALLS.load(Ordering::SeqCst), 2, "相同的键只计算一次");
+
+    assert_eq!(block_on(slow_square(7)), 49);
+    assert_eq!(SHARED_CALLS.load(Ordering::SeqCst), 2, "之后直接命中缓存");
+}
+
+// ========== leader 被取消时，等待者接手计算 ==========
+
+static CANCEL_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo]
+async fn cancellable(n: u64) -> u64 {
+    CANCEL_CALLS.fetch_add(1, Ordering::SeqCst);
+    yield_now().await;
+    n + 1
+}
+
+#[test]
+fn test_async_leader_cancelled() {
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    let mut leader = Box::pin(cancellable(1));
+    let mut follower = Box::pin(cancellable(1));
+    assert!(leader.as_mut().poll(&mut cx).is_pending());
+    assert!(follower.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(CANCEL_CALLS.load(Ordering::SeqCst), 1);
+
+    drop(leader);
+    assert_eq!(block_on(follower), 2);
+    assert_eq!(CANCEL_CALLS.load(Ordering::SeqCst), 2, "等待者重新计算");
+}
+
+// ========== leader panic 时，之后的调用重新计算 ==========
+
+static PANIC_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo]
+async fn flaky(n: u64) -> u64 {
+    if PANIC_CALLS.fetch_add(1, Ordering::SeqCst) == 0 {
+        panic!("first call fails");
+    }
+    n * 2
+}
+
+#[test]
+fn test_async_leader_panics() {
+    let result = std::panic::catch_unwind(|| block_on(flaky(5)));
+    assert!(result.is_err());
+    assert_eq!(block_on(flaky(5)), 10);
+    assert_eq!(PANIC_CALLS.load(Ordering::SeqCst), 2);
+}
+
+// ========== multi 模式：跨线程共享 ==========
+
+static MULTI_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi)]
+async fn multi_square(n: u64) -> u64 {
+    MULTI_CALLS.fetch_add(1, Ordering::SeqCst);
+    for _ in 0..10 {
+        yield_now().await;
+    }
+    n * n
+}
+
+fn assert_send<T: Send>(value: T) -> T {
+    value
+}
+
+#[test]
+fn test_async_multi_mode() {
+    let handles: Vec<_> = (0..4)
+        .map(|_| std::thread::spawn(|| block_on(assert_send(multi_square(9)))))
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 81);
+    }
+    assert_eq!(MULTI_CALLS.load(Ordering::SeqCst), 1);
+
+    multi_square_clear();
+    assert_eq!(block_on(multi_square_start(9)), 81);
+    assert_eq!(MULTI_CALLS.load(Ordering::SeqCst), 2);
+}
+
+// ========== cache_if：None 不写入缓存 ==========
+
+static OPTIONAL_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(cache_if = some)]
+async fn optional_half(n: u64) -> Option<u64> {
+    OPTIONAL_CALLS.fetch_add(1, Ordering::SeqCst);
+    yield_now().await;
+    n.is_multiple_of(2).then_some(n / 2)
+}
+
+#[test]
+fn test_async_cache_if() {
+    assert_eq!(block_on(optional_half(3)), None);
+    assert_eq!(block_on(optional_half(3)), None);
+    assert_eq!(block_on(optional_half(4)), Some(2));
+    assert_eq!(block_on(optional_half(4)), Some(2));
+    assert_eq!(OPTIONAL_CALLS.load(Ordering::SeqCst), 3);
+}
+
+#[memo(dense(n: 0..=40))]
+async fn dense_fib(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    yield_now().await;
+    Box::pin(dense_fib(n - 1)).await + Box::pin(dense_fib(n - 2)).await
+}
+
+#[test]
+fn test_async_dense() {
+    assert_eq!(block_on(dense_fib(60)), 1548008755920);
+    assert_eq!(dense_fib_stats().len, 61, "超出范围的参数退回哈希表");
+}
+
+#[memo(thread=multi, shards=8)]
+async fn sharded_square(n: u64) -> u64 {
+    yield_now().await;
+    n * n
+}
+
+#[test]
+fn test_async_shards() {
+    let handles: Vec<_> = (0..4)
+        .map(|_| std::thread::spawn(|| (0..32).map(|n| block_on(sharded_square(n))).sum::<u64>()))
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 10416);
+    }
+    assert_eq!(sharded_square_stats().len, 32);
+}
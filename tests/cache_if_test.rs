@@ -0,0 +1,155 @@
+use mau::memo;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== cache_if = ok：Err 不写入缓存 ==========
+
+static FETCH_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(cache_if = ok)]
+fn fetch(id: u32) -> Result<String, String> {
+    // 第一次调用模拟暂时性失败
+    if FETCH_CALLS.fetch_add(1, Ordering::SeqCst) == 0 {
+        Err("timeout".to_string())
+    } else {
+        Ok(format!("user-{}", id))
+    }
+}
+
+#[test]
+fn test_cache_if_ok() {
+    assert_eq!(fetch(1), Err("timeout".to_string()));
+    assert_eq!(fetch(1), Ok("user-1".to_string()), "Err 没有被缓存，重新计算");
+    assert_eq!(fetch(1), Ok("user-1".to_string()));
+    assert_eq!(FETCH_CALLS.load(Ordering::SeqCst), 2, "Ok 被缓存");
+
+    let stats = fetch_stats();
+    assert_eq!(stats.misses, 2);
+    assert_eq!(stats.inserts, 1);
+    assert_eq!(stats.len, 1);
+}
+
+// ========== cache_if = some：None 不写入缓存 ==========
+
+static LOOKUP_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi, cache_if = some)]
+fn lookup(key: i32) -> Option<u32> {
+    LOOKUP_CALLS.fetch_add(1, Ordering::SeqCst);
+    u32::try_from(key).ok()
+}
+
+#[test]
+fn test_cache_if_some() {
+    assert_eq!(lookup(-1), None);
+    assert_eq!(lookup(-1), None);
+    assert_eq!(LOOKUP_CALLS.load(Ordering::SeqCst), 2, "None 每次都重新计算");
+
+    assert_eq!(lookup(3), Some(3));
+    assert_eq!(lookup(3), Some(3));
+    assert_eq!(LOOKUP_CALLS.load(Ordering::SeqCst), 3, "Some 被缓存");
+}
+
+// ========== cache_if = 谓词：深度限制下的近似结果不缓存 ==========
+
+mod rules {
+    // 谓词依次接收每个参数和结果的引用
+    pub fn exact(_n: &u64, _budget: &u32, result: &(u64, bool)) -> bool {
+        result.1
+    }
+}
+
+static ESTIMATE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+// 返回 (fib(n), 是否精确)；预算耗尽时返回近似值
+#[memo(cache_if = rules::exact)]
+fn estimate(n: u64, budget: u32) -> (u64, bool) {
+    ESTIMATE_CALLS.fetch_add(1, Ordering::SeqCst);
+    if n < 2 {
+        return (n, true);
+    }
+    if budget == 0 {
+        return (0, false);
+    }
+    let (a, exact_a) = estimate(n - 1, budget - 1);
+    let (b, exact_b) = estimate(n - 2, budget - 1);
+    (a + b, exact_a && exact_b)
+}
+
+#[test]
+fn test_cache_if_predicate() {
+    assert_eq!(estimate(5, 10), (5, true));
+    let calls = ESTIMATE_CALLS.load(Ordering::SeqCst);
+    assert_eq!(estimate(5, 10), (5, true));
+    assert_eq!(ESTIMATE_CALLS.load(Ordering::SeqCst), calls);
+
+    assert!(!estimate(10, 2).1);
+    let calls = ESTIMATE_CALLS.load(Ordering::SeqCst);
+    assert!(!estimate(10, 2).1);
+    assert!(ESTIMATE_CALLS.load(Ordering::SeqCst) > calls, "近似结果没有被缓存");
+}
+
+// ========== 谓词 + 非引用参数（参数克隆后交给原函数体）+ mut 参数 ==========
+
+fn short_enough(words: &[String], limit: &usize, result: &str) -> bool {
+    result.len() <= *limit && !words.is_empty()
+}
+
+#[memo(cache_if = short_enough)]
+fn join_words(mut words: Vec<String>, limit: usize) -> String {
+    words.sort();
+    let joined = words.join(" ");
+    joined.chars().take(limit + 1).collect()
+}
+
+#[test]
+fn test_cache_if_owned_args() {
+    let words = vec!["b".to_string(), "a".to_string()];
+    assert_eq!(join_words(words.clone(), 10), "a b");
+    assert_eq!(join_words_stats().len, 1);
+    assert_eq!(join_words(words.clone(), 1), "a ");
+    assert_eq!(join_words_stats().len, 1, "超过 limit 的结果不缓存");
+    assert_eq!(join_words(Vec::new(), 3), "");
+    assert_eq!(join_words_stats().len, 1);
+}
+
+// ========== 方法 ==========
+
+struct Service {
+    calls: Cell<usize>,
+}
+
+impl Service {
+    fn cacheable(id: &u32, result: &Result<u32, String>) -> bool {
+        *id != 0 && result.is_ok()
+    }
+
+    #[memo(receiver=ignore, cache_if = Self::cacheable)]
+    fn load(&self, id: u32) -> Result<u32, String> {
+        self.calls.set(self.calls.get() + 1);
+        Ok(id * 2)
+    }
+
+    #[memo(receiver=ignore, cache_if = ok)]
+    fn parse(&self, mut text: String) -> Result<i64, String> {
+        self.calls.set(self.calls.get() + 1);
+        text.retain(|c| c != '_');
+        text.parse().map_err(|_| text)
+    }
+}
+
+#[test]
+fn test_cache_if_method() {
+    let service = Service { calls: Cell::new(0) };
+    service.load(0).unwrap();
+    service.load(0).unwrap();
+    service.load(5).unwrap();
+    service.load(5).unwrap();
+    assert_eq!(service.calls.get(), 3);
+
+    assert_eq!(service.parse("1_000".to_string()), Ok(1000));
+    assert_eq!(service.parse("1_000".to_string()), Ok(1000));
+    assert!(service.parse("x_y".to_string()).is_err());
+    assert!(service.parse("x_y".to_string()).is_err());
+    assert_eq!(service.calls.get(), 6);
+}
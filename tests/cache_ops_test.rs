@@ -0,0 +1,241 @@
+use mau::memo;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== 查询、写入、删除 ==========
+
+static FIB_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo]
+fn fib(n: u64) -> u64 {
+    FIB_CALLS.fetch_add(1, Ordering::SeqCst);
+    if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+}
+
+#[test]
+fn test_cached_insert_invalidate() {
+    assert_eq!(fib_cached(10), None);
+    assert_eq!(fib(10), 55);
+    assert_eq!(fib_cached(10), Some(55));
+    assert_eq!(fib_cached(11), None, "_cached 只查询，不计算");
+
+    // 删除一个条目：只重新计算这一项
+    let calls = FIB_CALLS.load(Ordering::SeqCst);
+    fib_invalidate(10);
+    assert_eq!(fib_cached(10), None);
+    assert_eq!(fib(10), 55);
+    assert_eq!(FIB_CALLS.load(Ordering::SeqCst) - calls, 1);
+
+    // 写入的值直接作为缓存结果
+    fib_insert(20, 0);
+    assert_eq!(fib(20), 0);
+    assert_eq!(fib_stats().len, 12);
+}
+
+// ========== 预先写入边界条件 ==========
+
+#[memo]
+fn triangle(n: u64) -> u64 {
+    // 没有写出 n == 0 的情况：由调用者预先写入
+    triangle(n - 1) + n
+}
+
+#[test]
+fn test_insert_base_case() {
+    triangle_insert(0, 0);
+    assert_eq!(triangle(100), 5050);
+    assert_eq!(triangle_cached(50), Some(1275));
+}
+
+// ========== 按条件保留 ==========
+
+#[memo]
+fn square(n: u32) -> u32 {
+    n * n
+}
+
+#[test]
+fn test_retain() {
+    for n in 0..10 {
+        square(n);
+    }
+    square_retain(|&n, &value| n % 2 == 0 && value < 50);
+    assert_eq!(square_snapshot(), vec![(0, 0), (2, 4), (4, 16), (6, 36)]);
+    assert_eq!(square_stats().len, 4);
+}
+
+// ========== 键与 key 模式一致 ==========
+
+#[memo(key = ptr)]
+fn total_ptr(data: &[u64]) -> u64 {
+    data.iter().sum()
+}
+
+#[memo(key = val)]
+fn total_val(data: &[u64]) -> u64 {
+    data.iter().sum()
+}
+
+#[test]
+fn test_key_mode() {
+    let data = vec![1, 2, 3];
+    let copy = data.clone();
+    total_ptr(&data);
+    total_val(&data);
+
+    // ptr 模式按地址：内容相同的另一个切片不命中
+    assert_eq!(total_ptr_cached(&data), Some(6));
+    assert_eq!(total_ptr_cached(&copy), None);
+
+    // val 模式按内容
+    assert_eq!(total_val_cached(&copy), Some(6));
+    total_val_invalidate(&copy);
+    assert_eq!(total_val_cached(&data), None);
+}
+
+// ========== ignore / symmetric ==========
+
+#[memo(ignore(log))]
+fn visit(log: &mut Vec<u32>, n: u32) -> u32 {
+    log.push(n);
+    n + 1
+}
+
+#[memo(symmetric(a, b))]
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[test]
+fn test_canonical_keys() {
+    let mut log = Vec::new();
+    visit(&mut log, 7);
+    // 被忽略的参数不出现在参数列表中
+    assert_eq!(visit_cached(7), Some(8));
+    visit_insert(9, 100);
+    assert_eq!(visit(&mut log, 9), 100);
+    assert_eq!(log, vec![7]);
+
+    gcd(12, 18);
+    assert_eq!(gcd_cached(18, 12), Some(6));
+    gcd_invalidate(18, 12);
+    assert_eq!(gcd_cached(12, 18), None);
+}
+
+// ========== dense ==========
+
+#[memo(dense(i: 0..4, j: 0..4))]
+fn cell(i: u32, j: u32) -> u32 {
+    i * 10 + j
+}
+
+#[test]
+fn test_dense_entries() {
+    cell(1, 2);
+    cell(3, 3);
+    cell(9, 9);
+    cell_insert(0, 0, 100);
+    cell_insert(7, 0, 700);
+    assert_eq!(cell_cached(0, 0), Some(100));
+    assert_eq!(cell_cached(7, 0), Some(700));
+
+    cell_invalidate(3, 3);
+    cell_invalidate(9, 9);
+    assert_eq!(cell_stats().len, 3);
+
+    cell_retain(|&(i, _), _| i < 5);
+    assert_eq!(cell_snapshot(), vec![((0, 0), 100), ((1, 2), 12)]);
+}
+
+// ========== capacity：删除后淘汰顺序仍然正确 ==========
+
+#[memo(capacity = 3)]
+fn bounded(n: u32) -> u32 {
+    n
+}
+
+#[test]
+fn test_retain_with_capacity() {
+    for n in 1..=3 {
+        bounded(n);
+    }
+    bounded_retain(|&n, _| n != 2);
+    bounded(4);
+    bounded(5);
+    assert_eq!(bounded_snapshot(), vec![(3, 3), (4, 4), (5, 5)]);
+}
+
+// ========== thread=multi + shards ==========
+
+#[memo(thread = multi, lifetime = program, shards = 4)]
+fn cube(n: u64) -> u64 {
+    n * n * n
+}
+
+#[test]
+fn test_sharded_entries() {
+    std::thread::spawn(|| cube_insert(3, 0)).join().unwrap();
+    assert_eq!(cube(3), 0);
+    for n in 0..10 {
+        cube(n);
+    }
+    cube_retain(|&n, _| n >= 5);
+    assert_eq!(cube_cached(4), None);
+    assert_eq!(cube_cached(5), Some(125));
+    assert_eq!(cube_stats().len, 5);
+}
+
+// ========== 泛型函数：每个单态化是独立的缓存 ==========
+
+#[memo]
+fn describe<T: std::fmt::Debug + Clone + std::hash::Hash + Eq>(value: T) -> String {
+    format!("{:?}", value)
+}
+
+#[test]
+fn test_generic_entries() {
+    describe(5u8);
+    assert_eq!(describe_cached(5u8), Some("5".to_string()));
+    assert_eq!(describe_cached(5u16), None);
+    describe_insert(6u16, "six".to_string());
+    assert_eq!(describe(6u16), "six");
+    describe_invalidate(5u8);
+    assert_eq!(describe_cached(5u8), None);
+}
+
+// ========== scope=instance：{Fn}Cache 的方法 ==========
+
+#[memo(scope = instance)]
+fn climb(n: u32) -> u64 {
+    if n < 2 { 1 } else { climb(n - 1) + climb(n - 2) }
+}
+
+#[test]
+fn test_instance_entries() {
+    let mut cache = ClimbCache::new();
+    cache.insert(1, 2);
+    assert_eq!(climb_in(&mut cache, 3), 5);
+    assert_eq!(cache.cached(3), Some(5));
+    cache.invalidate(3);
+    assert_eq!(cache.cached(3), None);
+    cache.retain(|&n, _| n > 1);
+    assert_eq!(cache.snapshot(), vec![(2, 3)]);
+}
+
+// ========== key_with ==========
+
+fn lowercase(word: &str) -> String {
+    word.to_lowercase()
+}
+
+#[memo(key_with = lowercase)]
+fn shout(word: &str) -> String {
+    word.to_uppercase()
+}
+
+#[test]
+fn test_key_with_entries() {
+    shout("Hello");
+    assert_eq!(shout_cached("HELLO"), Some("HELLO".to_string()));
+    shout_invalidate("hello");
+    assert_eq!(shout_cached("Hello"), None);
+}
@@ -0,0 +1,12 @@
+// 多个测试文件共用的辅助函数
+use std::panic;
+
+// 捕获 panic，返回 panic 消息
+pub fn panic_message<T>(f: impl FnOnce() -> T + panic::UnwindSafe) -> String {
+    let payload = panic::catch_unwind(f).err().expect("应该 panic");
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap()
+}
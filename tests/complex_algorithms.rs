@@ -1,3 +1,5 @@
+#![allow(clippy::needless_range_loop)]
+
 use mau::memo;
 // use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::time::Instant;
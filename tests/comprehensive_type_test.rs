@@ -1,3 +1,5 @@
+#![allow(clippy::approx_constant, clippy::ptr_arg)]
+
 use mau::memo;
 
 // 测试所有类型组合
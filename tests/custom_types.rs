@@ -1,3 +1,5 @@
+#![allow(clippy::upper_case_acronyms, dead_code)]
+
 use mau::memo;
 use std::collections::BTreeMap;
 
@@ -0,0 +1,125 @@
+use mau::memo;
+
+mod common;
+use common::panic_message;
+
+// ========== 单个参数：panic 消息包含函数名和调用路径 ==========
+
+// 1 → 2 → 3 → 1 形成循环
+#[memo(on_cycle = panic)]
+fn step(n: u32) -> u32 {
+    match n {
+        0 => 0,
+        1 => step(2),
+        2 => step(3),
+        3 => step(1),
+        _ => step(n - 1) + 1,
+    }
+}
+
+#[test]
+fn test_cycle_panic_path() {
+    let message = panic_message(|| step(5));
+    assert_eq!(message, "step: 检测到循环调用 step(3) → step(1) → step(2) → step(3)");
+
+    // panic 展开时计算中的键已经出栈：再次调用得到相同的路径，其他键不受影响
+    assert_eq!(panic_message(|| step(2)), "step: 检测到循环调用 step(2) → step(3) → step(1) → step(2)");
+    assert_eq!(step(0), 0);
+    assert_eq!(step_stats().len, 1);
+}
+
+// ========== cycle_check：debug 构建中检测 ==========
+
+#[memo(cycle_check)]
+fn walk(x: i32, y: i32) -> u32 {
+    match (x, y) {
+        (0, 0) => 0,
+        (1, 1) => walk(0, 1),
+        (0, 1) => walk(1, 0),
+        (1, 0) => walk(1, 1),
+        _ => walk(x - 1, y) + 1,
+    }
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn test_cycle_check_debug() {
+    // 多个参数的键显示为参数列表
+    assert_eq!(
+        panic_message(|| walk(3, 1)),
+        "walk: 检测到循环调用 walk(1, 1) → walk(0, 1) → walk(1, 0) → walk(1, 1)"
+    );
+    assert_eq!(walk(0, 0), 0);
+}
+
+// ========== 键没有实现 Debug ==========
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Token(u8);
+
+#[memo(on_cycle = panic)]
+fn resolve(token: Token) -> u8 {
+    if token.0 == 0 { resolve(Token(1)) } else { resolve(Token(0)) }
+}
+
+#[test]
+fn test_cycle_opaque_key() {
+    assert_eq!(panic_message(|| resolve(Token(0))), "resolve: 检测到循环调用 resolve(..) → resolve(..) → resolve(..)");
+}
+
+// ========== on_cycle = 值：返回哨兵值，不写入缓存 ==========
+
+const EDGES: [usize; 4] = [1, 2, 0, 0];
+
+// 沿着 0 → 1 → 2 → 0 走，回到正在计算的节点时返回 -1
+#[memo(on_cycle = -1)]
+fn depth(node: usize) -> i64 {
+    depth(EDGES[node]) + 1
+}
+
+#[test]
+fn test_cycle_sentinel() {
+    assert_eq!(depth(0), 2);
+    let stats = depth_stats();
+    assert_eq!(stats.len, 3, "哨兵值没有写入缓存");
+    assert_eq!(depth(3), 3);
+}
+
+// ========== multi 模式：检测到循环而不是死锁 ==========
+
+#[memo(thread=multi, on_cycle = panic)]
+fn ping(n: u8) -> u8 {
+    if n > 10 { ping(n - 1) } else { ping(n + 1) }
+}
+
+#[test]
+fn test_cycle_multi() {
+    assert_eq!(panic_message(|| ping(12)), "ping: 检测到循环调用 ping(11) → ping(10) → ping(11)");
+    // 登记已经清理，其他线程可以正常调用
+    let message = std::thread::spawn(|| panic_message(|| ping(11))).join().unwrap();
+    assert_eq!(message, "ping: 检测到循环调用 ping(11) → ping(10) → ping(11)");
+}
+
+// ========== 方法 ==========
+
+struct Machine {
+    transitions: Vec<Option<usize>>,
+}
+
+impl Machine {
+    // 停机状态返回 Some(步数)，进入循环的状态返回 None
+    #[memo(receiver=ignore, on_cycle = None)]
+    fn halts_after(&self, state: usize) -> Option<u32> {
+        match self.transitions[state] {
+            None => Some(0),
+            Some(next) => self.halts_after(next).map(|steps| steps + 1),
+        }
+    }
+}
+
+#[test]
+fn test_cycle_method() {
+    let machine = Machine { transitions: vec![Some(1), Some(2), None, Some(4), Some(3)] };
+    assert_eq!(machine.halts_after_start(0), Some(2));
+    assert_eq!(machine.halts_after_start(3), None);
+}
@@ -0,0 +1,144 @@
+use mau::{memo, solve};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== 单个参数：fib ==========
+
+static FIB_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(dense(n: 0..=50))]
+fn fib(n: u64) -> u64 {
+    FIB_CALLS.fetch_add(1, Ordering::SeqCst);
+    if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+}
+
+#[test]
+fn test_dense_fib() {
+    assert_eq!(fib(50), 12586269025);
+    assert_eq!(FIB_CALLS.load(Ordering::SeqCst), 51);
+    assert_eq!(fib(50), 12586269025);
+    assert_eq!(FIB_CALLS.load(Ordering::SeqCst), 51);
+
+    // 超出范围的参数退回哈希表，照样缓存
+    assert_eq!(fib(80), 23416728348467685);
+    assert_eq!(fib(80), 23416728348467685);
+    assert_eq!(FIB_CALLS.load(Ordering::SeqCst), 81);
+    let stats = fib_stats();
+    assert_eq!(stats.len, 81);
+    assert_eq!(stats.inserts, 81);
+
+    fib_clear();
+    assert_eq!(fib_stats().len, 0);
+    assert_eq!(fib(10), 55);
+    assert_eq!(fib_stats().len, 11);
+}
+
+// ========== 两个参数 + ignore：0/1 背包，范围使用常量 ==========
+
+const MAX_ITEMS: usize = 20;
+const MAX_CAPACITY: usize = 100;
+
+struct Item {
+    weight: usize,
+    value: u64,
+}
+
+#[memo(thread=multi, ignore(items), dense(i: 0..=MAX_ITEMS, cap: 0..=MAX_CAPACITY))]
+fn knapsack(items: &[Item], i: usize, cap: usize) -> u64 {
+    if i == items.len() {
+        return 0;
+    }
+    let skip = knapsack(items, i + 1, cap);
+    if items[i].weight > cap {
+        return skip;
+    }
+    skip.max(items[i].value + knapsack(items, i + 1, cap - items[i].weight))
+}
+
+#[test]
+fn test_dense_knapsack() {
+    let items = vec![
+        Item { weight: 12, value: 4 },
+        Item { weight: 2, value: 2 },
+        Item { weight: 1, value: 1 },
+        Item { weight: 1, value: 2 },
+        Item { weight: 4, value: 10 },
+    ];
+    assert_eq!(solve!(knapsack(&items, 0, 15)), 15);
+    assert_eq!(knapsack_stats().len, 0, "_start 之后清空");
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            std::thread::spawn(|| {
+                let items: Vec<_> = (1..=MAX_ITEMS).map(|w| Item { weight: w, value: (w * w) as u64 }).collect();
+                knapsack(&items, 0, MAX_CAPACITY)
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 1730);
+    }
+}
+
+// ========== 负数下界、参数顺序与声明顺序不同 ==========
+
+#[memo(dense(y: -3..=3, x: -3..3))]
+fn paths(x: i32, y: i32) -> u64 {
+    if x == -3 || y == -3 {
+        1
+    } else {
+        paths(x - 1, y) + paths(x, y - 1)
+    }
+}
+
+#[test]
+fn test_dense_negative_bounds() {
+    assert_eq!(paths(0, 0), 20);
+    assert_eq!(paths(2, 3), 462);
+    // x = 3 在半开区间之外，退回哈希表
+    assert_eq!(paths(3, 3), 924);
+    // x、y ∈ -3..=3 中除了 (-3, -3) 的所有格点
+    assert_eq!(paths_stats().len, 7 * 7 - 1);
+}
+
+// ========== out_of_bounds = panic ==========
+
+#[memo(dense(n: 0..100), out_of_bounds = panic)]
+fn triangle(n: u32) -> u32 {
+    if n == 0 { 0 } else { n + triangle(n - 1) }
+}
+
+#[test]
+fn test_dense_in_bounds() {
+    assert_eq!(triangle(99), 4950);
+    assert_eq!(triangle_stats().len, 100);
+}
+
+#[test]
+#[should_panic(expected = "triangle: 参数 n = 100 超出 dense 声明的范围 0..100")]
+fn test_dense_out_of_bounds_panic() {
+    triangle(100);
+}
+
+// ========== 方法 ==========
+
+struct Stairs {
+    steps: Vec<usize>,
+}
+
+impl Stairs {
+    #[memo(receiver=ignore, dense(n: 0..=64))]
+    fn ways(&self, n: usize) -> u64 {
+        if n == 0 {
+            return 1;
+        }
+        self.steps.iter().filter(|&&step| step <= n).map(|&step| self.ways(n - step)).sum()
+    }
+}
+
+#[test]
+fn test_dense_method() {
+    let stairs = Stairs { steps: vec![1, 2] };
+    assert_eq!(stairs.ways_start(64), 17167680177565);
+    let stairs = Stairs { steps: vec![1, 3] };
+    assert_eq!(stairs.ways_start(10), 28);
+}
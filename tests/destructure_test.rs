@@ -0,0 +1,113 @@
+use mau::{memo, solve};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== 元组模式 ==========
+
+static GRID_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo]
+fn paths((r, c): (usize, usize)) -> u64 {
+    GRID_CALLS.fetch_add(1, Ordering::SeqCst);
+    if r == 0 || c == 0 {
+        1
+    } else {
+        paths((r - 1, c)) + paths((r, c - 1))
+    }
+}
+
+#[test]
+fn test_tuple_pattern() {
+    assert_eq!(paths((16, 16)), 601080390);
+    assert_eq!(GRID_CALLS.load(Ordering::SeqCst), 17 * 17 - 1, "每个格子只计算一次（除了 (0, 0)）");
+    assert_eq!(solve!(paths((2, 2))), 6);
+}
+
+// ========== 结构体模式 + 混合参数 ==========
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+    mask: u32,
+    pos: usize,
+}
+
+// 旅行商：从 pos 出发访问 mask 之外的所有城市并回到 0
+#[memo]
+fn tsp(State { mask, pos }: State, dist: &[Vec<u32>]) -> u32 {
+    let n = dist.len();
+    if mask == (1 << n) - 1 {
+        return dist[pos][0];
+    }
+    (0..n)
+        .filter(|&next| mask & (1 << next) == 0)
+        .map(|next| dist[pos][next] + tsp(State { mask: mask | (1 << next), pos: next }, dist))
+        .min()
+        .unwrap()
+}
+
+#[test]
+fn test_struct_pattern() {
+    let dist = vec![
+        vec![0, 10, 15, 20],
+        vec![10, 0, 35, 25],
+        vec![15, 35, 0, 30],
+        vec![20, 25, 30, 0],
+    ];
+    assert_eq!(solve!(tsp(State { mask: 1, pos: 0 }, &dist)), 80);
+}
+
+// ========== 切片 / 数组模式、引用模式、通配符 ==========
+
+#[memo]
+fn weighted([a, b, c]: [i64; 3], &(x, y): &(i64, i64), _: u8) -> i64 {
+    a * x + b * y + c
+}
+
+#[test]
+fn test_array_reference_and_wildcard_patterns() {
+    assert_eq!(weighted([1, 2, 3], &(10, 100), 0), 213);
+    assert_eq!(weighted_start([1, 2, 3], &(10, 100), 1), 213);
+}
+
+// ========== 嵌套模式 + mut 绑定 ==========
+
+#[memo(key=val)]
+fn count_down((mut n, (step, floor)): (u32, (u32, u32))) -> u32 {
+    let mut steps = 0;
+    while n > floor {
+        n = n.saturating_sub(step);
+        steps += 1;
+    }
+    steps
+}
+
+#[test]
+fn test_nested_and_mut_patterns() {
+    assert_eq!(count_down((10, (3, 0))), 4);
+    assert_eq!(count_down((10, (3, 0))), 4);
+}
+
+// ========== 方法中的解构参数 ==========
+
+struct Board {
+    cost: Vec<Vec<u32>>,
+}
+
+impl Board {
+    #[memo]
+    fn best(&self, (r, c): (usize, usize)) -> u32 {
+        let here = self.cost[r][c];
+        if r + 1 == self.cost.len() {
+            here
+        } else {
+            let below = self.best((r + 1, c));
+            let diag = if c + 1 < self.cost[r].len() { self.best((r + 1, c + 1)) } else { u32::MAX };
+            here + below.min(diag)
+        }
+    }
+}
+
+#[test]
+fn test_method_with_tuple_pattern() {
+    let board = Board { cost: vec![vec![2, 0], vec![3, 4], vec![6, 5, 7], vec![4, 1, 8, 3]] };
+    assert_eq!(board.best_start((0, 0)), 11);
+}
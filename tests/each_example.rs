@@ -2,6 +2,8 @@
 //!
 //! each! 宏用于对指定范围内的每个索引执行闭包
 
+#![allow(clippy::useless_vec)]
+
 use mau::each;
 
 fn main() {
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_range_loop, clippy::useless_vec)]
+
 use mau::each;
 
 #[test]
@@ -1,3 +1,5 @@
+#![allow(clippy::reversed_empty_ranges, clippy::useless_vec)]
+
 use mau::sum;
 
 #[test]
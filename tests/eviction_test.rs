@@ -0,0 +1,118 @@
+use mau::memo;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== LRU（capacity 默认策略）==========
+
+static LRU_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(capacity=2)]
+fn square_lru(n: i32) -> i32 {
+    LRU_CALLS.fetch_add(1, Ordering::SeqCst);
+    n * n
+}
+
+#[test]
+fn test_lru_evicts_least_recently_used() {
+    square_lru(1);
+    square_lru(2);
+    square_lru(1); // 命中，1 变为最近使用
+    assert_eq!(LRU_CALLS.load(Ordering::SeqCst), 2);
+
+    square_lru(3); // 淘汰 2
+    assert_eq!(LRU_CALLS.load(Ordering::SeqCst), 3);
+
+    assert_eq!(square_lru(1), 1); // 仍在缓存中
+    assert_eq!(LRU_CALLS.load(Ordering::SeqCst), 3);
+
+    assert_eq!(square_lru(2), 4); // 已被淘汰，需要重新计算
+    assert_eq!(LRU_CALLS.load(Ordering::SeqCst), 4);
+}
+
+// ========== FIFO ==========
+
+static FIFO_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(capacity=2, evict=fifo)]
+fn square_fifo(n: i32) -> i32 {
+    FIFO_CALLS.fetch_add(1, Ordering::SeqCst);
+    n * n
+}
+
+#[test]
+fn test_fifo_evicts_oldest_insert() {
+    square_fifo(1);
+    square_fifo(2);
+    square_fifo(1); // 命中，但不改变插入顺序
+    square_fifo(3); // 淘汰 1
+    assert_eq!(FIFO_CALLS.load(Ordering::SeqCst), 3);
+
+    square_fifo(2);
+    assert_eq!(FIFO_CALLS.load(Ordering::SeqCst), 3, "2 应该仍在缓存中");
+
+    square_fifo(1);
+    assert_eq!(FIFO_CALLS.load(Ordering::SeqCst), 4, "1 应该已被淘汰");
+}
+
+// ========== LFU ==========
+
+static LFU_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(capacity=2, evict=lfu)]
+fn square_lfu(n: i32) -> i32 {
+    LFU_CALLS.fetch_add(1, Ordering::SeqCst);
+    n * n
+}
+
+#[test]
+fn test_lfu_evicts_least_frequently_used() {
+    square_lfu(1);
+    square_lfu(1);
+    square_lfu(1); // 1 被访问 3 次
+    square_lfu(2);
+    square_lfu(3); // 淘汰访问次数最少的 2
+    assert_eq!(LFU_CALLS.load(Ordering::SeqCst), 3);
+
+    square_lfu(1);
+    assert_eq!(LFU_CALLS.load(Ordering::SeqCst), 3, "1 访问最频繁，应该保留");
+
+    square_lfu(2);
+    assert_eq!(LFU_CALLS.load(Ordering::SeqCst), 4, "2 应该已被淘汰");
+}
+
+// ========== 递归函数 + 小容量 ==========
+
+#[memo(capacity=8)]
+fn fib_bounded(n: u64) -> u64 {
+    if n < 2 { n } else { fib_bounded(n - 1) + fib_bounded(n - 2) }
+}
+
+#[test]
+fn test_bounded_recursion_still_correct() {
+    assert_eq!(fib_bounded(50), 12586269025);
+    assert_eq!(fib_bounded_start(60), 1548008755920);
+}
+
+// ========== Multi 模式 ==========
+
+static MULTI_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi, capacity=1, evict=fifo)]
+fn square_multi(n: i32) -> i32 {
+    MULTI_CALLS.fetch_add(1, Ordering::SeqCst);
+    n * n
+}
+
+#[test]
+fn test_multi_mode_capacity() {
+    square_multi(1);
+    square_multi(1);
+    assert_eq!(MULTI_CALLS.load(Ordering::SeqCst), 1);
+
+    square_multi(2); // 淘汰 1
+    square_multi(1);
+    assert_eq!(MULTI_CALLS.load(Ordering::SeqCst), 3);
+
+    square_multi_clear();
+    square_multi(1);
+    assert_eq!(MULTI_CALLS.load(Ordering::SeqCst), 4);
+}
@@ -0,0 +1,115 @@
+use mau::memo;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+use common::panic_message;
+
+// ========== 有环图上的可达性 ==========
+
+// 0 → 1 → 2 → 0 形成环，2 → 3，4 → 4
+const GRAPH: [&[usize]; 5] = [&[1], &[2], &[0, 3], &[], &[4]];
+static REACH_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(fixpoint = false)]
+fn reaches(from: usize, to: usize) -> bool {
+    REACH_CALLS.fetch_add(1, Ordering::SeqCst);
+    from == to || GRAPH[from].iter().any(|&next| reaches(next, to))
+}
+
+#[test]
+fn test_fixpoint_reachability() {
+    assert!(reaches(0, 3));
+    assert!(!reaches(4, 3));
+    assert!(!reaches(3, 0));
+
+    // 环上的结果收敛之后全部写入缓存，之后直接命中
+    let calls = REACH_CALLS.load(Ordering::SeqCst);
+    assert!(reaches(1, 3));
+    assert!(reaches(2, 3));
+    assert!(!reaches(4, 0));
+    assert_eq!(REACH_CALLS.load(Ordering::SeqCst), calls + 1, "只有 reaches(4, 0) 是新的");
+}
+
+// ========== 带环的最短路：从 u64::MAX 开始逐轮下降 ==========
+
+// (目标, 边权)
+const ROADS: [&[(usize, u64)]; 4] = [&[(1, 4), (2, 1)], &[(3, 1), (0, 1)], &[(1, 2), (0, 7)], &[]];
+
+#[memo(fixpoint = u64::MAX)]
+fn distance(node: usize) -> u64 {
+    if node == 3 {
+        return 0;
+    }
+    ROADS[node].iter().map(|&(next, cost)| distance(next).saturating_add(cost)).min().unwrap_or(u64::MAX)
+}
+
+#[test]
+fn test_fixpoint_shortest_path() {
+    assert_eq!(distance(0), 4);
+    assert_eq!(distance(1), 1);
+    assert_eq!(distance(2), 3);
+    assert_eq!(distance_stats().len, 4);
+}
+
+// ========== 不收敛：达到 fixpoint_limit 时 panic ==========
+
+#[memo(fixpoint = 0, fixpoint_limit = 50)]
+fn runaway(n: u32) -> u64 {
+    if n == 0 { runaway(1) + 1 } else { runaway(0) }
+}
+
+#[test]
+fn test_fixpoint_diverges() {
+    let message = panic_message(|| runaway(0));
+    assert_eq!(message, "runaway: fixpoint 在 50 轮迭代后仍未收敛（循环入口 runaway(0)）");
+
+    // panic 展开时计算中的状态已经清理，没有写入缓存，再次调用得到相同的结果
+    assert_eq!(runaway_stats().len, 0);
+    assert_eq!(panic_message(|| runaway(1)), "runaway: fixpoint 在 50 轮迭代后仍未收敛（循环入口 runaway(1)）");
+}
+
+// ========== share：文法的 FIRST 集合 ==========
+
+// S → A b | c；A → S a | ε（大写为非终结符）
+fn rules(symbol: char) -> &'static [&'static str] {
+    match symbol {
+        'S' => &["Ab", "c"],
+        'A' => &["Sa", ""],
+        _ => &[],
+    }
+}
+
+#[memo(share, fixpoint = BTreeSet::new())]
+fn first(symbol: char) -> BTreeSet<char> {
+    let mut set = BTreeSet::new();
+    for rule in rules(symbol) {
+        let mut all_nullable = true;
+        for c in rule.chars() {
+            if c.is_lowercase() {
+                set.insert(c);
+                all_nullable = false;
+                break;
+            }
+            let inner = first(c);
+            set.extend(inner.iter().filter(|&&c| c != 'ε'));
+            if !inner.contains(&'ε') {
+                all_nullable = false;
+                break;
+            }
+        }
+        if all_nullable {
+            set.insert('ε');
+        }
+    }
+    set
+}
+
+#[test]
+fn test_fixpoint_share() {
+    let set: Rc<BTreeSet<char>> = first('S');
+    assert_eq!(set.iter().collect::<String>(), "bc");
+    assert_eq!(first('A').iter().collect::<String>(), "bcε");
+    assert!(Rc::ptr_eq(&set, &first('S')));
+}
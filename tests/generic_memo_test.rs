@@ -0,0 +1,177 @@
+use mau::memo;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::ops::Add;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== 泛型切片参数：每个单态化一个缓存 ==========
+
+static MAX_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo]
+fn max_of<T: Ord + Hash + Eq + Clone>(items: &[T]) -> T {
+    MAX_CALLS.fetch_add(1, Ordering::SeqCst);
+    if items.len() == 1 {
+        items[0].clone()
+    } else {
+        items[0].clone().max(max_of(&items[1..]))
+    }
+}
+
+#[test]
+fn test_generic_slice() {
+    let numbers = vec![3, 9, 2, 7];
+    let words = vec!["pear".to_string(), "apple".to_string(), "zoo".to_string()];
+
+    assert_eq!(max_of(&numbers), 9);
+    assert_eq!(max_of(&words), "zoo");
+    assert_eq!(MAX_CALLS.load(Ordering::SeqCst), 7);
+
+    // 两个单态化的缓存互不干扰，_stats 汇总所有单态化
+    assert_eq!(max_of(&numbers), 9);
+    assert_eq!(max_of(&words), "zoo");
+    assert_eq!(MAX_CALLS.load(Ordering::SeqCst), 7);
+    let stats = max_of_stats();
+    assert_eq!(stats.len, 7);
+    assert_eq!(stats.hits, 2);
+
+    max_of_clear();
+    assert_eq!(max_of_stats().len, 0);
+    assert_eq!(max_of_start(&numbers), 9);
+    assert_eq!(MAX_CALLS.load(Ordering::SeqCst), 11);
+}
+
+// ========== 类型参数只出现在约束中：按 TypeId 区分 ==========
+
+#[memo]
+fn bytes_for<T>(count: usize) -> usize {
+    count * std::mem::size_of::<T>()
+}
+
+#[test]
+fn test_type_param_only_in_body() {
+    assert_eq!(bytes_for::<u8>(10), 10);
+    assert_eq!(bytes_for::<u64>(10), 80, "不同类型参数不共享缓存");
+    assert_eq!(bytes_for::<u8>(10), 10);
+    assert_eq!(bytes_for_stats().len, 2);
+}
+
+// ========== where 子句 ==========
+
+#[memo(key=val)]
+fn total<T>(values: Vec<T>) -> T
+where
+    T: Copy + Default + Hash + Eq + Add<Output = T>,
+{
+    values.iter().fold(T::default(), |acc, &v| acc + v)
+}
+
+#[test]
+fn test_where_clause() {
+    assert_eq!(total(vec![1u32, 2, 3]), 6);
+    assert_eq!(total(vec![1i64, -2, 3]), 2);
+    assert_eq!(total_start(vec![4u8, 5]), 9);
+}
+
+// ========== impl Trait 参数 ==========
+
+static LABEL_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo]
+fn label(prefix: impl Display + Hash + Eq + Clone, id: u32) -> String {
+    LABEL_CALLS.fetch_add(1, Ordering::SeqCst);
+    format!("{}-{}", prefix, id)
+}
+
+#[memo(key=val)]
+fn longest(words: &[impl AsRef<str> + Hash + Eq + Clone]) -> usize {
+    words.iter().map(|w| w.as_ref().len()).max().unwrap_or(0)
+}
+
+#[test]
+fn test_impl_trait_params() {
+    assert_eq!(label("item", 1), "item-1");
+    assert_eq!(label(String::from("item"), 1), "item-1");
+    assert_eq!(label('x', 2), "x-2");
+    assert_eq!(label("item", 1), "item-1");
+    assert_eq!(LABEL_CALLS.load(Ordering::SeqCst), 3, "&str 与 String 是不同的单态化");
+
+    assert_eq!(longest(&["a", "abc"]), 3);
+    assert_eq!(longest(&[String::from("abcd")]), 4);
+}
+
+// ========== const 泛型：作为值参与键 ==========
+
+static BINOM_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo]
+fn binom<const M: u64>(n: u64, k: u64) -> u64 {
+    BINOM_CALLS.fetch_add(1, Ordering::SeqCst);
+    if k == 0 || k == n {
+        1
+    } else {
+        (binom::<M>(n - 1, k - 1) + binom::<M>(n - 1, k)) % M
+    }
+}
+
+#[test]
+fn test_const_generic() {
+    assert_eq!(binom::<1_000_000_007>(30, 15), 155117520);
+    let calls = BINOM_CALLS.load(Ordering::SeqCst);
+    assert_eq!(binom::<7>(30, 15), 155117520 % 7, "不同的 M 不共享结果");
+    assert!(BINOM_CALLS.load(Ordering::SeqCst) > calls);
+}
+
+// ========== multi 模式 ==========
+
+static PAIR_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi, key=val)]
+fn pair<A: Hash + Eq + Clone, B: Hash + Eq + Clone>(a: A, b: B) -> (B, A) {
+    PAIR_CALLS.fetch_add(1, Ordering::SeqCst);
+    (b, a)
+}
+
+#[test]
+fn test_generic_multi_mode() {
+    let handles: Vec<_> = (0..4)
+        .map(|_| std::thread::spawn(|| pair(1u8, "one")))
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), ("one", 1));
+    }
+    assert_eq!(pair("one", 1u8), (1, "one"));
+    assert_eq!(PAIR_CALLS.load(Ordering::SeqCst), 2, "每个单态化只计算一次");
+    assert_eq!(pair_stats().len, 2);
+}
+
+// ========== 泛型 impl 中的方法 ==========
+
+struct Sequence<T> {
+    items: Vec<T>,
+}
+
+impl<T: Ord + Clone + 'static> Sequence<T> {
+    // 后缀 items[i..] 的最大值
+    #[memo(receiver=ignore)]
+    fn suffix_max(&self, i: usize) -> T {
+        let here = self.items[i].clone();
+        if i + 1 == self.items.len() {
+            here
+        } else {
+            here.max(self.suffix_max(i + 1))
+        }
+    }
+}
+
+#[test]
+fn test_generic_impl_method() {
+    let numbers = Sequence { items: vec![4, 8, 1] };
+    let letters = Sequence { items: vec!['q', 'b', 'z'] };
+    assert_eq!(numbers.suffix_max(0), 8);
+    assert_eq!(letters.suffix_max(0), 'z', "不同的 impl 单态化使用不同的缓存");
+    assert_eq!(letters.suffix_max(1), 'z');
+
+    Sequence::<i32>::suffix_max_clear();
+    assert_eq!(numbers.suffix_max_start(1), 8);
+}
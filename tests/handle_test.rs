@@ -0,0 +1,194 @@
+use mau::{memo, Memo, MemoConfig};
+
+// ========== #[memo(handle)]：统一的句柄 Memo{Fn} ==========
+
+#[memo(handle)]
+fn fib(n: u64) -> u64 {
+    if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+}
+
+#[memo(handle, thread = multi, lifetime = program, capacity = 100, evict = lfu)]
+fn square(n: u32) -> u32 {
+    n * n
+}
+
+#[test]
+fn test_handle_methods() {
+    fib(20);
+    assert_eq!(MemoFib.len(), 21);
+    assert!(!MemoFib.is_empty());
+    let stats = MemoFib.stats();
+    assert_eq!((stats.misses, stats.len), (fib_stats().misses, 21));
+
+    MemoFib.clear();
+    assert!(MemoFib.is_empty());
+    assert_eq!(fib_stats().manual_clears, 1);
+
+    // lifetime=problem：call_start 与 fib_start 相同，返回前清除缓存
+    assert_eq!(MemoFib.call_start((30,)), 832040);
+    assert!(MemoFib.is_empty());
+
+    // lifetime=program：保留缓存
+    assert_eq!(MemoSquare.call_start((12,)), 144);
+    assert_eq!(MemoSquare.len(), 1);
+}
+
+#[test]
+fn test_handle_config() {
+    assert_eq!(
+        MemoFib.config(),
+        MemoConfig {
+            name: "fib",
+            thread: "single",
+            key: "ptr",
+            lifetime: "problem",
+            capacity: None,
+            evict: None,
+            ttl_ms: None,
+            shards: None,
+            dense: false,
+            hasher: "std",
+            clears_on_start: true,
+        }
+    );
+    let config = MemoSquare.config();
+    assert_eq!((config.thread, config.capacity, config.evict), ("multi", Some(100), Some("lfu")));
+    assert!(!config.clears_on_start);
+
+    // 分片时报告声明的容量，而不是每个分片的容量
+    let config = MemoSpread.config();
+    assert_eq!((config.capacity, config.shards), (Some(4), Some(8)));
+}
+
+#[memo(handle, thread = multi, lifetime = program, capacity = 4, shards = 8)]
+fn spread(n: u32) -> u32 {
+    n + 1
+}
+
+// ========== 不依赖函数名，统一管理多个函数 ==========
+
+#[memo(handle)]
+fn paths(i: u32, j: u32) -> u64 {
+    if i == 0 || j == 0 { 1 } else { paths(i - 1, j) + paths(i, j - 1) }
+}
+
+fn total_len(handles: &[&dyn Memo]) -> usize {
+    handles.iter().map(|handle| handle.len()).sum()
+}
+
+fn run_twice<M: Memo<Args<'static> = (u32, u32)>>(memo: M, args: (u32, u32)) -> (M::Output, M::Output) {
+    (memo.call_start(args), memo.call_start(args))
+}
+
+#[test]
+fn test_handle_generic_use() {
+    fib(10);
+    paths(3, 3);
+    let handles: [&dyn Memo; 2] = [&MemoFib, &MemoPaths];
+    assert_eq!(total_len(&handles), 11 + 15);
+    for handle in handles {
+        handle.clear();
+    }
+    assert_eq!(total_len(&handles), 0);
+
+    assert_eq!(run_twice(MemoPaths, (4, 4)), (70, 70));
+    let stats = MemoPaths.stats();
+    assert_eq!((stats.clears, stats.manual_clears), (2, 1));
+}
+
+// ========== 引用参数：Args<'a> ==========
+
+#[memo(handle, key = val, lifetime = program)]
+fn total(data: &[u64], scale: u64) -> u64 {
+    data.iter().sum::<u64>() * scale
+}
+
+#[test]
+fn test_handle_reference_args() {
+    let data = vec![1, 2, 3];
+    assert_eq!(MemoTotal.call_start((&data, 2)), 12);
+    assert_eq!(total_cached(&data, 2), Some(12));
+}
+
+// ========== helpers(...)：改名或不生成辅助函数 ==========
+
+// 与默认的 area_start / area_clear 同名的用户函数
+fn area_start() -> &'static str {
+    "user"
+}
+
+fn area_clear() -> &'static str {
+    "user"
+}
+
+#[memo(handle, helpers(start = none, clear = reset_area, stats = area_report))]
+fn area(w: u32, h: u32) -> u32 {
+    w * h
+}
+
+#[test]
+fn test_helpers_rename() {
+    assert_eq!(area_start(), "user");
+    assert_eq!(area_clear(), "user");
+
+    area(3, 4);
+    assert_eq!(area_report().len, 1);
+    reset_area();
+    assert_eq!(area_report().manual_clears, 1);
+    // 句柄不依赖辅助函数
+    assert_eq!(MemoArea.call_start((5, 6)), 30);
+    assert_eq!(MemoArea.stats().clears, 1);
+}
+
+#[memo(handle, helpers = none)]
+fn quiet(n: u32) -> u32 {
+    n + 1
+}
+
+#[test]
+fn test_helpers_none_keeps_handle() {
+    quiet(1);
+    assert_eq!(MemoQuiet.len(), 1);
+    MemoQuiet.clear();
+    assert!(MemoQuiet.is_empty());
+}
+
+// ========== 函数体内定义的函数 ==========
+
+#[test]
+fn test_handle_local_fn() {
+    #[memo(handle)]
+    fn steps(n: u32) -> u32 {
+        if n == 0 { 0 } else { steps(n - 1) + 1 }
+    }
+
+    assert_eq!(steps(9), 9);
+    assert_eq!(MemoSteps.len(), 10);
+    assert_eq!(MemoSteps.config().name, "steps");
+}
+
+// ========== 句柄不占用函数名：与同名的模块共存 ==========
+
+mod label {
+    use mau::memo;
+    use std::fmt;
+
+    pub struct Label(pub u32);
+
+    impl fmt::Display for Label {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "#{}", self.0)
+        }
+    }
+
+    #[memo(handle, helpers = none)]
+    pub fn fmt(n: u32) -> String {
+        Label(n).to_string()
+    }
+}
+
+#[test]
+fn test_handle_name_does_not_collide() {
+    assert_eq!(label::fmt(7), "#7");
+    assert_eq!(label::MemoFmt.len(), 1);
+}
@@ -0,0 +1,120 @@
+use mau::{memo, solve};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== 整数元组键：网格路径 ==========
+
+#[memo(hasher=fx)]
+fn grid_fx(x: u64, y: u64) -> u64 {
+    if x == 0 || y == 0 { 1 } else { grid_fx(x - 1, y) + grid_fx(x, y - 1) }
+}
+
+#[memo(hasher=ahash_like)]
+fn grid_ahash(x: u64, y: u64) -> u64 {
+    if x == 0 || y == 0 { 1 } else { grid_ahash(x - 1, y) + grid_ahash(x, y - 1) }
+}
+
+#[memo(hasher=identity)]
+fn grid_identity(x: u64, y: u64) -> u64 {
+    if x == 0 || y == 0 { 1 } else { grid_identity(x - 1, y) + grid_identity(x, y - 1) }
+}
+
+#[memo(hasher=std)]
+fn grid_std(x: u64, y: u64) -> u64 {
+    if x == 0 || y == 0 { 1 } else { grid_std(x - 1, y) + grid_std(x, y - 1) }
+}
+
+#[test]
+fn test_hashers_agree() {
+    let expected = 155117520;
+    assert_eq!(solve!(grid_fx(15, 15)), expected);
+    assert_eq!(solve!(grid_ahash(15, 15)), expected);
+    assert_eq!(solve!(grid_identity(15, 15)), expected);
+    assert_eq!(solve!(grid_std(15, 15)), expected);
+
+    // 缓存行为与哈希函数无关
+    assert_eq!(grid_fx(15, 15), expected);
+    assert_eq!(grid_ahash(15, 15), expected);
+    assert_eq!(grid_fx_stats().len, grid_ahash_stats().len);
+    assert_eq!(grid_fx_stats().len, 16 * 16 - 1);
+}
+
+// ========== 非整数键：字节切片按 8 字节分块哈希 ==========
+
+static EDIT_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(key=val, hasher=identity)]
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    EDIT_CALLS.fetch_add(1, Ordering::SeqCst);
+    match (a.split_first(), b.split_first()) {
+        (None, _) => b.len(),
+        (_, None) => a.len(),
+        (Some((x, ra)), Some((y, rb))) => {
+            if x == y {
+                edit_distance(ra, rb)
+            } else {
+                1 + edit_distance(ra, b).min(edit_distance(a, rb)).min(edit_distance(ra, rb))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_hasher_byte_keys() {
+    assert_eq!(edit_distance(b"kitten on the road", b"sitting on a road"), 6);
+    let calls = EDIT_CALLS.load(Ordering::SeqCst);
+    let copy = b"kitten on the road".to_vec();
+    assert_eq!(edit_distance(&copy, b"sitting on a road"), 6);
+    assert_eq!(EDIT_CALLS.load(Ordering::SeqCst), calls, "内容相同的键命中缓存");
+}
+
+// ========== 与 multi、capacity、dense 组合 ==========
+
+#[memo(thread=multi, hasher=fx, capacity=64, evict=lfu)]
+fn collatz_len(n: u64) -> u32 {
+    if n == 1 { 1 } else if n.is_multiple_of(2) { 1 + collatz_len(n / 2) } else { 1 + collatz_len(3 * n + 1) }
+}
+
+// 超出 dense 范围的参数退回使用 ahash_like 的哈希表
+#[memo(hasher=ahash_like, dense(n: 0..=20, max: 0..=20))]
+fn partitions(n: i64, max: i64) -> u64 {
+    if n == 0 {
+        return 1;
+    }
+    (1..=max.min(n)).map(|part| partitions(n - part, part)).sum()
+}
+
+#[test]
+fn test_hasher_combinations() {
+    let handles: Vec<_> = (0..4).map(|_| std::thread::spawn(|| collatz_len(27))).collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 112);
+    }
+    assert!(collatz_len_stats().len <= 64);
+
+    assert_eq!(partitions(30, 30), 5604);
+    assert_eq!(partitions(20, 20), 627);
+}
+
+// ========== 方法 ==========
+
+struct Triangle {
+    rows: Vec<Vec<u32>>,
+}
+
+impl Triangle {
+    #[memo(receiver=ignore, hasher=fx)]
+    fn best(&self, row: usize, col: usize) -> u32 {
+        let here = self.rows[row][col];
+        if row + 1 == self.rows.len() {
+            here
+        } else {
+            here + self.best(row + 1, col).max(self.best(row + 1, col + 1))
+        }
+    }
+}
+
+#[test]
+fn test_hasher_method() {
+    let triangle = Triangle { rows: vec![vec![3], vec![7, 4], vec![2, 4, 6], vec![8, 5, 9, 3]] };
+    assert_eq!(triangle.best_start(0, 0), 23);
+}
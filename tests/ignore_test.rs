@@ -0,0 +1,107 @@
+use mau::{memo, solve};
+
+// ========== 只读上下文 + &mut 计数器 ==========
+
+struct Graph {
+    edges: Vec<Vec<usize>>,
+}
+
+// DAG 上从 node 出发的最长路径（边数）；visited 记录实际计算过的节点
+#[memo(ignore(graph, visited))]
+fn longest(graph: &Graph, visited: &mut Vec<usize>, node: usize) -> usize {
+    visited.push(node);
+    graph.edges[node]
+        .iter()
+        .map(|&next| 1 + longest(graph, visited, next))
+        .max()
+        .unwrap_or(0)
+}
+
+#[test]
+fn test_ignore_context() {
+    // 0 → 1 → 3, 0 → 2 → 3, 3 → 4
+    let graph = Graph { edges: vec![vec![1, 2], vec![3], vec![3], vec![4], vec![]] };
+    let mut visited = Vec::new();
+    assert_eq!(longest(&graph, &mut visited, 0), 3);
+    visited.sort_unstable();
+    assert_eq!(visited, vec![0, 1, 2, 3, 4], "每个节点只计算一次");
+
+    // 键只包含 node：不同的问题需要用 _start / solve! 清除缓存
+    let mut visited = Vec::new();
+    assert_eq!(longest_start(&graph, &mut visited, 1), 2);
+    assert!(visited.is_empty(), "命中第一次调用留下的缓存");
+    let mut visited = Vec::new();
+    assert_eq!(solve!(longest(&graph, &mut visited, 1)), 2);
+    assert_eq!(visited.len(), 3, "_start 之后重新计算");
+}
+
+// ========== 按值传入的上下文：原样移动给原函数体，不需要 Clone ==========
+
+struct Unit {
+    name: &'static str,
+}
+
+#[memo(ignore(unit))]
+fn area(unit: Unit, w: u32, h: u32) -> String {
+    format!("{}{}", w * h, unit.name)
+}
+
+#[test]
+fn test_ignore_owned() {
+    assert_eq!(area(Unit { name: "m2" }, 3, 4), "12m2");
+    assert_eq!(area(Unit { name: "ft2" }, 3, 4), "12m2", "unit 不参与键");
+    assert_eq!(area_stats().len, 1);
+}
+
+// ========== 与 cache_if 组合：谓词只接收参与键的参数 ==========
+
+fn within_budget(_n: &u64, result: &Option<u64>) -> bool {
+    result.is_some()
+}
+
+// budget 耗尽时返回 None，不缓存
+#[memo(ignore(budget), cache_if = within_budget)]
+fn tribonacci(budget: &mut u32, n: u64) -> Option<u64> {
+    if *budget == 0 {
+        return None;
+    }
+    *budget -= 1;
+    if n < 3 {
+        return Some(if n == 2 { 1 } else { 0 });
+    }
+    Some(tribonacci(budget, n - 1)? + tribonacci(budget, n - 2)? + tribonacci(budget, n - 3)?)
+}
+
+#[test]
+fn test_ignore_with_cache_if() {
+    let mut budget = 5;
+    assert_eq!(tribonacci(&mut budget, 10), None);
+    let mut budget = 100;
+    assert_eq!(tribonacci(&mut budget, 10), Some(81));
+    assert!(budget > 80, "之前成功的子问题命中缓存");
+}
+
+// ========== 方法 ==========
+
+struct Stairs {
+    max_step: u64,
+}
+
+impl Stairs {
+    #[memo(thread=multi, ignore(trace))]
+    fn ways(&self, trace: &mut Vec<u64>, n: u64) -> u64 {
+        trace.push(n);
+        if n == 0 {
+            return 1;
+        }
+        (1..=self.max_step.min(n)).map(|step| self.ways(trace, n - step)).sum()
+    }
+}
+
+#[test]
+fn test_ignore_method() {
+    let stairs = Stairs { max_step: 2 };
+    let mut trace = Vec::new();
+    assert_eq!(stairs.ways_start(&mut trace, 30), 1346269);
+    assert_eq!(trace.len(), 31);
+}
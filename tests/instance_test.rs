@@ -0,0 +1,174 @@
+use mau::memo;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== 每个 FibCache 是独立的缓存 ==========
+
+static FIB_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(scope = instance)]
+fn fib(n: u64) -> u64 {
+    FIB_CALLS.fetch_add(1, Ordering::SeqCst);
+    if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+}
+
+#[test]
+fn test_instance_separate_caches() {
+    let mut first = FibCache::new();
+    let mut second = FibCache::default();
+    assert!(first.is_empty());
+
+    assert_eq!(fib_in(&mut first, 30), 832040);
+    assert_eq!(first.len(), 31);
+    assert!(second.is_empty(), "另一个缓存不受影响");
+
+    assert_eq!(fib_in(&mut second, 10), 55);
+    assert_eq!(second.len(), 11);
+    assert_eq!(first.len(), 31);
+
+    // 原函数体中的递归调用使用调用者传入的缓存：fib(30) 和 fib(29) 命中
+    let hits = first.stats().hits;
+    assert_eq!(fib_in(&mut first, 31), 1346269);
+    assert_eq!(first.len(), 32);
+    assert_eq!(first.stats().hits, hits + 2);
+    assert_eq!(format!("{:?}", first), "FibCache { len: 32 }");
+
+    first.clear();
+    assert!(first.is_empty());
+    assert_eq!(first.stats().manual_clears, 1);
+}
+
+#[test]
+fn test_instance_plain_call_uses_temporary_cache() {
+    let before = FIB_CALLS.load(Ordering::SeqCst);
+    assert_eq!(fib(20), 6765);
+    assert_eq!(fib(20), 6765);
+    // 每次直接调用都使用新的临时缓存
+    assert_eq!(FIB_CALLS.load(Ordering::SeqCst) - before, 2 * 21);
+}
+
+// ========== 同一个线程中的两个求解器互不干扰 ==========
+
+// 用 coins[i..] 凑出 amount 的方案数
+#[memo(scope = instance, ignore(coins))]
+fn ways(coins: &[u64], i: usize, amount: u64) -> u64 {
+    if amount == 0 {
+        return 1;
+    }
+    if i == coins.len() {
+        return 0;
+    }
+    let skip = ways(coins, i + 1, amount);
+    if coins[i] > amount { skip } else { skip + ways(coins, i, amount - coins[i]) }
+}
+
+struct Solver {
+    coins: Vec<u64>,
+    cache: WaysCache,
+}
+
+impl Solver {
+    fn new(coins: Vec<u64>) -> Self {
+        Solver { coins, cache: WaysCache::new() }
+    }
+
+    fn count(&mut self, amount: u64) -> u64 {
+        ways_in(&mut self.cache, &self.coins, 0, amount)
+    }
+}
+
+#[test]
+fn test_instance_independent_solvers() {
+    let mut euro = Solver::new(vec![1, 2, 5, 10, 20, 50, 100, 200]);
+    let mut odd = Solver::new(vec![3, 7]);
+
+    // 交替使用：键相同但输入不同，各自的缓存不会互相污染
+    assert_eq!(euro.count(10), 11);
+    assert_eq!(odd.count(10), 1);
+    assert_eq!(euro.count(200), 73682);
+    assert_eq!(odd.count(20), 1);
+    assert_eq!(odd.count(11), 0);
+
+    // 释放一个求解器只释放它自己的缓存
+    let euro_len = euro.cache.len();
+    drop(odd);
+    assert_eq!(euro.cache.len(), euro_len);
+    assert_eq!(euro.count(200), 73682);
+}
+
+// ========== panic 时缓存交还给调用者 ==========
+
+#[memo(scope = instance)]
+fn fragile(n: u32) -> u32 {
+    let result = if n == 0 { 0 } else { fragile(n - 1) + 1 };
+    if n == 7 {
+        panic!("fragile(7)");
+    }
+    result
+}
+
+#[test]
+fn test_instance_panic_restores_cache() {
+    let mut cache = FragileCache::new();
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| fragile_in(&mut cache, 10)));
+    assert!(result.is_err());
+
+    // panic 之前写入的结果（fragile(0..=6)）仍然在缓存中
+    assert_eq!(cache.len(), 7);
+    assert_eq!(fragile_in(&mut cache, 6), 6);
+    assert_eq!(cache.stats().hits, 1);
+}
+
+// ========== 带生命周期参数的函数 ==========
+
+#[memo(scope = instance, key = val)]
+fn count_prefix<'a>(bytes: &'a [u8], n: usize) -> usize {
+    if n == 0 { 0 } else { count_prefix(bytes, n - 1) + usize::from(bytes[n - 1] == b'a') }
+}
+
+#[test]
+fn test_instance_lifetime_params() {
+    let mut cache = CountPrefixCache::new();
+    let text = b"banana".to_vec();
+    assert_eq!(count_prefix_in(&mut cache, &text, 6), 3);
+    assert_eq!(cache.len(), 7);
+    assert_eq!(count_prefix_in(&mut cache, &text, 4), 2);
+    assert_eq!(cache.stats().hits, 1);
+}
+
+// ========== 比较两个缓存的条目 ==========
+
+#[test]
+fn test_instance_cache_eq() {
+    let mut first = FibCache::new();
+    let mut second = FibCache::new();
+    assert_eq!(first, second);
+
+    // 插入顺序和命中次数不同，条目相同即相等
+    fib_in(&mut first, 20);
+    for n in 0..=20 {
+        fib_in(&mut second, n);
+    }
+    assert_eq!(first, second);
+    assert_ne!(first.stats().hits, second.stats().hits);
+
+    fib_in(&mut second, 21);
+    assert_ne!(first, second);
+    second.invalidate(21);
+    assert_eq!(first, second);
+}
+
+// 值没有实现 PartialEq 时缓存类型照常可用，只是不能比较
+struct Opaque(u64);
+
+#[memo(scope = instance)]
+fn opaque(n: u64) -> std::rc::Rc<Opaque> {
+    std::rc::Rc::new(Opaque(n))
+}
+
+#[test]
+fn test_instance_cache_without_eq() {
+    let mut cache = OpaqueCache::new();
+    assert_eq!(opaque_in(&mut cache, 3).0, 3);
+    assert_eq!(cache.len(), 1);
+}
@@ -0,0 +1,170 @@
+use mau::memo;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== key_with：周期函数只按余数缓存 ==========
+
+static WEEKDAY_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn day_of_week(day: &u64) -> u64 {
+    day % 7
+}
+
+#[memo(key_with = day_of_week)]
+fn weekday(day: u64) -> &'static str {
+    WEEKDAY_CALLS.fetch_add(1, Ordering::SeqCst);
+    ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"][(day % 7) as usize]
+}
+
+#[test]
+fn test_key_with_period() {
+    assert_eq!(weekday(0), "Mon");
+    assert_eq!(weekday(7), "Mon");
+    assert_eq!(weekday(700), "Mon");
+    assert_eq!(weekday(3), "Thu");
+    assert_eq!(WEEKDAY_CALLS.load(Ordering::SeqCst), 2, "余数相同的调用共享一个条目");
+    assert_eq!(weekday_stats().len, 2);
+
+    weekday_clear();
+    assert_eq!(weekday_stats().len, 0);
+}
+
+// ========== key_with：引用参数按内容规范化 ==========
+
+mod keys {
+    pub fn lowercase(word: &str) -> String {
+        word.to_lowercase()
+    }
+}
+
+static SCORE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi, key_with = keys::lowercase)]
+fn score(word: &str) -> usize {
+    SCORE_CALLS.fetch_add(1, Ordering::SeqCst);
+    word.chars().filter(|c| "aeiou".contains(c.to_ascii_lowercase())).count()
+}
+
+#[test]
+fn test_key_with_reference() {
+    let owned = String::from("Hello");
+    assert_eq!(score(&owned), 2);
+    assert_eq!(score("hello"), 2);
+    assert_eq!(score("HELLO"), 2);
+    assert_eq!(SCORE_CALLS.load(Ordering::SeqCst), 1);
+}
+
+// ========== key_with + 泛型 ==========
+
+fn text_len<T: AsRef<str>>(text: &T) -> usize {
+    text.as_ref().len()
+}
+
+#[memo(key_with = text_len)]
+fn padding<T: AsRef<str>>(text: T) -> String {
+    " ".repeat(8usize.saturating_sub(text.as_ref().len()))
+}
+
+#[test]
+fn test_key_with_generic() {
+    assert_eq!(padding("abc"), "     ");
+    assert_eq!(padding(String::from("xyz")), "     ");
+    assert_eq!(padding("abcd"), "    ");
+    // &str 和 String 是不同的单态化，各自缓存
+    assert_eq!(padding_stats().len, 3);
+}
+
+// ========== symmetric：交换参数共享条目 ==========
+
+static GCD_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(symmetric(a, b))]
+fn gcd(a: u64, b: u64) -> u64 {
+    GCD_CALLS.fetch_add(1, Ordering::SeqCst);
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[test]
+fn test_symmetric() {
+    assert_eq!(gcd(12, 18), 6);
+    let calls = GCD_CALLS.load(Ordering::SeqCst);
+    assert_eq!(gcd(18, 12), 6);
+    assert_eq!(GCD_CALLS.load(Ordering::SeqCst), calls, "gcd(18, 12) 命中 gcd(12, 18) 的缓存");
+}
+
+static TRIANGLE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+// 三个参数任意排列都共享条目，scale 正常参与键
+#[memo(symmetric(a, b, c))]
+fn perimeter(a: u32, scale: u32, b: u32, c: u32) -> u32 {
+    TRIANGLE_CALLS.fetch_add(1, Ordering::SeqCst);
+    (a + b + c) * scale
+}
+
+#[test]
+fn test_symmetric_three_args() {
+    assert_eq!(perimeter(3, 2, 4, 5), 24);
+    assert_eq!(perimeter(5, 2, 3, 4), 24);
+    assert_eq!(perimeter(4, 2, 5, 3), 24);
+    assert_eq!(TRIANGLE_CALLS.load(Ordering::SeqCst), 1);
+    assert_eq!(perimeter(3, 1, 4, 5), 12);
+    assert_eq!(TRIANGLE_CALLS.load(Ordering::SeqCst), 2);
+}
+
+// ========== sorted：多重集合参数 ==========
+
+static SUM_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(sorted(items))]
+fn largest_pair(items: Vec<i32>) -> i32 {
+    SUM_CALLS.fetch_add(1, Ordering::SeqCst);
+    let mut items = items;
+    items.sort_unstable_by(|a, b| b.cmp(a));
+    items[0] + items[1]
+}
+
+#[memo(sorted(items), lifetime=program)]
+fn distinct(items: &[u8]) -> usize {
+    let mut items = items.to_vec();
+    items.dedup();
+    items.len()
+}
+
+#[test]
+fn test_sorted() {
+    assert_eq!(largest_pair(vec![3, 9, 1, 7]), 16);
+    assert_eq!(largest_pair(vec![7, 1, 9, 3]), 16);
+    assert_eq!(SUM_CALLS.load(Ordering::SeqCst), 1);
+
+    // 按排序后的内容作为键：键不含地址，lifetime=program 时 _start 保留缓存
+    assert_eq!(distinct_start(&[1, 1, 2]), 2);
+    assert_eq!(distinct_start(&[2, 1, 1]), 2);
+    assert_eq!(distinct_stats().len, 1);
+}
+
+// ========== 方法 ==========
+
+struct Board {
+    size: i64,
+}
+
+fn manhattan_key(dx: &i64, dy: &i64) -> (i64, i64) {
+    let (x, y) = (dx.abs(), dy.abs());
+    (x.min(y), x.max(y))
+}
+
+impl Board {
+    // 对称的走法：只与 |dx|、|dy| 的无序对有关
+    #[memo(receiver=ignore, key_with = manhattan_key)]
+    fn moves(&self, dx: i64, dy: i64) -> i64 {
+        (dx.abs() + dy.abs()).min(self.size)
+    }
+}
+
+#[test]
+fn test_key_with_method() {
+    let board = Board { size: 100 };
+    assert_eq!(board.moves(3, -4), 7);
+    assert_eq!(board.moves(-4, 3), 7);
+    assert_eq!(board.moves(4, 3), 7);
+    assert_eq!(board.moves(1, 1), 2);
+}
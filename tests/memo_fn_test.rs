@@ -0,0 +1,109 @@
+use mau::memo_fn;
+use std::cell::Cell;
+use std::rc::Rc;
+
+// ========== 按引用捕获局部变量 ==========
+
+#[test]
+fn test_memo_fn_captures_locals() {
+    let grid = [[1, 3, 1], [1, 5, 1], [4, 2, 1]];
+    let calls = Cell::new(0);
+    let min_path = memo_fn!(|rec, i: usize, j: usize| -> u64 {
+        calls.set(calls.get() + 1);
+        let here = grid[i][j];
+        match (i, j) {
+            (0, 0) => here,
+            (0, _) => here + rec(0, j - 1),
+            (_, 0) => here + rec(i - 1, 0),
+            _ => here + rec(i - 1, j).min(rec(i, j - 1)),
+        }
+    });
+
+    assert_eq!(min_path(2, 2), 7);
+    assert_eq!(calls.get(), 9, "每个格子只计算一次");
+    assert_eq!(min_path(1, 1), 7);
+    assert_eq!(calls.get(), 9);
+}
+
+// ========== 两个字符串的编辑距离 ==========
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let distance = memo_fn!(|rec, i: usize, j: usize| -> usize {
+        if i == 0 || j == 0 {
+            return i + j;
+        }
+        if a[i - 1] == b[j - 1] {
+            return rec(i - 1, j - 1);
+        }
+        1 + rec(i - 1, j).min(rec(i, j - 1)).min(rec(i - 1, j - 1))
+    });
+    distance(a.len(), b.len())
+}
+
+#[test]
+fn test_memo_fn_edit_distance() {
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("flaw", "lawn"), 2);
+}
+
+// ========== 每个闭包有自己的缓存，随闭包一起释放 ==========
+
+#[test]
+fn test_memo_fn_cache_per_instance() {
+    let token = Rc::new(String::from("cached"));
+    let first = memo_fn!(|_, n: u32| -> Rc<String> {
+        let _ = n;
+        Rc::clone(&token)
+    });
+    let second = memo_fn!(|_, n: u32| -> Rc<String> {
+        let _ = n;
+        Rc::clone(&token)
+    });
+
+    for n in 0..10 {
+        first(n);
+    }
+    second(0);
+    // token 本身 + first 缓存的 10 个 + second 缓存的 1 个
+    assert_eq!(Rc::strong_count(&token), 12);
+
+    drop(first);
+    assert_eq!(Rc::strong_count(&token), 2);
+    drop(second);
+    assert_eq!(Rc::strong_count(&token), 1);
+}
+
+// ========== move：返回按值捕获环境的闭包 ==========
+
+fn ways_mod(modulus: u64) -> impl Fn(u64) -> u64 {
+    memo_fn!(move |rec, n: u64| -> u64 {
+        if n < 2 { 1 } else { (rec(n - 1) + rec(n - 2)) % modulus }
+    })
+}
+
+#[test]
+fn test_memo_fn_move() {
+    let ways = ways_mod(1_000_000_007);
+    assert_eq!(ways(10), 89);
+    assert_eq!(ways(90), 4660046610375530309 % 1_000_000_007);
+    assert_eq!(ways_mod(10)(10), 9);
+}
+
+// ========== 泛型函数中使用 ==========
+
+// 从 i 开始的最长不下降子序列长度
+fn longest_run<T: PartialOrd>(items: &[T]) -> usize {
+    let from = memo_fn!(|rec, i: usize| -> usize {
+        1 + (i + 1..items.len()).filter(|&j| items[j] >= items[i]).map(rec).max().unwrap_or(0)
+    });
+    (0..items.len()).map(&from).max().unwrap_or(0)
+}
+
+#[test]
+fn test_memo_fn_in_generic_fn() {
+    assert_eq!(longest_run(&[3, 1, 4, 1, 5, 9, 2, 6]), 4);
+    assert_eq!(longest_run(&["b", "a", "c"]), 2);
+    assert_eq!(longest_run::<u8>(&[]), 0);
+}
@@ -0,0 +1,251 @@
+use mau::memo;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== &self 方法：默认 key=ptr，接收者地址参与键 ==========
+
+struct Grid {
+    cells: Vec<Vec<u64>>,
+    calls: Cell<usize>,
+}
+
+impl Grid {
+    fn new(cells: Vec<Vec<u64>>) -> Self {
+        Grid { cells, calls: Cell::new(0) }
+    }
+
+    // 从左上角到 (r, c) 的最小路径和
+    #[memo]
+    fn min_path(&self, r: usize, c: usize) -> u64 {
+        self.calls.set(self.calls.get() + 1);
+        let here = self.cells[r][c];
+        match (r, c) {
+            (0, 0) => here,
+            (0, _) => here + self.min_path(0, c - 1),
+            (_, 0) => here + self.min_path(r - 1, 0),
+            _ => here + self.min_path(r - 1, c).min(self.min_path(r, c - 1)),
+        }
+    }
+}
+
+#[test]
+fn test_method_ptr_receiver() {
+    let grid = Grid::new(vec![vec![1, 3, 1], vec![1, 5, 1], vec![4, 2, 1]]);
+    assert_eq!(grid.min_path(2, 2), 7);
+    assert_eq!(grid.calls.get(), 9, "每个格子只计算一次");
+
+    // 不同实例的地址不同，不共享缓存
+    let other = Grid::new(vec![vec![1, 1], vec![1, 1]]);
+    assert_eq!(other.min_path(1, 1), 3);
+
+    // _start 清除缓存（键包含地址）
+    Grid::min_path_clear();
+    assert_eq!(grid.min_path_start(2, 2), 7);
+    assert_eq!(grid.calls.get(), 18);
+    assert_eq!(grid.min_path_start(2, 2), 7);
+    assert_eq!(grid.calls.get(), 27, "_start 之后缓存已清除");
+}
+
+// ========== key=val：接收者按内容参与键 ==========
+
+static PRICE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Pricing {
+    base: u64,
+}
+
+impl Pricing {
+    #[memo(key=val, lifetime=program)]
+    pub fn price(&self, qty: u64) -> u64 {
+        PRICE_CALLS.fetch_add(1, Ordering::SeqCst);
+        self.base * qty
+    }
+}
+
+#[test]
+fn test_method_val_receiver() {
+    let a = Pricing { base: 3 };
+    let b = Pricing { base: 3 };
+    assert_eq!(a.price(10), 30);
+    assert_eq!(b.price(10), 30, "内容相同的不同实例共享缓存");
+    assert_eq!(PRICE_CALLS.load(Ordering::SeqCst), 1);
+
+    // lifetime=program + key=val：_start 保留缓存
+    assert_eq!(a.price_start(10), 30);
+    assert_eq!(PRICE_CALLS.load(Ordering::SeqCst), 1);
+
+    assert_eq!(Pricing { base: 4 }.price(10), 40);
+    assert_eq!(PRICE_CALLS.load(Ordering::SeqCst), 2);
+}
+
+// ========== key=ref：先比地址，再比内容 ==========
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Word(String);
+
+impl Word {
+    #[memo(key=ref)]
+    fn repeat(&self, times: usize) -> String {
+        self.0.repeat(times)
+    }
+}
+
+#[test]
+fn test_method_ref_receiver() {
+    let w = Word("ab".to_string());
+    assert_eq!(w.repeat(3), "ababab");
+    assert_eq!(Word("ab".to_string()).repeat(3), "ababab");
+    assert_eq!(w.repeat_start(2), "abab");
+}
+
+// ========== receiver=ignore：接收者不参与键，允许 &mut self ==========
+
+struct Counter {
+    hits: usize,
+}
+
+impl Counter {
+    #[memo(receiver=ignore)]
+    fn fib(&mut self, n: u64) -> u64 {
+        self.hits += 1;
+        if n < 2 { n } else { self.fib(n - 1) + self.fib(n - 2) }
+    }
+}
+
+#[test]
+fn test_method_ignore_receiver() {
+    let mut counter = Counter { hits: 0 };
+    assert_eq!(counter.fib(30), 832040);
+    assert_eq!(counter.hits, 31);
+
+    // 另一个实例共享缓存
+    let mut other = Counter { hits: 0 };
+    assert_eq!(other.fib(30), 832040);
+    assert_eq!(other.hits, 0);
+
+    Counter::fib_clear();
+    assert_eq!(other.fib(5), 5);
+    assert_eq!(other.hits, 6);
+}
+
+// ========== 按值接收者 self ==========
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+impl Point {
+    #[memo]
+    fn manhattan(self, other: Point) -> i64 {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+}
+
+#[test]
+fn test_method_by_value_receiver() {
+    let p = Point { x: 1, y: 2 };
+    assert_eq!(p.manhattan(Point { x: 4, y: -2 }), 7);
+    assert_eq!(p.manhattan_start(Point { x: 1, y: 2 }), 0);
+}
+
+// ========== trait impl：helpers=none ==========
+
+trait Solver {
+    fn ways(&self, n: u64) -> u64;
+}
+
+struct Stairs {
+    steps: Vec<u64>,
+}
+
+impl Solver for Stairs {
+    #[memo(helpers=none)]
+    fn ways(&self, n: u64) -> u64 {
+        if n == 0 {
+            return 1;
+        }
+        self.steps.iter().filter(|&&s| s <= n).map(|&s| self.ways(n - s)).sum()
+    }
+}
+
+#[test]
+fn test_trait_impl_method() {
+    let stairs = Stairs { steps: vec![1, 2] };
+    assert_eq!(stairs.ways(50), 20365011074);
+    let dyn_solver: &dyn Solver = &Stairs { steps: vec![1, 2, 3] };
+    assert_eq!(dyn_solver.ways(10), 274);
+}
+
+// ========== multi 模式 ==========
+
+static MULTI_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Modulus(u64);
+
+impl Modulus {
+    #[memo(thread=multi, key=val)]
+    fn pow(&self, base: u64, exp: u64) -> u64 {
+        MULTI_CALLS.fetch_add(1, Ordering::SeqCst);
+        (0..exp).fold(1, |acc, _| acc * base % self.0)
+    }
+}
+
+#[test]
+fn test_method_multi_mode() {
+    let handles: Vec<_> = (0..4)
+        .map(|_| std::thread::spawn(|| Modulus(1_000_007).pow(3, 100)))
+        .collect();
+    let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert!(results.windows(2).all(|w| w[0] == w[1]));
+
+    let before = MULTI_CALLS.load(Ordering::SeqCst);
+    Modulus(1_000_007).pow(3, 100);
+    assert_eq!(MULTI_CALLS.load(Ordering::SeqCst), before, "多线程共享缓存");
+
+    Modulus::pow_clear();
+    Modulus(1_000_007).pow(3, 100);
+    assert_eq!(MULTI_CALLS.load(Ordering::SeqCst), before + 1);
+}
+
+// ========== 单个表达式的函数体：不产生 unused_braces 警告 ==========
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Scale(u64);
+
+#[deny(unused_braces)]
+impl Scale {
+    #[memo(key=val)]
+    fn apply(&self, n: u64) -> u64 { self.0 * n }
+
+    #[memo(key=val)]
+    fn unit(&self) -> u64 { 1 }
+}
+
+#[test]
+fn test_method_expression_body() {
+    let scale = Scale(3);
+    assert_eq!(scale.apply(5), 15);
+    assert_eq!(scale.unit(), 1);
+}
+
+// ========== 泛型 impl：每个 Self 类型是独立的缓存 ==========
+
+struct Sized_<T>(std::marker::PhantomData<T>);
+
+impl<T> Sized_<T> {
+    #[memo(receiver=ignore)]
+    fn size(&self, n: usize) -> usize {
+        std::mem::size_of::<T>() * n
+    }
+}
+
+#[test]
+fn test_method_generic_impl() {
+    assert_eq!(Sized_::<u8>(std::marker::PhantomData).size(3), 3);
+    assert_eq!(Sized_::<u64>(std::marker::PhantomData).size(3), 24);
+    assert_eq!(Sized_::<u8>(std::marker::PhantomData).size(3), 3);
+}
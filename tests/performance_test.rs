@@ -41,6 +41,16 @@ fn sum_multi(data: &[i32]) -> i32 {
     data.iter().sum()
 }
 
+#[memo(thread=multi)]
+fn square_mutex(n: u64) -> u64 {
+    n * n
+}
+
+#[memo(thread=multi, shards=64)]
+fn square_sharded(n: u64) -> u64 {
+    n * n
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,4 +289,48 @@ mod tests {
         
         println!("并发访问模拟测试 ({} 次调用): {:?}", iterations, mixed_time);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_multi_thread_scaling() {
+        // 读多写少：预热后所有调用都命中缓存，比较单个 Mutex 与 64 个 RwLock 分片
+        let keys = 1024u64;
+        let calls_per_thread = 200_000u64;
+        for n in 0..keys {
+            square_mutex(n);
+            square_sharded(n);
+        }
+
+        fn run(threads: u64, calls: u64, keys: u64, f: fn(u64) -> u64) -> std::time::Duration {
+            let start = Instant::now();
+            let handles: Vec<_> = (0..threads)
+                .map(|t| {
+                    std::thread::spawn(move || {
+                        for i in 0..calls {
+                            let n = (i * 7 + t * 131) % keys;
+                            // 每个线程拿到的结果都必须正确
+                            assert_eq!(f(n), n * n);
+                        }
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            start.elapsed()
+        }
+
+        // 耗时只作为基准数据输出，不做断言（与机器负载相关）
+        println!("多线程扩展性测试（每个线程 {} 次命中）:", calls_per_thread);
+        for threads in [1, 2, 4, 8] {
+            let mutex_time = run(threads, calls_per_thread, keys, square_mutex);
+            let sharded_time = run(threads, calls_per_thread, keys, square_sharded);
+            println!("  {} 线程: Mutex {:?}, shards=64 {:?}", threads, mutex_time, sharded_time);
+        }
+
+        // 验证结果一致性
+        assert_eq!(square_mutex(keys - 1), square_sharded(keys - 1));
+        // 分片缓存的条目数等于不同键的个数：并发命中不会重复插入
+        assert_eq!(square_sharded_stats().len, keys as usize);
+        assert_eq!(square_sharded_stats().misses, keys);
+    }
+}
@@ -0,0 +1,290 @@
+use mau::memo;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// single 模式的缓存是线程局部的：新线程第一次调用时从文件加载，相当于一次新的进程运行
+fn in_new_thread<T: Send + 'static>(f: fn() -> T) -> T {
+    thread::spawn(f).join().unwrap()
+}
+
+fn fresh(path: &str) {
+    let _ = std::fs::remove_file(path);
+}
+
+// ========== 基本用法：_clear 时写回，下一次运行从文件加载 ==========
+
+const FIB_PATH: &str = "target/mau_persist_test/fib.bin";
+static FIB_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(lifetime=program, persist="target/mau_persist_test/fib.bin")]
+fn fib(n: u64) -> u64 {
+    FIB_CALLS.fetch_add(1, Ordering::SeqCst);
+    if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+}
+
+#[test]
+fn test_persist_reload() {
+    fresh(FIB_PATH);
+    in_new_thread(|| {
+        assert_eq!(fib(60), 1548008755920);
+        fib_clear();
+    });
+    assert_eq!(FIB_CALLS.load(Ordering::SeqCst), 61);
+
+    let bytes = std::fs::read(FIB_PATH).unwrap();
+    assert_eq!(&bytes[..4], b"MAUC");
+    assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 1, "格式版本");
+
+    in_new_thread(|| {
+        assert_eq!(fib(60), 1548008755920);
+        let stats = fib_stats();
+        assert_eq!(stats.len, 61);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 0);
+    });
+    assert_eq!(FIB_CALLS.load(Ordering::SeqCst), 61, "结果来自文件，没有重新计算");
+}
+
+// ========== 线程退出时写回；_clear 之后写回与文件合并 ==========
+
+const WORDS_PATH: &str = "target/mau_persist_test/words.bin";
+static WORDS_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(lifetime=program, persist="target/mau_persist_test/words.bin")]
+fn split_words(text: String, min_len: usize) -> Vec<String> {
+    WORDS_CALLS.fetch_add(1, Ordering::SeqCst);
+    text.split_whitespace().filter(|word| word.len() >= min_len).map(String::from).collect()
+}
+
+#[test]
+fn test_persist_thread_exit_and_merge() {
+    fresh(WORDS_PATH);
+    in_new_thread(|| {
+        assert_eq!(split_words(String::from("the quick brown fox"), 4), vec!["quick", "brown"]);
+        split_words_clear();
+        // 清空内存之后的新结果写回时与文件中已有的结果合并
+        assert_eq!(split_words(String::from("jumps over the lazy dog"), 4), vec!["jumps", "over", "lazy"]);
+    });
+    assert_eq!(WORDS_CALLS.load(Ordering::SeqCst), 2);
+
+    in_new_thread(|| {
+        assert_eq!(split_words(String::from("the quick brown fox"), 4), vec!["quick", "brown"]);
+        assert_eq!(split_words(String::from("jumps over the lazy dog"), 4), vec!["jumps", "over", "lazy"]);
+        assert_eq!(split_words_stats().len, 2);
+    });
+    assert_eq!(WORDS_CALLS.load(Ordering::SeqCst), 2);
+}
+
+// ========== 多个线程同时写回：临时文件互不覆盖 ==========
+
+const CUBE_PATH: &str = "target/mau_persist_test/cube.bin";
+
+#[memo(lifetime=program, persist="target/mau_persist_test/cube.bin")]
+fn cube(n: u64) -> u64 {
+    n * n * n
+}
+
+#[test]
+fn test_persist_concurrent_save() {
+    fresh(CUBE_PATH);
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            thread::spawn(|| {
+                for round in 0..20 {
+                    for n in 0..50 {
+                        assert_eq!(cube(n), n * n * n);
+                    }
+                    if round % 2 == 0 {
+                        cube_clear();
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // 文件完整可读，且没有遗留的临时文件
+    in_new_thread(|| {
+        assert_eq!(cube(7), 343);
+        assert_eq!(cube_stats().len, 50);
+    });
+    let leftovers = std::fs::read_dir("target/mau_persist_test")
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("cube.bin.") && name.ends_with(".tmp"))
+        .count();
+    assert_eq!(leftovers, 0);
+}
+
+// ========== 函数签名改变：旧文件被丢弃 ==========
+
+const SCHEMA_PATH: &str = "target/mau_persist_test/schema.bin";
+static SCHEMA_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(lifetime=program, persist="target/mau_persist_test/schema.bin")]
+fn cube_v1(n: u32) -> u32 {
+    SCHEMA_CALLS.fetch_add(1, Ordering::SeqCst);
+    n * n * n
+}
+
+// 同一个文件，返回类型不同
+#[memo(lifetime=program, persist="target/mau_persist_test/schema.bin")]
+fn cube_v2(n: u32) -> u64 {
+    SCHEMA_CALLS.fetch_add(1, Ordering::SeqCst);
+    (n as u64).pow(3)
+}
+
+#[test]
+fn test_persist_schema_mismatch() {
+    fresh(SCHEMA_PATH);
+    in_new_thread(|| {
+        assert_eq!(cube_v1(7), 343);
+        cube_v1_clear();
+    });
+    in_new_thread(|| {
+        assert_eq!(cube_v2_stats().len, 0, "模式键不同，不加载旧文件");
+        assert_eq!(cube_v2(7), 343);
+    });
+    assert_eq!(SCHEMA_CALLS.load(Ordering::SeqCst), 2);
+
+    // 文件已被新版本覆盖
+    in_new_thread(|| {
+        assert_eq!(cube_v2(7), 343);
+    });
+    assert_eq!(SCHEMA_CALLS.load(Ordering::SeqCst), 2);
+}
+
+// ========== 损坏的文件：从空缓存开始，之后正常写回 ==========
+
+const CORRUPT_PATH: &str = "target/mau_persist_test/corrupt.bin";
+static CORRUPT_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(lifetime=program, persist="target/mau_persist_test/corrupt.bin")]
+fn digits(n: u64) -> Option<Vec<u8>> {
+    CORRUPT_CALLS.fetch_add(1, Ordering::SeqCst);
+    (n > 0).then(|| n.to_string().bytes().map(|b| b - b'0').collect())
+}
+
+#[test]
+fn test_persist_corrupt_file() {
+    std::fs::create_dir_all("target/mau_persist_test").unwrap();
+    std::fs::write(CORRUPT_PATH, b"MAUC\x01\x00\x00\x00garbage").unwrap();
+    in_new_thread(|| {
+        assert_eq!(digits(907), Some(vec![9, 0, 7]));
+        assert_eq!(digits(0), None);
+    });
+    in_new_thread(|| {
+        assert_eq!(digits(907), Some(vec![9, 0, 7]));
+        assert_eq!(digits(0), None);
+    });
+    assert_eq!(CORRUPT_CALLS.load(Ordering::SeqCst), 2);
+}
+
+// ========== 自定义类型实现 MemoCodec{Fn} ==========
+
+#[derive(Debug, Clone, PartialEq)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl MemoCodecMidpoint for Point {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // 同一作用域中可能有多个 MemoCodec{Fn}，使用完全限定的调用
+        MemoCodecMidpoint::encode(&self.x, out);
+        MemoCodecMidpoint::encode(&self.y, out);
+    }
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Point {
+            x: <i32 as MemoCodecMidpoint>::decode(input)?,
+            y: <i32 as MemoCodecMidpoint>::decode(input)?,
+        })
+    }
+}
+
+const MIDPOINT_PATH: &str = "target/mau_persist_test/midpoint.bin";
+static MIDPOINT_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(lifetime=program, share, persist="target/mau_persist_test/midpoint.bin")]
+fn midpoint(a: (i32, i32), b: (i32, i32)) -> Point {
+    MIDPOINT_CALLS.fetch_add(1, Ordering::SeqCst);
+    Point { x: (a.0 + b.0) / 2, y: (a.1 + b.1) / 2 }
+}
+
+#[test]
+fn test_persist_custom_codec() {
+    fresh(MIDPOINT_PATH);
+    in_new_thread(|| {
+        assert_eq!(*midpoint((0, 0), (4, -6)), Point { x: 2, y: -3 });
+    });
+    in_new_thread(|| {
+        assert_eq!(*midpoint((0, 0), (4, -6)), Point { x: 2, y: -3 });
+    });
+    assert_eq!(MIDPOINT_CALLS.load(Ordering::SeqCst), 1);
+}
+
+// ========== multi 模式 + persist_interval_ms：运行期间定期写回 ==========
+
+const COLLATZ_PATH: &str = "target/mau_persist_test/collatz.bin";
+
+#[memo(thread=multi, lifetime=program, capacity=100, persist="target/mau_persist_test/collatz.bin", persist_interval_ms=1)]
+fn collatz(n: u64) -> u32 {
+    if n == 1 { 0 } else if n.is_multiple_of(2) { 1 + collatz(n / 2) } else { 1 + collatz(3 * n + 1) }
+}
+
+#[test]
+fn test_persist_interval() {
+    fresh(COLLATZ_PATH);
+    assert_eq!(collatz(27), 111);
+    thread::sleep(std::time::Duration::from_millis(5));
+    assert_eq!(collatz(97), 118);
+
+    // 距离上次写回超过 1 毫秒的插入已经写回文件，条目数不超过 capacity
+    let bytes = std::fs::read(COLLATZ_PATH).unwrap();
+    let count = u64::from_le_bytes(bytes[16..24].try_into().unwrap());
+    assert!(count > 0 && count <= 100, "文件中有 {} 个条目", count);
+}
+
+// ========== _invalidate / _retain：删除的条目不会从文件中恢复 ==========
+
+const SQ_PATH: &str = "target/mau_persist_test/sq.bin";
+
+#[memo(lifetime=program, persist="target/mau_persist_test/sq.bin")]
+fn sq(n: u64) -> u64 {
+    n * n
+}
+
+#[test]
+fn test_persist_removals() {
+    fresh(SQ_PATH);
+    in_new_thread(|| {
+        for n in 0..6 {
+            sq(n);
+        }
+        sq_clear();
+    });
+
+    in_new_thread(|| {
+        assert_eq!(sq_cached(3), Some(9));
+        sq_invalidate(3);
+        sq_clear();
+    });
+    in_new_thread(|| {
+        assert_eq!(sq_cached(3), None);
+        assert_eq!(sq_stats().len, 5);
+        // 文件中已不在内存里的条目同样按回调删除
+        sq_clear();
+        sq_retain(|&n, _| n % 2 == 0);
+        sq_insert(7, 49);
+    });
+    in_new_thread(|| {
+        assert_eq!(sq_snapshot(), vec![(0, 0), (2, 4), (4, 16), (7, 49)]);
+        sq_retain(|_, _| false);
+    });
+    in_new_thread(|| {
+        assert_eq!(sq_stats().len, 0);
+    });
+}
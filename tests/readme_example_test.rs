@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison)]
+
 use mau::{memo, min, max, sum, and, or};
 
 // 使用 memo 宏优化递归函数
@@ -1,3 +1,5 @@
+#![allow(clippy::useless_vec)]
+
 use mau::{min, max, sum};
 
 #[test]
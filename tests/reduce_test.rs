@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison, clippy::reversed_empty_ranges, clippy::useless_vec)]
+
 use mau::reduce;
 
 #[test]
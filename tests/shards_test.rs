@@ -0,0 +1,94 @@
+use mau::{memo, solve};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ========== 基本用法：多线程共享分片缓存 ==========
+
+static COLLATZ_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi, shards=16)]
+fn collatz(n: u64) -> u32 {
+    COLLATZ_CALLS.fetch_add(1, Ordering::SeqCst);
+    if n == 1 {
+        0
+    } else if n.is_multiple_of(2) {
+        1 + collatz(n / 2)
+    } else {
+        1 + collatz(3 * n + 1)
+    }
+}
+
+#[test]
+fn test_shards_basic() {
+    let handles: Vec<_> = (0..8)
+        .map(|t| std::thread::spawn(move || (1..=2000u64).filter(|n| n % 8 == t).map(collatz).max().unwrap()))
+        .collect();
+    let longest = handles.into_iter().map(|handle| handle.join().unwrap()).max().unwrap();
+    assert_eq!(longest, 181);
+
+    // 所有线程的结果都在同一个缓存中
+    let calls = COLLATZ_CALLS.load(Ordering::SeqCst);
+    assert_eq!((1..=2000u64).map(collatz).max(), Some(181));
+    assert_eq!(COLLATZ_CALLS.load(Ordering::SeqCst), calls);
+
+    let stats = collatz_stats();
+    assert!(stats.len > 2000);
+    assert!(stats.hits >= 2000);
+
+    collatz_clear();
+    let stats = collatz_stats();
+    assert_eq!(stats.len, 0);
+    assert_eq!(stats.manual_clears, 1, "所有分片一起清除只记一次");
+    assert_eq!(stats.clears, 0);
+}
+
+// ========== _start / solve! 清除所有分片 ==========
+
+#[memo(thread=multi, shards=4)]
+fn grid(x: u32, y: u32) -> u64 {
+    if x == 0 || y == 0 { 1 } else { grid(x - 1, y) + grid(x, y - 1) }
+}
+
+#[test]
+fn test_shards_start() {
+    assert_eq!(solve!(grid(16, 16)), 601080390);
+    assert_eq!(grid_stats().len, 0);
+    assert_eq!(grid(3, 3), 20);
+    assert_eq!(grid_stats().len, 15);
+}
+
+// ========== 容量上限平均分给每个分片 ==========
+
+#[memo(thread=multi, shards=4, capacity=64, evict=lru, hasher=fx)]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+#[test]
+fn test_shards_capacity() {
+    for n in 0..1000 {
+        assert_eq!(square(n), n * n);
+    }
+    let stats = square_stats();
+    assert!(stats.len <= 64, "每个分片最多 16 个条目，实际 {}", stats.len);
+    assert_eq!(stats.inserts, 1000);
+}
+
+// ========== ttl 与 cache_if ==========
+
+static PARSE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi, shards=8, lifetime=program, ttl_ms=60000, cache_if=ok)]
+fn parse(text: String) -> Result<i64, String> {
+    PARSE_CALLS.fetch_add(1, Ordering::SeqCst);
+    text.parse().map_err(|_| text)
+}
+
+#[test]
+fn test_shards_ttl_cache_if() {
+    assert_eq!(parse("42".to_string()), Ok(42));
+    assert_eq!(parse("42".to_string()), Ok(42));
+    assert!(parse("x".to_string()).is_err());
+    assert!(parse("x".to_string()).is_err());
+    assert_eq!(PARSE_CALLS.load(Ordering::SeqCst), 3);
+    assert_eq!(parse_stats().len, 1);
+}
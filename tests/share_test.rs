@@ -0,0 +1,107 @@
+use mau::{memo, solve};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// ========== single 模式：Rc<T>，返回值不需要实现 Clone ==========
+
+// 故意不实现 Clone
+#[derive(Debug, PartialEq)]
+struct Row {
+    values: Vec<u64>,
+}
+
+static ROW_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(share)]
+fn pascal_row(n: usize) -> Row {
+    ROW_CALLS.fetch_add(1, Ordering::SeqCst);
+    if n == 0 {
+        return Row { values: vec![1] };
+    }
+    let prev = pascal_row(n - 1);
+    let mut values = vec![1];
+    values.extend(prev.values.windows(2).map(|pair| pair[0] + pair[1]));
+    values.push(1);
+    Row { values }
+}
+
+#[test]
+fn test_share_rc() {
+    let row: Rc<Row> = pascal_row(5);
+    assert_eq!(row.values, vec![1, 5, 10, 10, 5, 1]);
+    assert_eq!(ROW_CALLS.load(Ordering::SeqCst), 6);
+
+    // 命中时返回同一个分配，而不是克隆
+    let again = pascal_row(5);
+    assert!(Rc::ptr_eq(&row, &again));
+    assert_eq!(ROW_CALLS.load(Ordering::SeqCst), 6);
+    assert_eq!(pascal_row_stats().hits, 1);
+}
+
+#[test]
+fn test_share_with() {
+    let sum = pascal_row_with(10, |row| row.values.iter().sum::<u64>());
+    assert_eq!(sum, 1024);
+    let middle = pascal_row_with(10, |row| row.values[5]);
+    assert_eq!(middle, 252);
+}
+
+// ========== multi 模式：Arc<T>，线程之间共享同一个值 ==========
+
+#[memo(thread=multi, share)]
+fn primes_below(n: u64) -> Vec<u64> {
+    (2..n).filter(|&k| (2..k).take_while(|d| d * d <= k).all(|d| !k.is_multiple_of(d))).collect()
+}
+
+#[test]
+fn test_share_arc() {
+    let first: Arc<Vec<u64>> = primes_below(1000);
+    let handles: Vec<_> = (0..4).map(|_| std::thread::spawn(|| primes_below(1000))).collect();
+    for handle in handles {
+        assert!(Arc::ptr_eq(&first, &handle.join().unwrap()));
+    }
+    assert_eq!(first.len(), 168);
+    assert_eq!(primes_below_with(1000, |primes| primes.last().copied()), Some(997));
+}
+
+// ========== 与 _start / solve!、cache_if 组合 ==========
+
+#[memo(share, cache_if=some)]
+fn divisors(n: u32) -> Option<Vec<u32>> {
+    if n == 0 {
+        return None;
+    }
+    Some((1..=n).filter(|d| n.is_multiple_of(*d)).collect())
+}
+
+#[test]
+fn test_share_start_and_cache_if() {
+    assert_eq!(solve!(divisors(12)).as_deref(), Some(&[1, 2, 3, 4, 6, 12][..]));
+    assert_eq!(divisors_stats().len, 0);
+    assert!(divisors(0).is_none());
+    assert_eq!(divisors_stats().len, 0, "None 没有写入缓存");
+}
+
+// ========== 方法 ==========
+
+struct Library {
+    books: Vec<&'static str>,
+}
+
+impl Library {
+    #[memo(share, receiver=ignore)]
+    fn titles(&self, prefix: char) -> Vec<String> {
+        self.books.iter().filter(|book| book.starts_with(prefix)).map(|book| book.to_string()).collect()
+    }
+}
+
+#[test]
+fn test_share_method() {
+    let library = Library { books: vec!["Rust", "Ruby", "Go", "Racket"] };
+    let titles = library.titles('R');
+    assert_eq!(*titles, vec!["Rust", "Ruby", "Racket"]);
+    assert!(Rc::ptr_eq(&titles, &library.titles('R')));
+    assert_eq!(library.titles_with('R', |titles| titles.len()), 3);
+    assert_eq!(library.titles_start('G').len(), 1);
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison, clippy::useless_vec)]
+
 use mau::{and, or};
 
 // 简单的计数器
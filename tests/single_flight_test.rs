@@ -0,0 +1,155 @@
+use mau::memo;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Duration;
+
+// 同时启动 n 个线程执行 f，返回每个线程的结果
+fn race<T: Send + 'static>(n: usize, f: fn() -> T) -> Vec<thread::Result<T>> {
+    let barrier = Arc::new(Barrier::new(n));
+    let handles: Vec<_> = (0..n)
+        .map(|_| {
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                f()
+            })
+        })
+        .collect();
+    handles.into_iter().map(|handle| handle.join()).collect()
+}
+
+// ========== 并发未命中只计算一次 ==========
+
+static SLOW_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi)]
+fn slow_square(n: u64) -> u64 {
+    SLOW_CALLS.fetch_add(1, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(100));
+    n * n
+}
+
+#[test]
+fn test_single_flight() {
+    let results = race(8, || slow_square(12));
+    for result in results {
+        assert_eq!(result.unwrap(), 144);
+    }
+    assert_eq!(SLOW_CALLS.load(Ordering::SeqCst), 1, "其他线程等待第一个线程的结果");
+
+    let stats = slow_square_stats();
+    assert_eq!(stats.inserts, 1);
+    assert_eq!(stats.len, 1);
+}
+
+// ========== 分片缓存 ==========
+
+static SHARDED_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi, shards=8)]
+fn slow_cube(n: u64) -> u64 {
+    SHARDED_CALLS.fetch_add(1, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(100));
+    n * n * n
+}
+
+#[test]
+fn test_single_flight_sharded() {
+    for result in race(6, || slow_cube(3)) {
+        assert_eq!(result.unwrap(), 27);
+    }
+    assert_eq!(SHARDED_CALLS.load(Ordering::SeqCst), 1);
+    assert_eq!(slow_cube_stats().misses, 6);
+}
+
+// ========== 计算线程 panic：等待者以相同的消息 panic，之后可以重试 ==========
+
+static FLAKY_CALLS: AtomicUsize = AtomicUsize::new(0);
+static FLAKY_FAIL: AtomicBool = AtomicBool::new(true);
+
+#[memo(thread=multi)]
+fn flaky(n: u64) -> u64 {
+    FLAKY_CALLS.fetch_add(1, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(100));
+    if FLAKY_FAIL.load(Ordering::SeqCst) {
+        panic!("flaky({}) 失败", n);
+    }
+    n + 1
+}
+
+#[test]
+fn test_single_flight_panic() {
+    let results = race(5, || flaky(7));
+    assert_eq!(FLAKY_CALLS.load(Ordering::SeqCst), 1);
+    for result in results {
+        let payload = result.unwrap_err();
+        let message = payload
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap();
+        assert_eq!(message, "flaky(7) 失败");
+    }
+
+    // panic 的结果没有缓存，登记也已移除：下一次调用重新计算
+    FLAKY_FAIL.store(false, Ordering::SeqCst);
+    assert_eq!(flaky(7), 8);
+    assert_eq!(FLAKY_CALLS.load(Ordering::SeqCst), 2);
+}
+
+// ========== 同一线程对同一个键重入：直接计算，不等待自己 ==========
+
+fn parity(n: &u64) -> u64 {
+    n % 2
+}
+
+#[memo(thread=multi, key_with = parity)]
+fn countdown(n: u64) -> u64 {
+    if n < 2 { n } else { countdown(n - 2) }
+}
+
+#[test]
+fn test_single_flight_reentrant() {
+    assert_eq!(countdown(9), 1);
+    assert_eq!(countdown(10), 0);
+    assert_eq!(countdown(3), 1);
+}
+
+// ========== 泛型函数和方法 ==========
+
+static GENERIC_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi, key=val)]
+fn slow_clone<T: Clone + Hash + Eq>(value: T) -> T {
+    GENERIC_CALLS.fetch_add(1, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(100));
+    value
+}
+
+struct Oracle;
+
+static ORACLE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+impl Oracle {
+    #[memo(thread=multi, receiver=ignore)]
+    fn answer(&self, question: u32) -> u32 {
+        ORACLE_CALLS.fetch_add(1, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(100));
+        question * 2
+    }
+}
+
+#[test]
+fn test_single_flight_generic_and_method() {
+    for result in race(4, || slow_clone("same")) {
+        assert_eq!(result.unwrap(), "same");
+    }
+    assert_eq!(GENERIC_CALLS.load(Ordering::SeqCst), 1);
+
+    for result in race(4, || Oracle.answer(21)) {
+        assert_eq!(result.unwrap(), 42);
+    }
+    assert_eq!(ORACLE_CALLS.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,148 @@
+use mau::memo;
+
+fn export(write: impl FnOnce(&mut Vec<u8>) -> std::io::Result<()>) -> String {
+    let mut out = Vec::new();
+    write(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+// ========== 快照：整数键按键排序 ==========
+
+#[memo]
+fn fib(n: u64) -> u64 {
+    if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+}
+
+#[test]
+fn test_snapshot_sorted() {
+    fib(10);
+    let snapshot: Vec<(u64, u64)> = fib_snapshot();
+    assert_eq!(snapshot, vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 3), (5, 5), (6, 8), (7, 13), (8, 21), (9, 34), (10, 55)]);
+
+    fib_clear();
+    fib(3);
+    assert_eq!(
+        export(|out| fib_export_json(out)),
+        "[\n  {\"key\": 0, \"value\": 0},\n  {\"key\": 1, \"value\": 1},\n  {\"key\": 2, \"value\": 1},\n  {\"key\": 3, \"value\": 2}\n]\n"
+    );
+    assert_eq!(export(|out| fib_export_csv(out)), "n,value\n0,0\n1,1\n2,1\n3,2\n");
+
+    fib_clear();
+    assert_eq!(export(|out| fib_export_json(out)), "[]\n");
+}
+
+// ========== 两个整数参数：二维表格 ==========
+
+#[memo]
+fn grid(i: u32, j: u32) -> u64 {
+    if i == 0 || j == 0 { 1 } else { grid(i - 1, j) + grid(i, j - 1) }
+}
+
+#[test]
+fn test_snapshot_grid() {
+    assert_eq!(grid(2, 2), 6);
+    let expected = "\
+i\\j | 0 1 2
+----+------
+  0 | . 1 1
+  1 | 1 2 3
+  2 | 1 3 6
+";
+    assert_eq!(export(|out| grid_print_grid(out)), expected);
+
+    let json = export(|out| grid_export_json(out));
+    assert!(json.starts_with("[\n  {\"key\": [0, 1], \"value\": 1},\n"));
+    assert!(json.ends_with("  {\"key\": [2, 2], \"value\": 6}\n]\n"));
+    let csv = export(|out| grid_export_csv(out));
+    assert_eq!(csv.lines().next(), Some("i,j,value"));
+    assert_eq!(csv.lines().last(), Some("2,2,6"));
+    assert_eq!(csv.lines().count(), 1 + 8);
+}
+
+// ========== 字符串：JSON 和 CSV 的转义 ==========
+
+#[memo]
+fn label(id: u32) -> Option<String> {
+    (id > 0).then(|| format!("a,\"{}\"", id))
+}
+
+#[test]
+fn test_snapshot_escaping() {
+    label(0);
+    label(7);
+    assert_eq!(
+        export(|out| label_export_json(out)),
+        "[\n  {\"key\": 0, \"value\": \"None\"},\n  {\"key\": 7, \"value\": \"Some(\\\"a,\\\\\\\"7\\\\\\\"\\\")\"}\n]\n"
+    );
+    assert_eq!(export(|out| label_export_csv(out)), "id,value\n0,None\n7,\"Some(\"\"a,\\\"\"7\\\"\"\"\")\"\n");
+}
+
+// ========== 键没有实现 Debug：快照照常可用 ==========
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Tile(u8);
+
+#[memo]
+fn weight(tile: Tile) -> u32 {
+    tile.0 as u32 * 10
+}
+
+#[test]
+fn test_snapshot_without_debug() {
+    weight(Tile(4));
+    let snapshot = weight_snapshot();
+    assert_eq!(snapshot.len(), 1);
+    assert!(snapshot[0].0 == Tile(4));
+    assert_eq!(snapshot[0].1, 40);
+}
+
+// ========== dense：由下标还原键，包括超出范围的条目 ==========
+
+#[memo(dense(i: 0..3, j: -2..=2))]
+fn offset(i: i32, j: i32) -> i32 {
+    i * 10 + j
+}
+
+#[test]
+fn test_snapshot_dense() {
+    offset(2, -2);
+    offset(0, 2);
+    offset(1, 0);
+    offset(5, 5);
+    assert_eq!(offset_snapshot(), vec![((0, 2), 2), ((1, 0), 10), ((2, -2), 18), ((5, 5), 55)]);
+}
+
+// ========== multi 模式 ==========
+
+#[memo(thread=multi, lifetime=program)]
+fn square(n: u16) -> u32 {
+    n as u32 * n as u32
+}
+
+#[test]
+fn test_snapshot_multi() {
+    let handles: Vec<_> = (1..=4).map(|n| std::thread::spawn(move || square(n))).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(square_snapshot(), vec![(1, 1), (2, 4), (3, 9), (4, 16)]);
+}
+
+// ========== scope=instance：{Fn}Cache 的方法 ==========
+
+#[memo(scope = instance)]
+fn steps(from: u8, to: u8) -> u32 {
+    if from >= to { 0 } else { 1 + steps(from + 1, to) }
+}
+
+#[test]
+fn test_snapshot_instance() {
+    let mut cache = StepsCache::new();
+    steps_in(&mut cache, 0, 2);
+    assert_eq!(cache.snapshot(), vec![((0, 2), 2), ((1, 2), 1), ((2, 2), 0)]);
+    assert_eq!(export(|out| cache.export_csv(out)), "from,to,value\n0,2,2\n1,2,1\n2,2,0\n");
+    assert_eq!(
+        export(|out| cache.print_grid(out)),
+        "from\\to | 2\n--------+--\n      0 | 2\n      1 | 1\n      2 | 0\n"
+    );
+}
@@ -0,0 +1,90 @@
+use mau::{memo, solve};
+use std::panic;
+
+// ========== 深层线性递归 ==========
+
+#[memo(stack=grow)]
+fn sum_to(n: u64) -> u64 {
+    if n == 0 { 0 } else { n + sum_to(n - 1) }
+}
+
+#[test]
+fn test_stack_grow_deep_recursion() {
+    // 测试线程默认只有 2 MiB 的栈，直接递归几十万层会栈溢出
+    assert_eq!(solve!(sum_to(300_000)), 45_000_150_000);
+    assert_eq!(sum_to_start(10), 55);
+}
+
+// ========== multi + lifetime=program：结果留在缓存中 ==========
+
+#[memo(thread=multi, lifetime=program, stack=grow, stack_mb=512)]
+fn chain(n: u32) -> u32 {
+    if n == 0 { 0 } else { (chain(n - 1) + n) % 1_000_007 }
+}
+
+#[test]
+fn test_stack_grow_multi_keeps_cache() {
+    let expected = (0..=300_000u64).sum::<u64>() % 1_000_007;
+    assert_eq!(solve!(chain(300_000)) as u64, expected);
+    // 新线程写入的是共享缓存，调用者线程可以直接命中
+    assert_eq!(chain_stats().len, 300_001);
+    assert_eq!(chain(300_000) as u64, expected);
+}
+
+// ========== 借用调用者的数据 ==========
+
+#[memo(ignore(costs), stack=grow)]
+fn min_cost(costs: &[u32], i: usize) -> u64 {
+    if i + 1 >= costs.len() {
+        return costs.get(i).copied().unwrap_or(0) as u64;
+    }
+    let step = min_cost(costs, i + 1);
+    let jump = if i + 2 < costs.len() { min_cost(costs, i + 2) } else { 0 };
+    costs[i] as u64 + step.min(jump)
+}
+
+#[test]
+fn test_stack_grow_borrowed_args() {
+    let costs: Vec<u32> = (0..200_000).map(|i| (i * 7 % 10) as u32).collect();
+    let result = solve!(min_cost(&costs, 0));
+    assert_eq!(min_cost_stats().len, 0, "清除的是新线程中的缓存");
+    assert!(result > 0);
+    assert_eq!(solve!(min_cost(&[10, 15, 20], 0)), 25);
+}
+
+// ========== panic 原样传回调用者 ==========
+
+#[memo(stack=grow)]
+fn checked_depth(n: u64) -> u64 {
+    if n == 0 {
+        panic!("到达底部");
+    }
+    1 + checked_depth(n - 1)
+}
+
+#[test]
+fn test_stack_grow_panic() {
+    let payload = panic::catch_unwind(|| checked_depth_start(500_000)).unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"到达底部"));
+}
+
+// ========== 方法 ==========
+
+struct Linked {
+    next: Vec<usize>,
+}
+
+impl Linked {
+    #[memo(receiver=ignore, stack=grow)]
+    fn length(&self, i: usize) -> usize {
+        if self.next[i] == usize::MAX { 1 } else { 1 + self.length(self.next[i]) }
+    }
+}
+
+#[test]
+fn test_stack_grow_method() {
+    let n = 300_000;
+    let next = (0..n).map(|i| if i + 1 == n { usize::MAX } else { i + 1 }).collect();
+    let linked = Linked { next };
+    assert_eq!(linked.length_start(0), n);
+}
@@ -0,0 +1,92 @@
+use mau::{memo, solve};
+
+#[memo]
+fn fib_stats_single(n: u64) -> u64 {
+    if n < 2 { n } else { fib_stats_single(n - 1) + fib_stats_single(n - 2) }
+}
+
+#[test]
+fn test_stats_hits_misses_inserts() {
+    assert_eq!(fib_stats_single_stats(), FibStatsSingleStats::default());
+
+    fib_stats_single(10);
+    let stats = fib_stats_single_stats();
+    // 0..=10 每个值未命中一次并写入缓存，其余调用全部命中
+    assert_eq!(stats.misses, 11);
+    assert_eq!(stats.inserts, 11);
+    assert_eq!(stats.hits, 8);
+    assert_eq!(stats.len, 11);
+    assert_eq!(stats.peak_len, 11);
+    assert_eq!(stats.clears, 0);
+    assert_eq!(stats.manual_clears, 0);
+
+    fib_stats_single(10);
+    assert_eq!(fib_stats_single_stats().hits, 9);
+}
+
+#[test]
+fn test_stats_survive_clear() {
+    solve!(fib_stats_single(5));
+    let stats = fib_stats_single_stats();
+    assert_eq!(stats.len, 0, "_start 之后缓存被清空");
+    assert_eq!(stats.peak_len, 6, "峰值保留");
+    assert_eq!(stats.clears, 1, "_start 的清除");
+    assert_eq!(stats.manual_clears, 0);
+    assert_eq!(stats.misses, 6);
+
+    // 手动清除单独计数，不计入 clears
+    fib_stats_single_clear();
+    let stats = fib_stats_single_stats();
+    assert_eq!((stats.clears, stats.manual_clears), (1, 1));
+    solve!(fib_stats_single(5));
+    let stats = fib_stats_single_stats();
+    assert_eq!((stats.clears, stats.manual_clears), (2, 1));
+}
+
+#[test]
+fn test_hit_rate() {
+    let stats = FibStatsSingleStats { hits: 3, misses: 1, ..Default::default() };
+    assert!((stats.hit_rate() - 0.75).abs() < 1e-9);
+    assert_eq!(FibStatsSingleStats::default().hit_rate(), 0.0);
+}
+
+// ========== Multi 模式：所有线程共享统计 ==========
+
+#[memo(thread=multi)]
+fn square_stats_multi(n: u64) -> u64 {
+    n * n
+}
+
+#[test]
+fn test_stats_multi_shared() {
+    let handles: Vec<_> = (0..4)
+        .map(|_| std::thread::spawn(|| square_stats_multi(3)))
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 9);
+    }
+    let stats = square_stats_multi_stats();
+    assert_eq!(stats.hits + stats.misses, 4);
+    assert_eq!(stats.len, 1);
+}
+
+// ========== stats=print：程序退出时打印摘要 ==========
+
+#[memo(stats=print)]
+fn square_report(n: u64) -> u64 {
+    n * n
+}
+
+#[memo(thread=multi, stats=print)]
+fn square_report_multi(n: u64) -> u64 {
+    n * n
+}
+
+#[test]
+fn test_stats_print_registers() {
+    std::thread::spawn(|| square_report(2)).join().unwrap();
+    assert_eq!(square_report(2), 4);
+    assert_eq!(square_report(2), 4);
+    assert_eq!(square_report_stats().hits, 1);
+    assert_eq!(square_report_multi(4), 16);
+}
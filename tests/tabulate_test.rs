@@ -0,0 +1,98 @@
+use mau::{memo, solve};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+use common::panic_message;
+
+// ========== 二维表：第一次调用填满整张表 ==========
+
+static PATHS_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(tabulate(i: 0..=20, j: 0..=20, order = ascending))]
+fn paths(i: usize, j: usize) -> u64 {
+    PATHS_CALLS.fetch_add(1, Ordering::SeqCst);
+    if i == 0 || j == 0 { 1 } else { paths(i - 1, j) + paths(i, j - 1) }
+}
+
+#[test]
+fn test_tabulate_grid() {
+    assert_eq!(paths(20, 20), 137846528820);
+    assert_eq!(PATHS_CALLS.load(Ordering::SeqCst), 21 * 21);
+    assert_eq!(paths_stats().len, 21 * 21);
+
+    // 之后的调用直接查表
+    assert_eq!(paths(3, 2), 10);
+    assert_eq!(paths(10, 0), 1);
+    assert_eq!(PATHS_CALLS.load(Ordering::SeqCst), 21 * 21);
+}
+
+// ========== 深层依赖：没有递归深度的限制 ==========
+
+#[memo(tabulate(n: 0..=1_000_000))]
+fn staircase(n: u32) -> u64 {
+    match n {
+        0 | 1 => 1,
+        _ => (staircase(n - 1) + staircase(n - 2)) % 1_000_000_007,
+    }
+}
+
+#[test]
+fn test_tabulate_deep() {
+    // 自顶向下需要一百万层递归，测试线程的栈会溢出
+    assert_eq!(staircase(1_000_000), 534400663);
+}
+
+// ========== order = descending + ignore：背包 ==========
+
+const MAX_ITEMS: usize = 10;
+
+#[memo(ignore(weights, values), tabulate(i: 0..=MAX_ITEMS, cap: 0..=50, order = descending))]
+fn knapsack(weights: &[usize], values: &[u64], i: usize, cap: usize) -> u64 {
+    if i >= weights.len() {
+        return 0;
+    }
+    let skip = knapsack(weights, values, i + 1, cap);
+    if weights[i] > cap {
+        return skip;
+    }
+    skip.max(values[i] + knapsack(weights, values, i + 1, cap - weights[i]))
+}
+
+#[test]
+fn test_tabulate_descending() {
+    assert_eq!(solve!(knapsack(&[10, 20, 30], &[60, 100, 120], 0, 50)), 220);
+    assert_eq!(knapsack_stats().len, 0, "lifetime=problem：_start 之后清除整张表");
+    assert_eq!(solve!(knapsack(&[1, 3, 4, 5], &[1, 4, 5, 7], 0, 7)), 9);
+}
+
+// ========== 超出范围的键照常递归计算 ==========
+
+#[memo(tabulate(n: 0..=50))]
+fn fib(n: u64) -> u64 {
+    if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+}
+
+#[test]
+fn test_tabulate_out_of_range() {
+    assert_eq!(fib(60), 1548008755920);
+    assert_eq!(fib_stats().len, 51 + 10);
+}
+
+// ========== 顺序错误：依赖尚未计算的表项时 panic ==========
+
+#[memo(tabulate(n: 0..10))]
+fn countdown(n: u32) -> u32 {
+    if n == 9 { 0 } else { countdown(n + 1) + 1 }
+}
+
+#[test]
+fn test_tabulate_wrong_order() {
+    let message = panic_message(|| countdown(5));
+    assert_eq!(
+        message,
+        "countdown: tabulate 按 order = ascending 填表时依赖了尚未计算的 countdown(1)（请检查 order 和各参数的声明顺序）"
+    );
+    // 填表标记已经清除：再次调用得到相同的错误，而不是把第一次调用也当作依赖
+    assert_eq!(panic_message(|| countdown(0)), message);
+    assert_eq!(countdown_stats().len, 0);
+}
@@ -0,0 +1,100 @@
+use mau::memo;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::sleep;
+use std::time::Duration;
+
+// ========== Single 模式 ==========
+
+static SINGLE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(lifetime=program, ttl_ms=200)]
+fn config_single(id: u32) -> u32 {
+    SINGLE_CALLS.fetch_add(1, Ordering::SeqCst);
+    id * 10
+}
+
+#[test]
+fn test_ttl_single_expires() {
+    assert_eq!(config_single_start(1), 10);
+    assert_eq!(config_single_start(1), 10);
+    assert_eq!(SINGLE_CALLS.load(Ordering::SeqCst), 1, "未过期时应该命中缓存");
+
+    sleep(Duration::from_millis(300));
+
+    assert_eq!(config_single_start(1), 10);
+    assert_eq!(SINGLE_CALLS.load(Ordering::SeqCst), 2, "过期后应该重新计算");
+
+    assert_eq!(config_single_start(1), 10);
+    assert_eq!(SINGLE_CALLS.load(Ordering::SeqCst), 2, "重新计算后应该再次命中");
+}
+
+// ========== Multi 模式 ==========
+
+static MULTI_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(thread=multi, lifetime=program, ttl_ms=200)]
+fn config_multi(id: u32) -> u32 {
+    MULTI_CALLS.fetch_add(1, Ordering::SeqCst);
+    id + 1
+}
+
+#[test]
+fn test_ttl_multi_expires() {
+    let handle = std::thread::spawn(|| config_multi_start(7));
+    assert_eq!(handle.join().unwrap(), 8);
+    assert_eq!(config_multi_start(7), 8);
+    assert_eq!(MULTI_CALLS.load(Ordering::SeqCst), 1, "多线程共享未过期的缓存");
+
+    sleep(Duration::from_millis(300));
+
+    assert_eq!(config_multi_start(7), 8);
+    assert_eq!(MULTI_CALLS.load(Ordering::SeqCst), 2, "过期后应该重新计算");
+}
+
+// ========== ttl + capacity ==========
+
+static BOUNDED_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[memo(lifetime=program, ttl_ms=200, capacity=2)]
+fn config_bounded(id: u32) -> u32 {
+    BOUNDED_CALLS.fetch_add(1, Ordering::SeqCst);
+    id
+}
+
+#[test]
+fn test_ttl_with_capacity() {
+    config_bounded_start(1);
+    config_bounded_start(2);
+    config_bounded_start(1);
+    assert_eq!(BOUNDED_CALLS.load(Ordering::SeqCst), 2);
+
+    sleep(Duration::from_millis(300));
+
+    // 过期条目被移除后，容量仍然可以正常使用
+    config_bounded_start(1);
+    config_bounded_start(3);
+    config_bounded_start(1);
+    assert_eq!(BOUNDED_CALLS.load(Ordering::SeqCst), 4);
+}
+
+// ========== 过期条目不计入条目数，插入时被清扫 ==========
+
+#[memo(lifetime=program, ttl_ms=100)]
+fn config_len(id: u32) -> u32 {
+    id * 2
+}
+
+#[test]
+fn test_ttl_len_drops_after_expiry() {
+    for id in 0..5 {
+        config_len(id);
+    }
+    assert_eq!(config_len_stats().len, 5);
+
+    sleep(Duration::from_millis(150));
+    assert_eq!(config_len_stats().len, 0, "过期条目不计入 len");
+
+    config_len(10);
+    assert_eq!(config_len_stats().len, 1);
+    assert_eq!(config_len_snapshot(), vec![(10, 20)]);
+}
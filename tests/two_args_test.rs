@@ -1,3 +1,5 @@
+#![allow(clippy::approx_constant, clippy::bool_assert_comparison, clippy::useless_vec)]
+
 use mau::{min, max, sum, and, or};
 
 #[test]